@@ -1,6 +1,8 @@
 use std::env;
 
 use askama::Template;
+use auth_service_client::client::JWT_COOKIE_NAME;
+use auth_service_client::{AuthServiceClient, AuthServiceError};
 use axum::{
     http::StatusCode,
     response::{Html, IntoResponse},
@@ -47,38 +49,25 @@ async fn root() -> impl IntoResponse {
 }
 
 async fn protected(jar: CookieJar) -> impl IntoResponse {
-    let jwt_cookie = match jar.get("jwt") {
+    let jwt_cookie = match jar.get(JWT_COOKIE_NAME) {
         Some(cookie) => cookie,
         None => {
             return StatusCode::UNAUTHORIZED.into_response();
         }
     };
 
-    let api_client = reqwest::Client::builder().build().unwrap();
-
-    let verify_token_body = serde_json::json!({
-        "token": &jwt_cookie.value(),
-    });
-
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
-    let url = format!("http://{}:3000/verify-token", auth_hostname);
-
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
-        Ok(response) => response,
-        Err(_) => {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+    let auth_client = AuthServiceClient::new(&format!("http://{}:3000", auth_hostname));
 
-    match response.status() {
-        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
-            StatusCode::UNAUTHORIZED.into_response()
-        }
-        reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
+    match auth_client.verify_token(jwt_cookie.value()).await {
+        Ok(()) => Json(ProtectedRouteResponse {
             img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
         })
         .into_response(),
-        _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        Err(AuthServiceError::Unauthorized(_) | AuthServiceError::InvalidInput(_)) => {
+            StatusCode::UNAUTHORIZED.into_response()
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 }
 
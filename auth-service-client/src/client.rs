@@ -0,0 +1,156 @@
+use reqwest::header::COOKIE;
+use reqwest::{Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::AuthServiceError;
+use crate::types::{
+    ErrorResponse, LoginRequest, LoginResponse, SignupRequest, SignupResponse, TwoFactorAuthResponse,
+    Verify2FARequest, VerifyTokenRequest,
+};
+
+// Names of the cookies auth-service hands the tokens out in
+pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const LOGIN_ATTEMPT_COOKIE_NAME: &str = "login_attempt";
+
+// Tokens issued by a successful login or 2FA verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthTokens {
+    pub auth_token: String,
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginOutcome {
+    LoggedIn(AuthTokens),
+    // The user has 2FA enabled. Finish with `verify_2fa`, passing back the login attempt cookie.
+    TwoFactorRequired {
+        response: TwoFactorAuthResponse,
+        login_attempt_cookie: String,
+    },
+}
+
+// Stateless client: tokens are returned to the caller and passed back in explicitly,
+// so a single client can serve requests for any number of users.
+#[derive(Debug, Clone)]
+pub struct AuthServiceClient {
+    base_url: String,
+    http_client: reqwest::Client,
+}
+
+impl AuthServiceClient {
+    // `base_url` is where auth-service is reached, e.g. `http://auth-service:3000`
+    pub fn new(base_url: &str) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_http_client(base_url: &str, http_client: reqwest::Client) -> Self {
+        Self { base_url: base_url.trim_end_matches('/').to_owned(), http_client }
+    }
+
+    pub async fn signup(&self, request: &SignupRequest) -> Result<SignupResponse, AuthServiceError> {
+        let response = self.post("/signup", request).await?;
+        parse_json(check_status(response).await?).await
+    }
+
+    pub async fn login(&self, request: &LoginRequest) -> Result<LoginOutcome, AuthServiceError> {
+        let response = check_status(self.post("/login", request).await?).await?;
+        if response.status() == StatusCode::PARTIAL_CONTENT {
+            let login_attempt_cookie = response
+                .cookies()
+                .find(|cookie| cookie.name() == LOGIN_ATTEMPT_COOKIE_NAME)
+                .map(|cookie| cookie.value().to_owned());
+            return match parse_json(response).await? {
+                LoginResponse::TwoFactorAuth(two_fa) => {
+                    let login_attempt_cookie = login_attempt_cookie
+                        .ok_or_else(|| unexpected(StatusCode::PARTIAL_CONTENT, "missing login attempt cookie"))?;
+                    Ok(LoginOutcome::TwoFactorRequired { response: two_fa, login_attempt_cookie })
+                }
+                LoginResponse::RegularAuth => Err(unexpected(StatusCode::PARTIAL_CONTENT, "missing login attempt")),
+            };
+        }
+        tokens_from_cookies(&response).map(LoginOutcome::LoggedIn)
+    }
+
+    // `login_attempt_cookie` is the one handed out with `LoginOutcome::TwoFactorRequired`
+    pub async fn verify_2fa(&self, request: &Verify2FARequest, login_attempt_cookie: &str) -> Result<AuthTokens, AuthServiceError> {
+        let response = self
+            .http_client
+            .post(self.url("/verify-2fa"))
+            .header(COOKIE, format!("{}={}", LOGIN_ATTEMPT_COOKIE_NAME, login_attempt_cookie))
+            .json(request)
+            .send()
+            .await?;
+        tokens_from_cookies(&check_status(response).await?)
+    }
+
+    pub async fn logout(&self, auth_token: &str) -> Result<(), AuthServiceError> {
+        let response = self
+            .http_client
+            .post(self.url("/logout"))
+            .header(COOKIE, format!("{}={}", JWT_COOKIE_NAME, auth_token))
+            .send()
+            .await?;
+        check_status(response).await.map(|_| ())
+    }
+
+    // Succeeds if the token is valid, fails with `Unauthorized` otherwise
+    pub async fn verify_token(&self, token: &str) -> Result<(), AuthServiceError> {
+        let request = VerifyTokenRequest { token: token.to_owned() };
+        let response = self.post("/verify-token", &request).await?;
+        check_status(response).await.map(|_| ())
+    }
+
+    async fn post<Body: Serialize>(&self, path: &str, body: &Body) -> Result<Response, AuthServiceError> {
+        Ok(self.http_client.post(self.url(path)).json(body).send().await?)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+// Turns error statuses into `AuthServiceError`, using the message of the `ErrorResponse` body
+async fn check_status(response: Response) -> Result<Response, AuthServiceError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let message = match response.json::<ErrorResponse>().await {
+        Ok(body) => body.error,
+        Err(_) => status.canonical_reason().unwrap_or_default().to_owned(),
+    };
+    Err(match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => AuthServiceError::InvalidInput(message),
+        StatusCode::UNAUTHORIZED => AuthServiceError::Unauthorized(message),
+        StatusCode::FORBIDDEN => AuthServiceError::Forbidden(message),
+        StatusCode::NOT_FOUND => AuthServiceError::NotFound(message),
+        StatusCode::CONFLICT => AuthServiceError::UserAlreadyExists,
+        _ => unexpected(status, &message),
+    })
+}
+
+async fn parse_json<T: DeserializeOwned>(response: Response) -> Result<T, AuthServiceError> {
+    Ok(response.json().await?)
+}
+
+fn tokens_from_cookies(response: &Response) -> Result<AuthTokens, AuthServiceError> {
+    let mut auth_token = None;
+    let mut refresh_token = None;
+    for cookie in response.cookies() {
+        match cookie.name() {
+            JWT_COOKIE_NAME => auth_token = Some(cookie.value().to_owned()),
+            REFRESH_TOKEN_COOKIE_NAME => refresh_token = Some(cookie.value().to_owned()),
+            _ => {}
+        }
+    }
+
+    let auth_token = auth_token.ok_or_else(|| unexpected(response.status(), "missing auth cookie"))?;
+    Ok(AuthTokens { auth_token, refresh_token })
+}
+
+fn unexpected(status: StatusCode, message: &str) -> AuthServiceError {
+    AuthServiceError::Unexpected { status: status.as_u16(), message: message.to_owned() }
+}
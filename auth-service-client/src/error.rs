@@ -0,0 +1,25 @@
+use thiserror::Error;
+
+// What went wrong with a call, mirroring the status codes documented in api_schema.yml
+#[derive(Debug, Error)]
+pub enum AuthServiceError {
+    // 400 and 422: the request was malformed or failed validation
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    // 401: wrong credentials, or a token that is not valid
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    // 403: the account can't be used yet, e.g. its email address isn't verified
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("user already exists")]
+    UserAlreadyExists,
+    // Any other status, including 500s
+    #[error("unexpected response ({status}): {message}")]
+    Unexpected { status: u16, message: String },
+    // auth-service could not be reached, or answered with something we couldn't read
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+}
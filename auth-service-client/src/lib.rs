@@ -0,0 +1,12 @@
+//! Typed client for the auth-service API.
+//!
+//! The request and response types in [`types`] are the ones auth-service's routes use,
+//! so both sides always agree on the schema.
+pub mod client;
+pub mod error;
+pub mod types;
+
+// re-export the items callers need
+pub use client::{AuthServiceClient, AuthTokens, LoginOutcome};
+pub use error::AuthServiceError;
+pub use types::*;
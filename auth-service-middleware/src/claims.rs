@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+// Claims carried by the auth tokens auth-service issues
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+    // Unique token id, used to ban a single token on logout
+    pub jti: String,
+    // The session the token belongs to
+    pub sid: String,
+    // The user's session epoch when the token was issued
+    pub session_epoch: i64,
+}
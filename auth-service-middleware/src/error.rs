@@ -0,0 +1,26 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AuthError {
+    #[error("missing auth token")]
+    MissingToken,
+    #[error("invalid auth token")]
+    InvalidToken,
+    // auth-service could not be reached, or the service is misconfigured
+    #[error("unexpected error")]
+    UnexpectedError,
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            AuthError::MissingToken | AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            AuthError::UnexpectedError => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
@@ -0,0 +1,26 @@
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+use crate::claims::Claims;
+use crate::error::AuthError;
+
+// The claims of the caller's auth token. Only works on routes wrapped in an `AuthLayer`;
+// anywhere else the request fails with a 500, as the route would otherwise be unprotected.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser(pub Claims);
+
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .map(AuthenticatedUser)
+            .ok_or(AuthError::UnexpectedError)
+    }
+}
@@ -0,0 +1,152 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::extract::Request;
+use axum::response::{IntoResponse, Response};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::error::AuthError;
+use crate::token::extract_token;
+use crate::validator::TokenValidator;
+
+// Rejects requests without a valid auth token with a 401. The claims of valid tokens
+// are put into the request extensions, for `AuthenticatedUser` to pick up.
+#[derive(Clone)]
+pub struct AuthLayer {
+    validator: Arc<dyn TokenValidator + Send + Sync>,
+}
+
+impl AuthLayer {
+    pub fn new<V>(validator: V) -> Self
+    where
+        V: TokenValidator + Send + Sync + 'static,
+    {
+        Self { validator: Arc::new(validator) }
+    }
+
+    // Lets several layers share one validator, and its key cache
+    pub fn from_shared(validator: Arc<dyn TokenValidator + Send + Sync>) -> Self {
+        Self { validator }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService { inner, validator: self.validator.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    validator: Arc<dyn TokenValidator + Send + Sync>,
+}
+
+impl<S> Service<Request> for AuthService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // Use the service that was driven to readiness, and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let validator = self.validator.clone();
+
+        Box::pin(async move {
+            let token = match extract_token(request.headers()) {
+                Some(token) => token,
+                None => return Ok(AuthError::MissingToken.into_response()),
+            };
+            let claims = match validator.validate(&token).await {
+                Ok(claims) => claims,
+                Err(e) => return Ok(e.into_response()),
+            };
+
+            request.extensions_mut().insert(claims);
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claims::Claims;
+    use crate::extractor::AuthenticatedUser;
+    use crate::validator::{LocalValidator, DEFAULT_AUDIENCE, DEFAULT_ISSUER};
+    use axum::body::Body;
+    use axum::http::{header, StatusCode};
+    use axum::{routing::get, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tower::ServiceExt;
+
+    const SECRET: &[u8] = b"secret";
+
+    fn test_token() -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as usize;
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            iss: DEFAULT_ISSUER.to_owned(),
+            aud: DEFAULT_AUDIENCE.to_owned(),
+            iat: now,
+            nbf: now,
+            exp: now + 600,
+            jti: "jti".to_owned(),
+            sid: "sid".to_owned(),
+            session_epoch: 0,
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/protected", get(|AuthenticatedUser(claims): AuthenticatedUser| async move { claims.sub }))
+            .layer(AuthLayer::new(LocalValidator::with_secret(SECRET)))
+    }
+
+    fn request(header: Option<(header::HeaderName, String)>) -> Request {
+        let mut builder = Request::builder().uri("/protected");
+        if let Some((name, value)) = header {
+            builder = builder.header(name, value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_layer_accepts_cookie_and_bearer_tokens() {
+        let cookie = (header::COOKIE, format!("jwt={}", test_token()));
+        let response = app().oneshot(request(Some(cookie))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bearer = (header::AUTHORIZATION, format!("Bearer {}", test_token()));
+        let response = app().oneshot(request(Some(bearer))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"test@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_layer_rejects_missing_or_invalid_tokens() {
+        let response = app().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let bearer = (header::AUTHORIZATION, "Bearer invalid".to_owned());
+        let response = app().oneshot(request(Some(bearer))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
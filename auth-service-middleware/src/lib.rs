@@ -0,0 +1,33 @@
+//! Authentication for axum services that sit behind auth-service.
+//!
+//! Wrap the protected routes in an [`AuthLayer`] and take an [`AuthenticatedUser`]
+//! in the handlers:
+//!
+//! ```no_run
+//! use auth_service_middleware::{AuthLayer, AuthenticatedUser, RemoteValidator};
+//! use axum::{routing::get, Router};
+//!
+//! async fn protected(AuthenticatedUser(claims): AuthenticatedUser) -> String {
+//!     format!("Hello {}", claims.sub)
+//! }
+//!
+//! let validator = RemoteValidator::new("http://auth-service:3000", "app-service", "<client secret>");
+//! let app: Router = Router::new()
+//!     .route("/protected", get(protected))
+//!     .layer(AuthLayer::new(validator));
+//! ```
+pub mod claims;
+pub mod error;
+pub mod extractor;
+pub mod layer;
+pub mod revocation;
+pub mod token;
+pub mod validator;
+
+// re-export the items services need
+pub use claims::Claims;
+pub use error::AuthError;
+pub use extractor::AuthenticatedUser;
+pub use layer::{AuthLayer, AuthService};
+pub use revocation::RevocationCache;
+pub use validator::{LocalValidator, RemoteValidator, TokenValidator};
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::error::AuthError;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+// auth-service sends a keep-alive every 15 seconds. A quieter stream is presumed dead.
+const READ_TIMEOUT: Duration = Duration::from_secs(45);
+
+// In-process copy of the bans auth-service holds, kept up to date through its
+// `/revocations` stream. Lets `LocalValidator` reject logged out tokens and revoked
+// sessions without a round trip per request.
+//
+// Until the first snapshot arrives, and whenever the stream drops, bans may be missing.
+// The cache then answers with `AuthError::UnexpectedError` rather than let revoked tokens through.
+#[derive(Default)]
+pub struct RevocationCache {
+    state: RwLock<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    // Banned jtis and session ids, next to the unix timestamp their ban ends at
+    revoked: HashMap<String, i64>,
+    ready: bool,
+}
+
+#[derive(Deserialize)]
+struct RevokedToken {
+    id: String,
+    exp: i64,
+}
+
+impl RevocationCache {
+    // Starts following auth-service's revocations with an API client's credentials.
+    // The stream is followed, and reconnected, for as long as the cache is alive.
+    pub fn subscribe(base_url: &str, client_id: &str, client_secret: &str) -> Arc<Self> {
+        let cache = Arc::new(Self::default());
+        let stream = RevocationStream {
+            url: format!("{}/revocations", base_url.trim_end_matches('/')),
+            client_id: client_id.to_owned(),
+            client_secret: client_secret.to_owned(),
+            http_client: reqwest::Client::new(),
+        };
+        tokio::spawn(stream.follow(Arc::downgrade(&cache)));
+        cache
+    }
+
+    // Whether the cache holds every ban, so its answers can be trusted
+    pub async fn is_ready(&self) -> bool {
+        self.state.read().await.ready
+    }
+
+    pub async fn is_revoked(&self, id: &str) -> Result<bool, AuthError> {
+        let state = self.state.read().await;
+        if !state.ready {
+            return Err(AuthError::UnexpectedError);
+        }
+        Ok(matches!(state.revoked.get(id), Some(exp) if *exp > now()))
+    }
+
+    async fn replace(&self, revoked: HashMap<String, i64>) {
+        let mut state = self.state.write().await;
+        state.revoked = revoked;
+        state.ready = true;
+    }
+
+    async fn insert(&self, token: RevokedToken) {
+        let mut state = self.state.write().await;
+        // Expired bans are dropped along the way
+        let now = now();
+        state.revoked.retain(|_, exp| *exp > now);
+        state.revoked.insert(token.id, token.exp);
+    }
+
+    async fn mark_stale(&self) {
+        self.state.write().await.ready = false;
+    }
+}
+
+struct RevocationStream {
+    url: String,
+    client_id: String,
+    client_secret: String,
+    http_client: reqwest::Client,
+}
+
+impl RevocationStream {
+    async fn follow(self, cache: Weak<RevocationCache>) {
+        loop {
+            let _ = self.read(&cache).await;
+            // Bans issued from now on until the next snapshot would be missed
+            match cache.upgrade() {
+                Some(cache) => cache.mark_stale().await,
+                None => return,
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    // Reads the stream until it ends. Every connection starts with a snapshot of the bans,
+    // closed by a `ready` event, which replaces whatever the cache held before.
+    async fn read(&self, cache: &Weak<RevocationCache>) -> Result<(), AuthError> {
+        let mut response = self
+            .http_client
+            .get(&self.url)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|_| AuthError::UnexpectedError)?;
+
+        let mut parser = EventParser::default();
+        let mut snapshot = Some(HashMap::new());
+        loop {
+            let chunk = match tokio::time::timeout(READ_TIMEOUT, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => chunk,
+                _ => return Err(AuthError::UnexpectedError),
+            };
+
+            for (event, data) in parser.feed(&chunk) {
+                let Some(cache) = cache.upgrade() else {
+                    return Ok(());
+                };
+                match event.as_str() {
+                    "revoked" => {
+                        let token: RevokedToken =
+                            serde_json::from_str(&data).map_err(|_| AuthError::UnexpectedError)?;
+                        match snapshot.as_mut() {
+                            Some(snapshot) => {
+                                snapshot.insert(token.id, token.exp);
+                            }
+                            None => cache.insert(token).await,
+                        }
+                    }
+                    "ready" => {
+                        if let Some(snapshot) = snapshot.take() {
+                            cache.replace(snapshot).await;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+// Splits a server-sent events stream into (event, data) pairs
+#[derive(Default)]
+struct EventParser {
+    buffer: String,
+}
+
+impl EventParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<(String, String)> {
+        self.buffer.push_str(&String::from_utf8_lossy(chunk).replace("\r\n", "\n"));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.find("\n\n") {
+            let block: String = self.buffer.drain(..end + 2).collect();
+            let mut event = String::new();
+            let mut data = Vec::new();
+            for line in block.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    event = value.trim_start().to_owned();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push(value.strip_prefix(' ').unwrap_or(value).to_owned());
+                }
+                // Anything else, like keep-alive comments, is ignored
+            }
+            if !event.is_empty() {
+                events.push((event, data.join("\n")));
+            }
+        }
+        events
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_event_parser_handles_split_chunks() {
+        let mut parser = EventParser::default();
+        assert!(parser.feed(b"event: revoked\ndata: {\"id\":\"jti\",").is_empty());
+
+        let events = parser.feed(b"\"exp\":1}\n\n:\n\nevent: ready\ndata: \n\n");
+        assert_eq!(events, vec![
+            ("revoked".to_owned(), "{\"id\":\"jti\",\"exp\":1}".to_owned()),
+            ("ready".to_owned(), "".to_owned()),
+        ]);
+    }
+
+    // Serves a snapshot revoking `first`, then drops the connection. Later connections
+    // get a snapshot revoking `second` and are kept open.
+    async fn serve_flaky_stream() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for connection in 0.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request).await;
+
+                let id = if connection == 0 { "first" } else { "second" };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n\
+                     event: revoked\ndata: {{\"id\":\"{}\",\"exp\":{}}}\n\nevent: ready\ndata: \n\n",
+                    id,
+                    now() + 600
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                if connection > 0 {
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        drop(socket);
+                    });
+                }
+            }
+        });
+        base_url
+    }
+
+    #[tokio::test]
+    async fn test_cache_resyncs_after_reconnect() {
+        let base_url = serve_flaky_stream().await;
+        let cache = RevocationCache::subscribe(&base_url, "client", "secret");
+
+        for _ in 0..50 {
+            if cache.is_revoked("second").await == Ok(true) {
+                // The new snapshot replaced the old one entirely
+                assert_eq!(cache.is_revoked("first").await, Ok(false));
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("cache never picked up the second snapshot");
+    }
+
+    #[tokio::test]
+    async fn test_cache_only_answers_once_ready() {
+        let cache = RevocationCache::default();
+        assert_eq!(cache.is_revoked("jti").await, Err(AuthError::UnexpectedError));
+
+        let exp = now() + 600;
+        cache.replace(HashMap::from([("jti".to_owned(), exp)])).await;
+        assert_eq!(cache.is_revoked("jti").await, Ok(true));
+        assert_eq!(cache.is_revoked("other").await, Ok(false));
+
+        cache.insert(RevokedToken { id: "expired".to_owned(), exp: now() - 1 }).await;
+        assert_eq!(cache.is_revoked("expired").await, Ok(false));
+
+        cache.mark_stale().await;
+        assert_eq!(cache.is_revoked("jti").await, Err(AuthError::UnexpectedError));
+    }
+}
@@ -0,0 +1,50 @@
+use axum::http::{header, HeaderMap};
+use axum_extra::extract::CookieJar;
+
+// Name of the cookie auth-service stores the auth token in
+pub const JWT_COOKIE_NAME: &str = "jwt";
+
+// Finds the auth token of a request. An `Authorization: Bearer` header wins over the cookie.
+pub fn extract_token(headers: &HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_owned())
+        .filter(|token| !token.is_empty());
+    if bearer.is_some() {
+        return bearer;
+    }
+
+    CookieJar::from_headers(headers)
+        .get(JWT_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_extract_token_from_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("theme=dark; jwt=cookie-token"));
+        assert_eq!(extract_token(&headers), Some("cookie-token".to_owned()));
+    }
+
+    #[test]
+    fn test_extract_token_prefers_bearer_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("jwt=cookie-token"));
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer header-token"));
+        assert_eq!(extract_token(&headers), Some("header-token".to_owned()));
+    }
+
+    #[test]
+    fn test_extract_token_missing() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic dXNlcjpwYXNz"));
+        assert_eq!(extract_token(&headers), None);
+    }
+}
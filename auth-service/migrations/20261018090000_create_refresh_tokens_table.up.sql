@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS refresh_tokens(
+                                    token TEXT NOT NULL PRIMARY KEY,
+                                    email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE,
+                                    family_id TEXT NOT NULL,
+                                    used BOOLEAN NOT NULL DEFAULT FALSE,
+                                    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id_idx ON refresh_tokens(family_id);
@@ -0,0 +1,14 @@
+-- Add up migration script here
+-- The private key is stored encrypted with SIGNING_KEY_ENCRYPTION_KEY
+CREATE TABLE IF NOT EXISTS signing_keys(
+    kid TEXT NOT NULL PRIMARY KEY,
+    algorithm TEXT NOT NULL,
+    private_key TEXT NOT NULL,
+    public_key TEXT,
+    state TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    state_changed_at TIMESTAMPTZ NOT NULL
+);
+
+-- Only one key may sign new tokens, even when several instances rotate at once
+CREATE UNIQUE INDEX IF NOT EXISTS signing_keys_single_active_idx ON signing_keys(state) WHERE state = 'active';
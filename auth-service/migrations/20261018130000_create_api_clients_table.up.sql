@@ -0,0 +1,6 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS api_clients(
+    id TEXT NOT NULL PRIMARY KEY,
+    secret_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE users DROP COLUMN IF EXISTS totp_last_used_step;
+
+ALTER TABLE users DROP COLUMN IF EXISTS totp_confirmed;
+
+ALTER TABLE users DROP COLUMN IF EXISTS totp_secret;
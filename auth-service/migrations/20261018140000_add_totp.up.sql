@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- The TOTP secret is stored encrypted with TOTP_ENCRYPTION_KEY
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret TEXT;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_confirmed BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_used_step BIGINT;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Accounts created before verification existed keep working
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ALTER COLUMN email_verified SET DEFAULT FALSE;
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use crate::domain::data_stores::{BannedTokenStore, TwoFACodeStore, UserStore};
-use crate::domain::EmailClient;
+use crate::domain::data_stores::{ApiClientStore, BannedTokenStore, EmailChangeStore, EmailVerificationTokenStore, PasswordResetTokenStore, PhoneVerificationStore, RecoveryCodeStore, RefreshTokenStore, SessionStore, SigningKeyStore, TrustedDeviceStore, TwoFACodeStore, UserStore, WebAuthnChallengeStore, WebAuthnCredentialStore};
+use crate::domain::{EmailClient, SmsClient};
+use crate::services::revocation_feed::RevocationFeed;
+use crate::utils::auth::KeyRing;
 
 // Using a type alias to improve readability!
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
@@ -9,21 +11,83 @@ pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 
 pub type TwoFaCodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+
+pub type WebAuthnCredentialStoreType = Arc<RwLock<dyn WebAuthnCredentialStore + Send + Sync>>;
+
+pub type WebAuthnChallengeStoreType = Arc<RwLock<dyn WebAuthnChallengeStore + Send + Sync>>;
+
+pub type TrustedDeviceStoreType = Arc<RwLock<dyn TrustedDeviceStore + Send + Sync>>;
+
+pub type EmailVerificationTokenStoreType = Arc<RwLock<dyn EmailVerificationTokenStore + Send + Sync>>;
+
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore + Send + Sync>>;
+
+pub type EmailChangeStoreType = Arc<RwLock<dyn EmailChangeStore + Send + Sync>>;
+
+pub type PhoneVerificationStoreType = Arc<RwLock<dyn PhoneVerificationStore + Send + Sync>>;
+
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+
+pub type SigningKeyStoreType = Arc<RwLock<dyn SigningKeyStore + Send + Sync>>;
+
+pub type ApiClientStoreType = Arc<RwLock<dyn ApiClientStore + Send + Sync>>;
+
+pub type KeyRingType = Arc<RwLock<KeyRing>>;
+
 pub type EmailClientType =  Arc<RwLock<dyn EmailClient + Send + Sync>>;
 
+pub type SmsClientType = Arc<RwLock<dyn SmsClient + Send + Sync>>;
+
+// A store's lock is only held for the calls made to that store, and released before the next
+// store is used. Where two locks have to be held at once they are taken in field order, starting
+// with `user_store`, so two requests never wait on each other.
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
+    // Bans of every instance, as they happen
+    pub revocation_feed: RevocationFeed,
     pub two_fa_code_store: TwoFaCodeStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub webauthn_credential_store: WebAuthnCredentialStoreType,
+    pub webauthn_challenge_store: WebAuthnChallengeStoreType,
+    pub trusted_device_store: TrustedDeviceStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_change_store: EmailChangeStoreType,
+    pub phone_verification_store: PhoneVerificationStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_store: SessionStoreType,
+    pub api_client_store: ApiClientStoreType,
+    pub key_ring: KeyRingType,
     pub email_client: EmailClientType,
+    pub sms_client: SmsClientType,
 }
 
 impl AppState {
+    // One argument per store, in field order
+    #[allow(clippy::too_many_arguments)]
     pub fn new(user_store: UserStoreType,
                banned_token_store: BannedTokenStoreType,
+               revocation_feed: RevocationFeed,
                two_fa_code_store: TwoFaCodeStoreType,
-               email_client: EmailClientType) -> Self {
-        Self { user_store, banned_token_store, two_fa_code_store, email_client }
+               recovery_code_store: RecoveryCodeStoreType,
+               webauthn_credential_store: WebAuthnCredentialStoreType,
+               webauthn_challenge_store: WebAuthnChallengeStoreType,
+               trusted_device_store: TrustedDeviceStoreType,
+               email_verification_token_store: EmailVerificationTokenStoreType,
+               password_reset_token_store: PasswordResetTokenStoreType,
+               email_change_store: EmailChangeStoreType,
+               phone_verification_store: PhoneVerificationStoreType,
+               refresh_token_store: RefreshTokenStoreType,
+               session_store: SessionStoreType,
+               api_client_store: ApiClientStoreType,
+               key_ring: KeyRingType,
+               email_client: EmailClientType,
+               sms_client: SmsClientType) -> Self {
+        Self { user_store, banned_token_store, revocation_feed, two_fa_code_store, recovery_code_store, webauthn_credential_store, webauthn_challenge_store, trusted_device_store, email_verification_token_store, password_reset_token_store, email_change_store, phone_verification_store, refresh_token_store, session_store, api_client_store, key_ring, email_client, sms_client }
     }
 }
\ No newline at end of file
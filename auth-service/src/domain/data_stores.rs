@@ -1,16 +1,34 @@
-use std::collections::hash_map::Entry;
+use std::fmt;
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use aws_lc_rs::hmac;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rand::distr::Alphanumeric;
 use rand::Rng;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 use crate::domain::email::Email;
-use crate::domain::error::TwoFaError;
 use crate::domain::HashedPassword;
-use crate::domain::user::User;
+use crate::domain::phone_number::PhoneNumber;
+use crate::domain::error::{SigningKeyError, TwoFaError};
+use crate::domain::totp::EncryptedTotpSecret;
+use crate::domain::user::{TwoFaChannel, User};
+use crate::utils::constants::{
+    DEFAULT_TWO_FA_MAX_FAILED_ATTEMPTS, DEFAULT_TWO_FA_MAX_PENDING_ATTEMPTS, DEFAULT_TWO_FA_MAX_RESENDS, DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS,
+};
+use crate::domain::webauthn::{CredentialId, WebAuthnCeremony, WebAuthnChallenge, WebAuthnCredential};
 
+// Tokens are banned by their `jti`, or all at once by their session id. A ban only needs to
+// last until `exp` (a unix timestamp), after which the tokens are rejected anyway.
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
 
-    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError>;
-    async fn is_token_banned(&self, token: String) -> bool;
+    async fn add_token(&mut self, id: String, exp: i64) -> Result<(), BannedTokenStoreError>;
+    async fn is_token_banned(&self, id: String) -> bool;
+    // Every ban still in force, for revocation caches to start from
+    async fn get_banned_tokens(&self) -> Result<Vec<BannedToken>, BannedTokenStoreError>;
 
 }
 #[async_trait::async_trait]
@@ -21,22 +39,225 @@ pub trait UserStore {
      async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> ;
 
      async  fn validate_user(&self, email: &Email, password: &str) -> Result<(), UserStoreError> ;
+
+     // Invalidates every token issued to the user so far
+     async fn increment_session_epoch(&mut self, email: &Email) -> Result<(), UserStoreError>;
+
+     // Starts a TOTP enrollment, replacing any unconfirmed one
+     async fn set_totp_secret(&mut self, email: &Email, secret: EncryptedTotpSecret) -> Result<(), UserStoreError>;
+
+     // Turns on 2FA with the enrolled TOTP secret, burning the step of the code that proved it
+     async fn confirm_totp(&mut self, email: &Email, step: i64) -> Result<(), UserStoreError>;
+
+     // Burns the time step of an accepted TOTP code. Fails with `TotpStepAlreadyUsed` unless
+     // `step` is later than any step used before, so two requests can't both use a code.
+     async fn use_totp_step(&mut self, email: &Email, step: i64) -> Result<(), UserStoreError>;
+
+     // Turning 2FA off also drops the authenticator app, so turning it back on starts from sent codes
+     async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError>;
+
+     async fn set_two_fa_channel(&mut self, email: &Email, channel: TwoFaChannel) -> Result<(), UserStoreError>;
+
+     // The user proved the address is theirs by following the link sent to it
+     async fn set_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+
+     async fn set_password(&mut self, email: &Email, password: HashedPassword) -> Result<(), UserStoreError>;
+
+     // Moves the user to a new address in one step, together with everything stored under the old one.
+     // Fails with `UserAlreadyExists` if the new address belongs to another user.
+     async fn change_email(&mut self, email: &Email, new_email: Email) -> Result<(), UserStoreError>;
+
+     async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
 }
 
-// This trait represents the interface all concrete 2FA code stores should implement
+// This trait represents the interface all concrete 2FA code stores should implement.
+// Codes are only ever stored hashed, and looked up by the login attempt they belong to.
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    // Starts a pending login attempt of the user. Other pending attempts of the user are kept,
+    // up to `max_pending_attempts`; beyond that the oldest ones are dropped.
     async fn add_code(
         &mut self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFaCode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError>;
+    // Drops every pending login attempt of the user
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    // The user the login attempt belongs to, and the hash of its code
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFaCode), TwoFACodeStoreError>;
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFaCodeHash), TwoFACodeStoreError>;
+    // Counts a try at the login attempt's code, before it is checked so concurrent
+    // guesses count too. Once the tries are used up the login attempt is removed and this
+    // fails with `TooManyAttempts`.
+    async fn record_attempt(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError>;
+    // Swaps the login attempt's code for a new one, keeping its tries so far.
+    // Fails with `ResendTooSoon` within the cooldown of the last code, and with
+    // `TooManyResends` once the attempt's resends are used up.
+    async fn resend_code(&mut self, login_attempt_id: &LoginAttemptId, code: TwoFaCode) -> Result<(), TwoFACodeStoreError>;
+}
+
+// How hard a login attempt's 2FA code may be tried and resent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwoFaCodeLimits {
+    // Wrong codes the login attempt survives
+    pub max_failed_attempts: u32,
+    pub max_resends: u32,
+    // Time between sending a code and resending it
+    pub resend_cooldown_seconds: u64,
+    // Login attempts a user can have going at once, e.g. on their laptop and their phone
+    pub max_pending_attempts: u32,
+}
+
+impl Default for TwoFaCodeLimits {
+    fn default() -> Self {
+        Self {
+            max_failed_attempts: DEFAULT_TWO_FA_MAX_FAILED_ATTEMPTS,
+            max_resends: DEFAULT_TWO_FA_MAX_RESENDS,
+            resend_cooldown_seconds: DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS,
+            max_pending_attempts: DEFAULT_TWO_FA_MAX_PENDING_ATTEMPTS,
+        }
+    }
+}
+
+// This trait represents the interface all concrete recovery code stores should implement.
+// Codes are only ever stored hashed.
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    // Replaces the user's codes with a new set
+    async fn set_codes(&mut self, email: &Email, codes: Vec<HashedPassword>) -> Result<(), RecoveryCodeStoreError>;
+    async fn get_codes(&self, email: &Email) -> Result<Vec<HashedPassword>, RecoveryCodeStoreError>;
+    // Fails with `CodeNotFound` once the code was removed, so each code only works once
+    async fn remove_code(&mut self, email: &Email, code: &HashedPassword) -> Result<(), RecoveryCodeStoreError>;
+}
+
+// This trait represents the interface all concrete passkey stores should implement
+#[async_trait::async_trait]
+pub trait WebAuthnCredentialStore {
+    async fn add_credential(&mut self, credential: WebAuthnCredential) -> Result<(), WebAuthnCredentialStoreError>;
+    async fn get_credential(&self, id: &CredentialId) -> Result<WebAuthnCredential, WebAuthnCredentialStoreError>;
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, WebAuthnCredentialStoreError>;
+    async fn update_sign_count(&mut self, id: &CredentialId, sign_count: u32) -> Result<(), WebAuthnCredentialStoreError>;
+    async fn remove_credentials(&mut self, email: &Email) -> Result<(), WebAuthnCredentialStoreError>;
+}
+
+// This trait represents the interface all concrete WebAuthn challenge stores should implement.
+// Challenges expire after `WEBAUTHN_TIMEOUT_SECONDS`.
+#[async_trait::async_trait]
+pub trait WebAuthnChallengeStore {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge, ceremony: WebAuthnCeremony) -> Result<(), WebAuthnChallengeStoreError>;
+    // Removes the challenge, so it can't be answered twice.
+    // Fails with `ChallengeNotFound` if it expired or was already taken.
+    async fn take_challenge(&mut self, challenge: &WebAuthnChallenge) -> Result<WebAuthnCeremony, WebAuthnChallengeStoreError>;
+}
+
+// This trait represents the interface all concrete refresh token stores should implement
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+        session_epoch: i64,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    // Flags the token as consumed. Fails with `TokenAlreadyUsed` if it was already consumed.
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError>;
+    // Removes every token that belongs to the given family.
+    async fn revoke_family(&mut self, family_id: &RefreshTokenFamilyId) -> Result<(), RefreshTokenStoreError>;
+}
+
+// This trait represents the interface all concrete session stores should implement
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError>;
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    // Records activity on the session. Fails with `SessionNotFound` once it was removed.
+    async fn touch_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError>;
+    async fn remove_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError>;
+    async fn remove_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError>;
+}
+
+// This trait represents the interface all concrete trusted device stores should implement.
+// Devices stop being trusted at `expires_at`.
+#[async_trait::async_trait]
+pub trait TrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError>;
+    async fn get_device(&self, id: &TrustedDeviceId) -> Result<TrustedDevice, TrustedDeviceStoreError>;
+    async fn get_devices(&self, email: &Email) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError>;
+    async fn remove_device(&mut self, id: &TrustedDeviceId) -> Result<(), TrustedDeviceStoreError>;
+    async fn remove_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError>;
+}
+
+// This trait represents the interface all concrete email verification token stores should implement.
+// Tokens expire `EMAIL_VERIFICATION_TOKEN_TTL_SECONDS` after they were added.
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore {
+    async fn add_token(&mut self, token: EmailVerificationToken, email: Email) -> Result<(), EmailVerificationTokenStoreError>;
+    // Removes the token, so a link can't be followed twice.
+    // Fails with `TokenNotFound` if it expired or was already taken.
+    async fn take_token(&mut self, token: &EmailVerificationToken) -> Result<Email, EmailVerificationTokenStoreError>;
+}
+
+// This trait represents the interface all concrete password reset token stores should implement.
+// Tokens expire `PASSWORD_RESET_TOKEN_TTL_SECONDS` after they were added.
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore {
+    async fn add_token(&mut self, token: PasswordResetToken, email: Email) -> Result<(), PasswordResetTokenStoreError>;
+    // Removes the token, so it can't reset the password twice.
+    // Fails with `TokenNotFound` if it expired or was already taken.
+    async fn take_token(&mut self, token: &PasswordResetToken) -> Result<Email, PasswordResetTokenStoreError>;
+}
+
+// This trait represents the interface all concrete email change stores should implement.
+// Changes expire `EMAIL_CHANGE_TTL_SECONDS` after they were added.
+#[async_trait::async_trait]
+pub trait EmailChangeStore {
+    async fn add_change(&mut self, change: EmailChange) -> Result<(), EmailChangeStoreError>;
+    // Removes the change by its confirm token, so neither of its links works afterwards.
+    // Fails with `ChangeNotFound` if it expired, or was already confirmed or cancelled.
+    async fn take_change(&mut self, confirm_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError>;
+    // Removes the change by its cancel token, with the same failures as `take_change`
+    async fn cancel_change(&mut self, cancel_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError>;
+}
+
+// This trait represents the interface all concrete phone verification stores should implement.
+// A user has at most one number waiting to be confirmed, which expires
+// `PHONE_VERIFICATION_TTL_SECONDS` after it was added.
+#[async_trait::async_trait]
+pub trait PhoneVerificationStore {
+    // Replaces the number the user was confirming before, if any
+    async fn add_verification(&mut self, email: Email, verification: PhoneVerification) -> Result<(), PhoneVerificationStoreError>;
+    // Removes the user's pending number, so its code only gets one try.
+    // Fails with `VerificationNotFound` if it expired or was already taken.
+    async fn take_verification(&mut self, email: &Email) -> Result<PhoneVerification, PhoneVerificationStoreError>;
+}
+
+// This trait represents the interface all concrete JWT signing key stores should implement.
+// The store is the source of truth for the key ring shared by every instance.
+#[async_trait::async_trait]
+pub trait SigningKeyStore {
+    // Fails with `ActiveKeyConflict` when adding an active key while another one is active.
+    async fn add_key(&mut self, key: SigningKey) -> Result<(), SigningKeyStoreError>;
+    async fn get_keys(&self) -> Result<Vec<SigningKey>, SigningKeyStoreError>;
+    // Demotes `current_kid` to verify-only and activates `new_key` in one step.
+    // Fails with `ActiveKeyConflict` if `current_kid` is no longer the active key.
+    async fn rotate_key(&mut self, current_kid: &str, new_key: SigningKey) -> Result<(), SigningKeyStoreError>;
+    // Stops accepting tokens signed with the key. The active key can't be retired.
+    async fn retire_key(&mut self, kid: &str) -> Result<(), SigningKeyStoreError>;
+}
+
+// This trait represents the interface all concrete API client stores should implement.
+// API clients are the backend services allowed to call /introspect.
+#[async_trait::async_trait]
+pub trait ApiClientStore {
+    async fn add_client(&mut self, client: ApiClient) -> Result<(), ApiClientStoreError>;
+    async fn get_client(&self, id: &ApiClientId) -> Result<ApiClient, ApiClientStoreError>;
 }
 
 #[derive(Debug, PartialEq)]
@@ -44,9 +265,46 @@ pub enum UserStoreError {
     UserAlreadyExists,
     UserNotFound,
     InvalidCredentials,
+    TotpStepAlreadyUsed,
+    UnexpectedError,
+}
+
+
+#[derive(Debug, PartialEq)]
+pub enum SessionStoreError {
+    SessionNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TrustedDeviceStoreError {
+    DeviceNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EmailVerificationTokenStoreError {
+    TokenNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PasswordResetTokenStoreError {
+    TokenNotFound,
     UnexpectedError,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum EmailChangeStoreError {
+    ChangeNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PhoneVerificationStoreError {
+    VerificationNotFound,
+    UnexpectedError,
+}
 
 #[derive(Debug, PartialEq)]
 pub enum BannedTokenStoreError {
@@ -56,13 +314,56 @@ pub enum BannedTokenStoreError {
 
 
 
+#[derive(Debug, PartialEq)]
+pub enum RecoveryCodeStoreError {
+    CodeNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WebAuthnCredentialStoreError {
+    CredentialAlreadyExists,
+    CredentialNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WebAuthnChallengeStoreError {
+    ChallengeNotFound,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RefreshTokenStoreError {
+    TokenNotFound,
+    TokenAlreadyUsed,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SigningKeyStoreError {
+    KeyNotFound,
+    ActiveKeyConflict,
+    UnexpectedError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ApiClientStoreError {
+    ClientAlreadyExists,
+    ClientNotFound,
+    UnexpectedError,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum TwoFACodeStoreError {
     LoginAttemptIdNotFound,
+    TooManyAttempts,
+    ResendTooSoon,
+    TooManyResends,
     UnexpectedError,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct LoginAttemptId(String);
 
 impl LoginAttemptId {
@@ -120,4 +421,581 @@ impl AsRef<str> for TwoFaCode {
     fn as_ref(&self) -> &str {
         self.0.as_str()
     }
-}
\ No newline at end of file
+}
+
+impl TwoFaCode {
+    // HMAC-SHA256 under a server key, so reading the store isn't enough to brute force the
+    // few possible codes. Salted with what the code is for, the login attempt or the phone
+    // number being confirmed, so the same code hashes differently every time.
+    pub fn hash(&self, salt: &impl AsRef<str>, key: &[u8; 32]) -> TwoFaCodeHash {
+        let mut message = salt.as_ref().as_bytes().to_vec();
+        message.extend_from_slice(self.0.as_bytes());
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), &message);
+        TwoFaCodeHash(tag.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
+
+// What the 2FA code stores keep in place of the code
+#[derive(Clone, Debug, PartialEq)]
+pub struct TwoFaCodeHash(String);
+
+impl TwoFaCodeHash {
+    pub fn parse(hash: String) -> Result<Self, anyhow::Error> {
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow::Error::from(TwoFaError::InvalidCode));
+        }
+        Ok(TwoFaCodeHash(hash))
+    }
+
+    // Compares in constant time, so response times don't tell how close a guess was
+    pub fn matches(&self, code: &TwoFaCode, salt: &impl AsRef<str>, key: &[u8; 32]) -> bool {
+        code.hash(salt, key).0.as_bytes().ct_eq(self.0.as_bytes()).into()
+    }
+}
+
+impl AsRef<str> for TwoFaCodeHash {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// Single-use code that stands in for a 2FA code when the second factor is lost,
+// formatted as two groups of five characters, e.g. `k3b9x-7q2md`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryCode(String);
+
+impl RecoveryCode {
+    // Codes issued when 2FA is turned on, or regenerated
+    pub const COUNT: usize = 10;
+    const GROUP_LENGTH: usize = 5;
+    const ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+    // Case and the dash are optional when typing a code in
+    pub fn parse(code: String) -> Result<Self, String> {
+        let characters: String = code.trim().to_lowercase().chars().filter(|c| *c != '-').collect();
+        let is_valid = characters.len() == 2 * Self::GROUP_LENGTH
+            && characters.bytes().all(|c| Self::ALPHABET.contains(&c));
+        if !is_valid {
+            return Err("Invalid recovery code".to_owned());
+        }
+        let (first, second) = characters.split_at(Self::GROUP_LENGTH);
+        Ok(Self(format!("{}-{}", first, second)))
+    }
+
+    // Hashed with Argon2, like passwords
+    pub async fn hash(&self) -> Result<HashedPassword, String> {
+        HashedPassword::parse(self.0.clone()).await
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::rng();
+        let mut group = || -> String {
+            (0..Self::GROUP_LENGTH)
+                .map(|_| Self::ALPHABET[rng.random_range(0..Self::ALPHABET.len())] as char)
+                .collect()
+        };
+        Self(format!("{}-{}", group(), group()))
+    }
+}
+
+impl AsRef<str> for RecoveryCode {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// Opaque, random token handed out in the refresh cookie
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefreshToken(String);
+
+impl RefreshToken {
+    const LENGTH: usize = 64;
+
+    pub fn parse(token: String) -> Result<Self, String> {
+        if token.len() != Self::LENGTH || !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("Invalid refresh token".to_owned());
+        }
+        Ok(RefreshToken(token))
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        let token = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::LENGTH)
+            .map(char::from)
+            .collect();
+        RefreshToken(token)
+    }
+}
+
+impl AsRef<str> for RefreshToken {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// Every refresh token issued by rotating another one shares the family of the original login
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefreshTokenFamilyId(String);
+
+impl RefreshTokenFamilyId {
+    pub fn parse(id: String) -> Result<Self, String> {
+        if uuid::Uuid::parse_str(&id).is_err() {
+            Err(format!("{} is not a valid uuid", id))?
+        }
+        Ok(RefreshTokenFamilyId(id))
+    }
+}
+
+impl Default for RefreshTokenFamilyId {
+    fn default() -> Self {
+        RefreshTokenFamilyId(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for RefreshTokenFamilyId {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// Identifies a login on one device. A session owns the refresh token family with the same id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionId(String);
+
+impl SessionId {
+    pub fn parse(id: String) -> Result<Self, String> {
+        if uuid::Uuid::parse_str(&id).is_err() {
+            Err(format!("{} is not a valid uuid", id))?
+        }
+        Ok(SessionId(id))
+    }
+}
+
+impl Default for SessionId {
+    fn default() -> Self {
+        SessionId(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for SessionId {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl From<SessionId> for RefreshTokenFamilyId {
+    fn from(id: SessionId) -> Self {
+        RefreshTokenFamilyId(id.0)
+    }
+}
+
+impl From<RefreshTokenFamilyId> for SessionId {
+    fn from(id: RefreshTokenFamilyId) -> Self {
+        SessionId(id.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: SessionId,
+    pub email: Email,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn new(email: Email, user_agent: Option<String>, ip_address: Option<String>) -> Self {
+        let now = Utc::now();
+        Self { id: SessionId::default(), email, user_agent, ip_address, created_at: now, last_seen: now }
+    }
+}
+
+// Identifies a browser the user chose to remember, so logins from it skip 2FA
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrustedDeviceId(String);
+
+impl TrustedDeviceId {
+    pub fn parse(id: String) -> Result<Self, String> {
+        if uuid::Uuid::parse_str(&id).is_err() {
+            Err(format!("{} is not a valid uuid", id))?
+        }
+        Ok(TrustedDeviceId(id))
+    }
+}
+
+impl Default for TrustedDeviceId {
+    fn default() -> Self {
+        TrustedDeviceId(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for TrustedDeviceId {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrustedDevice {
+    pub id: TrustedDeviceId,
+    pub email: Email,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl TrustedDevice {
+    pub fn new(email: Email, user_agent: Option<String>, ip_address: Option<String>, ttl: chrono::Duration) -> Self {
+        let now = Utc::now();
+        Self { id: TrustedDeviceId::default(), email, user_agent, ip_address, created_at: now, expires_at: now + ttl }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+// Random token in the link that verifies a user's email address
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmailVerificationToken(String);
+
+impl EmailVerificationToken {
+    const LENGTH: usize = 64;
+
+    pub fn parse(token: String) -> Result<Self, String> {
+        if token.len() != Self::LENGTH || !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("Invalid email verification token".to_owned());
+        }
+        Ok(EmailVerificationToken(token))
+    }
+}
+
+impl Default for EmailVerificationToken {
+    fn default() -> Self {
+        let token = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::LENGTH)
+            .map(char::from)
+            .collect();
+        EmailVerificationToken(token)
+    }
+}
+
+impl AsRef<str> for EmailVerificationToken {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// How long a verification link can be followed
+pub const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: u64 = 60 * 60 * 24; // 1 day
+
+// Random token emailed to a user who forgot their password
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PasswordResetToken(String);
+
+impl PasswordResetToken {
+    const LENGTH: usize = 64;
+
+    pub fn parse(token: String) -> Result<Self, String> {
+        if token.len() != Self::LENGTH || !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("Invalid password reset token".to_owned());
+        }
+        Ok(PasswordResetToken(token))
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        let token = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::LENGTH)
+            .map(char::from)
+            .collect();
+        PasswordResetToken(token)
+    }
+}
+
+impl AsRef<str> for PasswordResetToken {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// How long a password reset token can be used
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: u64 = 60 * 60; // 1 hour
+
+// Random token in the links that confirm or cancel an email change
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmailChangeToken(String);
+
+impl EmailChangeToken {
+    const LENGTH: usize = 64;
+
+    pub fn parse(token: String) -> Result<Self, String> {
+        if token.len() != Self::LENGTH || !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("Invalid email change token".to_owned());
+        }
+        Ok(EmailChangeToken(token))
+    }
+}
+
+impl Default for EmailChangeToken {
+    fn default() -> Self {
+        let token = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::LENGTH)
+            .map(char::from)
+            .collect();
+        EmailChangeToken(token)
+    }
+}
+
+impl AsRef<str> for EmailChangeToken {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// A move to a new email address. The new address confirms it, the current one can cancel it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailChange {
+    pub email: Email,
+    pub new_email: Email,
+    pub confirm_token: EmailChangeToken,
+    pub cancel_token: EmailChangeToken,
+}
+
+impl EmailChange {
+    pub fn new(email: Email, new_email: Email) -> Self {
+        Self { email, new_email, confirm_token: EmailChangeToken::default(), cancel_token: EmailChangeToken::default() }
+    }
+}
+
+// How long an email change can be confirmed or cancelled
+pub const EMAIL_CHANGE_TTL_SECONDS: u64 = 60 * 60 * 24; // 1 day
+
+// A phone number the user wants 2FA codes sent to, and the hash of the code texted to it.
+// The number is only used once the code comes back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoneVerification {
+    pub phone_number: PhoneNumber,
+    pub code_hash: TwoFaCodeHash,
+}
+
+// How long the code texted to a new number can be entered
+pub const PHONE_VERIFICATION_TTL_SECONDS: u64 = 60 * 10; // 10 minutes
+
+// A banned jti or session id, and the unix timestamp the ban ends at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BannedToken {
+    pub id: String,
+    pub exp: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub email: Email,
+    pub family_id: RefreshTokenFamilyId,
+    pub used: bool,
+    // The user's session epoch when the family was started
+    pub session_epoch: i64,
+}
+
+// Lifecycle of a signing key: it signs new tokens while active, keeps verifying
+// tokens it already signed while verify-only, and is ignored once retired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningKeyState {
+    Active,
+    VerifyOnly,
+    Retired,
+}
+
+impl SigningKeyState {
+    pub fn parse(state: &str) -> Result<Self, String> {
+        match state {
+            "active" => Ok(SigningKeyState::Active),
+            "verify_only" => Ok(SigningKeyState::VerifyOnly),
+            "retired" => Ok(SigningKeyState::Retired),
+            _ => Err(format!("{} is not a valid signing key state", state)),
+        }
+    }
+}
+
+impl AsRef<str> for SigningKeyState {
+    fn as_ref(&self) -> &str {
+        match self {
+            SigningKeyState::Active => "active",
+            SigningKeyState::VerifyOnly => "verify_only",
+            SigningKeyState::Retired => "retired",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: String,
+    // PEM encoded private key, or the base64 encoded secret for HS256, encrypted
+    pub private_key: EncryptedPrivateKey,
+    // PEM encoded public key. Shared secrets have none.
+    pub public_key: Option<String>,
+    pub state: SigningKeyState,
+    pub created_at: DateTime<Utc>,
+    pub state_changed_at: DateTime<Utc>,
+}
+
+// Keep the private key out of logs
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("state", &self.state)
+            .field("created_at", &self.created_at)
+            .field("state_changed_at", &self.state_changed_at)
+            .finish_non_exhaustive()
+    }
+}
+
+// What the signing key store keeps of a private key: base64 of the nonce followed by the
+// key sealed with AES-256-GCM under the service's signing key encryption key
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptedPrivateKey(String);
+
+impl EncryptedPrivateKey {
+    pub fn parse(private_key: String) -> Result<Self, SigningKeyError> {
+        match STANDARD.decode(&private_key) {
+            Ok(bytes) if bytes.len() > NONCE_LEN => Ok(Self(private_key)),
+            _ => Err(SigningKeyError::InvalidKey),
+        }
+    }
+
+    pub fn encrypt(private_key: &str, key: &[u8; 32]) -> Result<Self, SigningKeyError> {
+        let nonce: [u8; NONCE_LEN] = rand::rng().random();
+        let mut in_out = private_key.as_bytes().to_vec();
+        signing_key_cipher(key)?
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+            .map_err(|_| SigningKeyError::Encryption)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(in_out);
+        Ok(Self(STANDARD.encode(sealed)))
+    }
+
+    pub fn decrypt(&self, key: &[u8; 32]) -> Result<String, SigningKeyError> {
+        let sealed = STANDARD.decode(&self.0).map_err(|_| SigningKeyError::InvalidKey)?;
+        if sealed.len() <= NONCE_LEN {
+            return Err(SigningKeyError::InvalidKey);
+        }
+        let (nonce, in_out) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| SigningKeyError::InvalidKey)?;
+
+        let mut in_out = in_out.to_vec();
+        let private_key = signing_key_cipher(key)?
+            .open_in_place(nonce, Aad::empty(), &mut in_out)
+            .map_err(|_| SigningKeyError::Encryption)?;
+        String::from_utf8(private_key.to_vec()).map_err(|_| SigningKeyError::InvalidKey)
+    }
+}
+
+impl AsRef<str> for EncryptedPrivateKey {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+fn signing_key_cipher(key: &[u8; 32]) -> Result<LessSafeKey, SigningKeyError> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| SigningKeyError::Encryption)?;
+    Ok(LessSafeKey::new(key))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApiClientId(String);
+
+impl ApiClientId {
+    const MAX_LENGTH: usize = 64;
+
+    pub fn parse(id: String) -> Result<Self, String> {
+        let valid_chars = id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if id.is_empty() || id.len() > Self::MAX_LENGTH || !valid_chars {
+            return Err(format!("{} is not a valid client id", id));
+        }
+        Ok(ApiClientId(id))
+    }
+}
+
+impl AsRef<str> for ApiClientId {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// Random secret handed to the client once, when it is created
+#[derive(Clone, PartialEq)]
+pub struct ApiClientSecret(String);
+
+impl ApiClientSecret {
+    const LENGTH: usize = 48;
+
+    pub fn parse(secret: String) -> Result<Self, String> {
+        if secret.len() != Self::LENGTH || !secret.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("Invalid client secret".to_owned());
+        }
+        Ok(ApiClientSecret(secret))
+    }
+
+    // Secrets are long and random, so a fast hash is enough to keep them out of the store
+    fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.0.as_bytes()))
+    }
+}
+
+impl Default for ApiClientSecret {
+    fn default() -> Self {
+        let secret = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(Self::LENGTH)
+            .map(char::from)
+            .collect();
+        ApiClientSecret(secret)
+    }
+}
+
+impl AsRef<str> for ApiClientSecret {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Debug for ApiClientSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiClientSecret(..)")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiClient {
+    pub id: ApiClientId,
+    // Hex encoded SHA-256 of the secret
+    pub secret_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiClient {
+    pub fn new(id: ApiClientId, secret: &ApiClientSecret) -> Self {
+        Self { id, secret_hash: secret.hash(), created_at: Utc::now() }
+    }
+
+    pub fn verify_secret(&self, secret: &ApiClientSecret) -> bool {
+        secret.hash().as_bytes().ct_eq(self.secret_hash.as_bytes()).into()
+    }
+}
@@ -1,6 +1,6 @@
-use serde::Deserialize;
-use validator::{Validate, ValidateEmail, ValidationError};
-#[derive(Debug, Clone, PartialEq, Hash, Eq, Deserialize)]
+use serde::{Deserialize, Serialize};
+use validator::ValidateEmail;
+#[derive(Debug, Clone, PartialEq, Hash, Eq, Serialize, Deserialize)]
 pub struct Email (pub String);
 
 impl Email {
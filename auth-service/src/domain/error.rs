@@ -1,7 +1,6 @@
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::Json;
 use axum::response::{IntoResponse, Response};
-use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -10,19 +9,60 @@ pub enum TwoFaError {
     InvalidCode,
 }
 
+#[derive(Debug, Error, PartialEq)]
+pub enum TotpError {
+    #[error("invalid TOTP secret")]
+    InvalidSecret,
+    #[error("TOTP secret encryption failed")]
+    Encryption,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SigningKeyError {
+    #[error("invalid signing key")]
+    InvalidKey,
+    #[error("signing key encryption failed")]
+    Encryption,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum WebAuthnError {
+    #[error("invalid credential")]
+    InvalidCredential,
+    #[error("invalid client data")]
+    InvalidClientData,
+    #[error("invalid authenticator data")]
+    InvalidAuthenticatorData,
+    #[error("unsupported public key")]
+    UnsupportedKey,
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
 pub enum AuthAPIError {
     UserAlreadyExists,
     InvalidCredentials,
     UnexpectedError,
     IncorrectCredentials,
     MissingToken,
-    InvalidToken
+    InvalidToken,
+    SessionNotFound,
+    InvalidClient,
+    TotpAlreadyEnabled,
+    TotpNotEnrolled,
+    TwoFaAlreadyEnabled,
+    TwoFaNotEnabled,
+    PasskeyAlreadyRegistered,
+    PasskeyNotFound,
+    TrustedDeviceNotFound,
+    TooManyAttempts,
+    ResendTooSoon,
+    EmailNotVerified,
+    PhoneVerificationNotFound,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ErrorResponse {
-    pub error: String,
-}
+// Shared with auth-service-client
+pub use auth_service_client::types::ErrorResponse;
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
@@ -32,6 +72,23 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Invalid credentials"),
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid credentials"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::TotpNotEnrolled => (StatusCode::NOT_FOUND, "TOTP enrollment not found"),
+            AuthAPIError::TwoFaAlreadyEnabled => (StatusCode::CONFLICT, "2FA already enabled"),
+            AuthAPIError::TwoFaNotEnabled => (StatusCode::CONFLICT, "2FA not enabled"),
+            AuthAPIError::PasskeyAlreadyRegistered => (StatusCode::CONFLICT, "Passkey already registered"),
+            AuthAPIError::PasskeyNotFound => (StatusCode::NOT_FOUND, "Passkey not found"),
+            AuthAPIError::TrustedDeviceNotFound => (StatusCode::NOT_FOUND, "Trusted device not found"),
+            AuthAPIError::TooManyAttempts => (StatusCode::TOO_MANY_REQUESTS, "Too many attempts"),
+            AuthAPIError::ResendTooSoon => (StatusCode::TOO_MANY_REQUESTS, "Please wait before requesting another code"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email address not verified"),
+            AuthAPIError::PhoneVerificationNotFound => (StatusCode::NOT_FOUND, "No phone number waiting to be confirmed"),
+            AuthAPIError::InvalidClient => {
+                // Tell the client how to authenticate, as RFC 6749 asks for
+                let body = Json(ErrorResponse { error: "Invalid client".to_string() });
+                return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Basic")], body).into_response();
+            }
             AuthAPIError::UnexpectedError => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
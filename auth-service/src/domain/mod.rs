@@ -4,6 +4,11 @@ pub mod error;
 pub mod data_stores;
 pub mod email;
 pub mod password;
+pub mod phone_number;
+pub mod totp;
+pub mod webauthn;
 pub use password::*;
 pub mod email_client;
-pub use email_client::*;
\ No newline at end of file
+pub use email_client::*;
+pub mod sms_client;
+pub use sms_client::*;
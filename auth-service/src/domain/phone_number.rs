@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+// Phone number in E.164 format, e.g. `+14155550123`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PhoneNumber(String);
+
+impl PhoneNumber {
+    // Spaces, dashes, dots and parentheses are dropped, so numbers can be entered the way
+    // they are usually written
+    pub fn parse(number: String) -> Result<Self, String> {
+        let normalized: String = number
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect();
+
+        // A country code can't start with 0, and numbers have at most 15 digits
+        let digits = normalized.strip_prefix('+').unwrap_or_default();
+        let is_valid = digits.len() >= 8
+            && digits.len() <= 15
+            && digits.chars().all(|c| c.is_ascii_digit())
+            && !digits.starts_with('0');
+        if is_valid {
+            Ok(Self(normalized))
+        } else {
+            Err(format!("Invalid phone number: {}", number))
+        }
+    }
+}
+
+impl AsRef<str> for PhoneNumber {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_phone_number() {
+        assert_eq!(PhoneNumber::parse("+14155550123".to_owned()).unwrap().as_ref(), "+14155550123");
+        assert_eq!(PhoneNumber::parse("+44 (20) 7946-0958".to_owned()).unwrap().as_ref(), "+442079460958");
+
+        // No country code
+        assert!(PhoneNumber::parse("4155550123".to_owned()).is_err());
+        assert!(PhoneNumber::parse("+04155550123".to_owned()).is_err());
+        // Too short, too long, not a number
+        assert!(PhoneNumber::parse("+1415".to_owned()).is_err());
+        assert!(PhoneNumber::parse("+1415555012345678".to_owned()).is_err());
+        assert!(PhoneNumber::parse("+1415555abcd".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,7 @@
+use crate::domain::phone_number::PhoneNumber;
+
+// This trait represents the interface all concrete SMS clients should implement
+#[async_trait::async_trait]
+pub trait SmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String>;
+}
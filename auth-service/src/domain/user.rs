@@ -1,17 +1,37 @@
 
 use crate::domain::email::Email;
 use crate::domain::HashedPassword;
+use crate::domain::phone_number::PhoneNumber;
+use crate::domain::totp::Totp;
+
+// Where emailed-style 2FA codes are delivered. Users with an authenticator app don't get codes sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TwoFaChannel {
+    #[default]
+    Email,
+    Sms(PhoneNumber),
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
     pub email: Email,
     pub password: HashedPassword,
     pub requires_2fa: bool,
+    // Bumped on "log out everywhere". Tokens carrying an older epoch are rejected.
+    pub session_epoch: i64,
+    pub totp: Option<Totp>,
+    pub two_fa_channel: TwoFaChannel,
+    // Whether the user followed the link sent to their address. Unverified users can't log in.
+    pub email_verified: bool,
 }
 
 impl User {
     pub fn new(email: Email, password: HashedPassword, requires_2fa: bool) -> Self {
-        Self { email, password, requires_2fa }
+        Self { email, password, requires_2fa, session_epoch: 0, totp: None, two_fa_channel: TwoFaChannel::Email, email_verified: false }
     }
-}
 
+    // Whether 2FA codes come from an authenticator app rather than email
+    pub fn has_totp(&self) -> bool {
+        self.totp.as_ref().is_some_and(|totp| totp.confirmed)
+    }
+}
@@ -0,0 +1,331 @@
+use std::io::Cursor;
+
+use aws_lc_rs::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ciborium::value::Value;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::domain::data_stores::LoginAttemptId;
+use crate::domain::email::Email;
+use crate::domain::error::WebAuthnError;
+
+// Only ES256 keys are accepted: every platform authenticator supports them
+pub const COSE_ALGORITHM_ES256: i64 = -7;
+// How long the user has to complete a ceremony
+pub const WEBAUTHN_TIMEOUT_SECONDS: u64 = 300;
+
+// Authenticator data flags, WebAuthn section 6.1
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// A passkey registered by a user
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebAuthnCredential {
+    pub id: CredentialId,
+    pub email: Email,
+    // Uncompressed SEC1 P-256 point
+    pub public_key: Vec<u8>,
+    // Signature counter last reported by the authenticator. Used to spot cloned authenticators.
+    pub sign_count: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebAuthnCredential {
+    pub fn new(id: CredentialId, email: Email, public_key: Vec<u8>, sign_count: u32) -> Self {
+        Self { id, email, public_key, sign_count, created_at: Utc::now() }
+    }
+
+    // Checks the signature over the authenticator data and the client data hash
+    pub fn verify_signature(&self, authenticator_data: &[u8], client_data_json: &[u8], signature: &[u8]) -> Result<(), WebAuthnError> {
+        let mut signed = authenticator_data.to_vec();
+        signed.extend_from_slice(&Sha256::digest(client_data_json));
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &self.public_key)
+            .verify(&signed, signature)
+            .map_err(|_| WebAuthnError::InvalidSignature)
+    }
+
+    // Authenticators that count signatures must report a higher count every time.
+    // Those that don't always report 0.
+    pub fn is_sign_count_valid(&self, sign_count: u32) -> bool {
+        (sign_count == 0 && self.sign_count == 0) || sign_count > self.sign_count
+    }
+}
+
+// Base64url credential id, as authenticators report it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CredentialId(String);
+
+impl CredentialId {
+    pub fn parse(id: String) -> Result<Self, WebAuthnError> {
+        match URL_SAFE_NO_PAD.decode(&id) {
+            // WebAuthn caps credential ids at 1023 bytes
+            Ok(bytes) if !bytes.is_empty() && bytes.len() <= 1023 => Ok(Self(id)),
+            _ => Err(WebAuthnError::InvalidCredential),
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for CredentialId {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// Random value the authenticator signs over. Each one is only good for a single ceremony.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebAuthnChallenge(String);
+
+impl WebAuthnChallenge {
+    pub fn parse(challenge: String) -> Result<Self, WebAuthnError> {
+        match URL_SAFE_NO_PAD.decode(&challenge) {
+            Ok(bytes) if bytes.len() >= 16 => Ok(Self(challenge)),
+            _ => Err(WebAuthnError::InvalidClientData),
+        }
+    }
+}
+
+impl Default for WebAuthnChallenge {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        Self(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for WebAuthnChallenge {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// What a challenge was handed out for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "ceremony", rename_all = "snake_case")]
+pub enum WebAuthnCeremony {
+    Registration { email: Email },
+    // Second factor of a password login that asked for 2FA
+    SecondFactor { email: Email, login_attempt_id: LoginAttemptId },
+    // Passwordless login: the passkey tells who the user is
+    Login,
+}
+
+// The parts of clientDataJSON that are checked
+#[derive(Debug, Deserialize)]
+pub struct ClientData {
+    #[serde(rename = "type")]
+    pub ceremony_type: String,
+    pub challenge: String,
+    pub origin: String,
+}
+
+impl ClientData {
+    // `ceremony_type` is `webauthn.create` for registrations and `webauthn.get` for logins
+    pub fn parse(client_data_json: &[u8], ceremony_type: &str, origin: &str) -> Result<Self, WebAuthnError> {
+        let client_data: Self = serde_json::from_slice(client_data_json).map_err(|_| WebAuthnError::InvalidClientData)?;
+        if client_data.ceremony_type != ceremony_type || client_data.origin != origin {
+            return Err(WebAuthnError::InvalidClientData);
+        }
+        Ok(client_data)
+    }
+
+    pub fn challenge(&self) -> Result<WebAuthnChallenge, WebAuthnError> {
+        WebAuthnChallenge::parse(self.challenge.clone())
+    }
+}
+
+// Credential created during registration
+#[derive(Debug, PartialEq)]
+pub struct AttestedCredential {
+    pub id: CredentialId,
+    pub public_key: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AuthenticatorData {
+    flags: u8,
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredential>,
+}
+
+impl AuthenticatorData {
+    // Parses authenticator data, WebAuthn section 6.1, for the relying party `rp_id`.
+    // The user must have been present.
+    pub fn parse(bytes: &[u8], rp_id: &str) -> Result<Self, WebAuthnError> {
+        if bytes.len() < 37 {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+        let (rp_id_hash, rest) = bytes.split_at(32);
+        if rp_id_hash != Sha256::digest(rp_id.as_bytes()).as_slice() {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+        let flags = rest[0];
+        if flags & FLAG_USER_PRESENT == 0 {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+        let sign_count = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]);
+
+        let attested_credential = match flags & FLAG_ATTESTED_CREDENTIAL_DATA {
+            0 => None,
+            _ => Some(parse_attested_credential(&rest[5..])?),
+        };
+        Ok(Self { flags, sign_count, attested_credential })
+    }
+
+    // Whether the authenticator checked who the user is, e.g. with a PIN or biometrics
+    pub fn user_verified(&self) -> bool {
+        self.flags & FLAG_USER_VERIFIED != 0
+    }
+}
+
+// Attestation statements are not checked: registrations ask for `none` attestation,
+// so only the authenticator data inside is of interest
+pub fn parse_attestation_object(bytes: &[u8], rp_id: &str) -> Result<AuthenticatorData, WebAuthnError> {
+    let attestation: Value = ciborium::from_reader(bytes).map_err(|_| WebAuthnError::InvalidAuthenticatorData)?;
+    let auth_data = map_entry(&attestation, Value::Text("authData".to_owned()))
+        .and_then(Value::as_bytes)
+        .ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+    AuthenticatorData::parse(auth_data, rp_id)
+}
+
+fn parse_attested_credential(bytes: &[u8]) -> Result<AttestedCredential, WebAuthnError> {
+    // 16 bytes AAGUID, then the credential id prefixed with its length
+    if bytes.len() < 18 {
+        return Err(WebAuthnError::InvalidAuthenticatorData);
+    }
+    let id_length = u16::from_be_bytes([bytes[16], bytes[17]]) as usize;
+    let rest = &bytes[18..];
+    if rest.len() < id_length {
+        return Err(WebAuthnError::InvalidAuthenticatorData);
+    }
+    let (id, public_key) = rest.split_at(id_length);
+
+    // The COSE key may be followed by extensions, so only read one CBOR item
+    let cose_key: Value = ciborium::from_reader(Cursor::new(public_key)).map_err(|_| WebAuthnError::InvalidAuthenticatorData)?;
+    Ok(AttestedCredential { id: CredentialId::from_bytes(id), public_key: parse_cose_key(&cose_key)? })
+}
+
+// Turns an EC2 P-256 COSE key (RFC 9053) into an uncompressed SEC1 point
+fn parse_cose_key(key: &Value) -> Result<Vec<u8>, WebAuthnError> {
+    let integer = |label: i64| map_entry(key, Value::Integer(label.into())).and_then(Value::as_integer).map(i128::from);
+    let coordinate = |label: i64| {
+        map_entry(key, Value::Integer(label.into()))
+            .and_then(Value::as_bytes)
+            .filter(|bytes| bytes.len() == 32)
+    };
+
+    // kty 2 is EC2, alg -7 is ES256, crv 1 is P-256
+    if integer(1) != Some(2) || integer(3) != Some(COSE_ALGORITHM_ES256.into()) || integer(-1) != Some(1) {
+        return Err(WebAuthnError::UnsupportedKey);
+    }
+    let (Some(x), Some(y)) = (coordinate(-2), coordinate(-3)) else {
+        return Err(WebAuthnError::UnsupportedKey);
+    };
+
+    let mut point = vec![0x04];
+    point.extend_from_slice(x);
+    point.extend_from_slice(y);
+    Ok(point)
+}
+
+fn map_entry(map: &Value, key: Value) -> Option<&Value> {
+    map.as_map()?.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+}
+
+// Authenticators keep one passkey per user handle. Deriving it from the email keeps it stable
+// across registrations without revealing the address.
+pub fn user_handle(email: &Email) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(email.as_ref().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RP_ID: &str = "localhost";
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32, attested: Option<(&[u8], Value)>) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        if let Some((id, key)) = attested {
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&(id.len() as u16).to_be_bytes());
+            data.extend_from_slice(id);
+            ciborium::into_writer(&key, &mut data).unwrap();
+        }
+        data
+    }
+
+    fn cose_key(alg: i64) -> Value {
+        Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer(alg.into())),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (Value::Integer((-2).into()), Value::Bytes(vec![1; 32])),
+            (Value::Integer((-3).into()), Value::Bytes(vec![2; 32])),
+        ])
+    }
+
+    #[test]
+    fn test_parse_attested_credential() {
+        let data = authenticator_data(RP_ID, FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA, 0, Some((b"cred", cose_key(-7))));
+        let parsed = AuthenticatorData::parse(&data, RP_ID).unwrap();
+        assert!(!parsed.user_verified());
+
+        let credential = parsed.attested_credential.unwrap();
+        assert_eq!(credential.id, CredentialId::from_bytes(b"cred"));
+        assert_eq!(credential.public_key.len(), 65);
+        assert_eq!(credential.public_key[0], 0x04);
+    }
+
+    #[test]
+    fn test_reject_other_rp_and_absent_user() {
+        let data = authenticator_data("evil.example", FLAG_USER_PRESENT, 1, None);
+        assert_eq!(AuthenticatorData::parse(&data, RP_ID), Err(WebAuthnError::InvalidAuthenticatorData));
+
+        let data = authenticator_data(RP_ID, FLAG_USER_VERIFIED, 1, None);
+        assert_eq!(AuthenticatorData::parse(&data, RP_ID), Err(WebAuthnError::InvalidAuthenticatorData));
+    }
+
+    #[test]
+    fn test_reject_unsupported_keys() {
+        // RS256
+        let data = authenticator_data(RP_ID, FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA, 0, Some((b"cred", cose_key(-257))));
+        assert_eq!(AuthenticatorData::parse(&data, RP_ID), Err(WebAuthnError::UnsupportedKey));
+    }
+
+    #[test]
+    fn test_client_data_must_match_ceremony_and_origin() {
+        let challenge = WebAuthnChallenge::default();
+        let json = format!(
+            r#"{{"type":"webauthn.get","challenge":"{}","origin":"http://localhost:3000"}}"#,
+            challenge.as_ref()
+        );
+
+        let client_data = ClientData::parse(json.as_bytes(), "webauthn.get", "http://localhost:3000").unwrap();
+        assert_eq!(client_data.challenge(), Ok(challenge));
+        assert!(ClientData::parse(json.as_bytes(), "webauthn.create", "http://localhost:3000").is_err());
+        assert!(ClientData::parse(json.as_bytes(), "webauthn.get", "https://evil.example").is_err());
+    }
+
+    #[test]
+    fn test_sign_count() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let mut credential = WebAuthnCredential::new(CredentialId::from_bytes(b"cred"), email, vec![], 0);
+        assert!(credential.is_sign_count_valid(0));
+        assert!(credential.is_sign_count_valid(1));
+
+        credential.sign_count = 5;
+        assert!(credential.is_sign_count_valid(6));
+        assert!(!credential.is_sign_count_valid(5));
+        assert!(!credential.is_sign_count_valid(0));
+    }
+}
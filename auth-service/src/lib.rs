@@ -1,9 +1,10 @@
 use std::error::Error;
-use std::sync::Arc;
-use axum::http::{Method, StatusCode};
-use axum::response::IntoResponse;
+use std::net::SocketAddr;
+use axum::http::Method;
 use axum::Router;
-use axum::routing::{post};
+use axum::extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo};
+use axum::middleware::AddExtension;
+use axum::routing::{delete, get, post};
 use axum::serve::Serve;
 use redis::{Client, RedisResult};
 use sqlx::PgPool;
@@ -19,7 +20,7 @@ pub mod utils;
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Serve<TcpListener, IntoMakeServiceWithConnectInfo<Router, SocketAddr>, AddExtension<Router, ConnectInfo<SocketAddr>>>,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -35,8 +36,8 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
             // Allow cookies to be included in requests
             .allow_credentials(true)
             .allow_origin(allowed_origins);
@@ -46,10 +47,41 @@ impl Application {
         let router =     Router::new()
             .fallback_service(assets_dir)
             .route("/signup", post(self::routes::signup))
+            .route("/verify-email", get(self::routes::verify_email))
+            .route("/resend-verification", post(self::routes::resend_verification))
+            .route("/forgot-password", post(self::routes::forgot_password))
+            .route("/reset-password", post(self::routes::reset_password))
             .route("/login", post(self::routes::login))
+            .route("/change-password", post(self::routes::change_password))
+            .route("/change-email", post(self::routes::change_email))
+            .route("/confirm-email-change", get(self::routes::confirm_email_change))
+            .route("/cancel-email-change", get(self::routes::cancel_email_change))
+            .route("/me", delete(self::routes::delete_account))
+            .route("/me/export", get(self::routes::export_account))
             .route("/verify-2fa", post(self::routes::verify_2fa))
+            .route("/resend-2fa", post(self::routes::resend_2fa))
+            .route("/enroll-totp", post(self::routes::enroll_totp))
+            .route("/confirm-totp", post(self::routes::confirm_totp))
+            .route("/enable-2fa", post(self::routes::enable_2fa))
+            .route("/disable-2fa", post(self::routes::disable_2fa))
+            .route("/2fa-channel", post(self::routes::set_two_fa_channel))
+            .route("/2fa-channel/confirm", post(self::routes::confirm_two_fa_channel))
+            .route("/recovery-codes", post(self::routes::regenerate_recovery_codes))
+            .route("/passkeys", post(self::routes::register_passkey))
+            .route("/passkeys/registration-options", post(self::routes::passkey_registration_options))
+            .route("/passkeys/login-options", post(self::routes::passkey_login_options))
+            .route("/passkeys/login", post(self::routes::passkey_login))
             .route("/logout", post(self::routes::logout))
+            .route("/logout-all", post(self::routes::logout_all))
+            .route("/refresh", post(self::routes::refresh))
+            .route("/sessions", get(self::routes::get_sessions))
+            .route("/sessions/{id}", delete(self::routes::delete_session))
+            .route("/trusted-devices", get(self::routes::get_trusted_devices))
+            .route("/trusted-devices/{id}", delete(self::routes::delete_trusted_device))
             .route("/verify-token", post(self::routes::verify_token))
+            .route("/introspect", post(self::routes::introspect))
+            .route("/revocations", get(self::routes::revocations))
+            .route("/.well-known/jwks.json", get(self::routes::jwks))
             .with_state(app_state)
             .layer(cors);
 
@@ -61,7 +93,8 @@ impl Application {
         let listener = TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
 
-        let server = axum::serve(listener, router);
+        // Connect info gives the client IP recorded on sessions
+        let server = axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>());
         // Create a new Application instance and return it
         Ok(Application { server, address })
     }
@@ -72,23 +105,6 @@ impl Application {
     }
 }
 
-
-async fn login() -> impl IntoResponse {
-    StatusCode::OK.into_response()
-}
-
-async fn verify_2fa() -> impl IntoResponse {
-    StatusCode::OK.into_response()
-}
-
-async fn logout() -> impl IntoResponse {
-    StatusCode::OK.into_response()
-}
-
-async fn verify_token() -> impl IntoResponse {
-    StatusCode::OK.into_response()
-}
-
 pub async fn get_postgres_pool(url: &str) -> Result<PgPool, sqlx::Error> {
     // Create a new PostgreSQL connection pool
     PgPoolOptions::new().max_connections(5).connect(url).await
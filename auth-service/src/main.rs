@@ -1,22 +1,100 @@
 use std::sync::{Arc};
 use sqlx::PgPool;
 use tokio::sync::RwLock;
-use auth_service::{app_state::AppState, services::data_stores::postgres_user_store, services::data_stores::redis_banned_token_store::RedisBannedTokenStore, utils::constants::prod, Application, get_postgres_pool, get_redis_client};
+use auth_service::{app_state::AppState, services::data_stores::redis_banned_token_store::RedisBannedTokenStore, utils::constants::prod, Application, get_postgres_pool, get_redis_client};
 use auth_service::services::data_stores::redis_two_fa_code_store::RedisTwoFACodeStore;
 use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
+use auth_service::services::data_stores::postgres_refresh_token_store::PostgresRefreshTokenStore;
+use auth_service::services::data_stores::postgres_session_store::PostgresSessionStore;
+use auth_service::services::data_stores::postgres_recovery_code_store::PostgresRecoveryCodeStore;
+use auth_service::services::data_stores::postgres_webauthn_credential_store::PostgresWebAuthnCredentialStore;
+use auth_service::services::data_stores::redis_webauthn_challenge_store::RedisWebAuthnChallengeStore;
+use auth_service::services::data_stores::redis_trusted_device_store::RedisTrustedDeviceStore;
+use auth_service::services::data_stores::redis_email_verification_token_store::RedisEmailVerificationTokenStore;
+use auth_service::services::data_stores::redis_password_reset_token_store::RedisPasswordResetTokenStore;
+use auth_service::services::data_stores::redis_email_change_store::RedisEmailChangeStore;
+use auth_service::services::data_stores::redis_phone_verification_store::RedisPhoneVerificationStore;
+use auth_service::services::data_stores::postgres_signing_key_store::PostgresSigningKeyStore;
+use auth_service::services::data_stores::postgres_api_client_store::PostgresApiClientStore;
+use auth_service::domain::data_stores::{ApiClient, ApiClientId, ApiClientSecret, ApiClientStore};
 use auth_service::services::mock_email_client::MockEmailClient;
-use auth_service::utils::constants::{DATABASE_URL, REDIS_HOST_NAME};
+use auth_service::services::http_sms_client::HttpSmsClient;
+use auth_service::services::mock_sms_client::MockSmsClient;
+use auth_service::app_state::SmsClientType;
+use auth_service::services::revocation_feed::{run_redis_revocation_listener, RevocationFeed};
+use auth_service::utils::auth::{run_key_rotation, KeyRing};
+use auth_service::utils::constants::{DATABASE_URL, REDIS_HOST_NAME, SMS_PROVIDER_AUTH_TOKEN, SMS_PROVIDER_BASE_URL, SMS_SENDER, TWO_FA_CODE_LIMITS};
 
 #[tokio::main]
 async fn main() {
     let pg_pool = configure_postgresql().await;
-    let user_store =  Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+    let signing_key_store = Arc::new(RwLock::new(PostgresSigningKeyStore::new(pg_pool.clone())));
+    let api_client_store = Arc::new(RwLock::new(PostgresApiClientStore::new(pg_pool.clone())));
+    let mut key_ring = KeyRing::load(signing_key_store)
+        .await
+        .expect("Failed to load signing keys");
+
+    // Admin actions. Running instances pick up key ring changes on their next sync.
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("rotate-signing-key") => {
+            let kid = key_ring.rotate().await.expect("Failed to rotate signing key");
+            println!("New active signing key: {}", kid);
+            return;
+        }
+        Some("retire-signing-key") => {
+            let kid = args.get(2).expect("Usage: auth-service retire-signing-key <kid>");
+            key_ring.retire(kid).await.expect("Failed to retire signing key");
+            println!("Retired signing key: {}", kid);
+            return;
+        }
+        Some("create-api-client") => {
+            let id = args.get(2).expect("Usage: auth-service create-api-client <client_id>");
+            let id = ApiClientId::parse(id.to_owned()).expect("Invalid client id");
+            // The secret is only stored hashed, so this is the one chance to see it
+            let secret = ApiClientSecret::default();
+            api_client_store
+                .write()
+                .await
+                .add_client(ApiClient::new(id.clone(), &secret))
+                .await
+                .expect("Failed to create API client");
+            println!("Created API client {} with secret: {}", id.as_ref(), secret.as_ref());
+            return;
+        }
+        Some(command) => panic!("Unknown command: {}", command),
+        None => {}
+    }
+
+    let key_ring = Arc::new(RwLock::new(key_ring));
+    tokio::spawn(run_key_rotation(key_ring.clone()));
+
+    let user_store =  Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+    let refresh_token_store = Arc::new(RwLock::new(PostgresRefreshTokenStore::new(pg_pool.clone())));
+    let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+    let recovery_code_store = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+    let webauthn_credential_store = Arc::new(RwLock::new(PostgresWebAuthnCredentialStore::new(pg_pool)));
     let redis_conn = Arc::new(RwLock::new(configure_redis()));
     let banned_token_store =  Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn)));
+    let revocation_feed = RevocationFeed::new();
+    tokio::spawn(run_redis_revocation_listener(redis_client(), revocation_feed.clone()));
     let redis_conn_2fa = Arc::new(RwLock::new(configure_redis()));
-    let two_fa_token_store =  Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn_2fa)));
+    let two_fa_token_store =  Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn_2fa, *TWO_FA_CODE_LIMITS)));
+    let redis_conn_webauthn = Arc::new(RwLock::new(configure_redis()));
+    let webauthn_challenge_store = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(redis_conn_webauthn)));
+    let redis_conn_trusted_devices = Arc::new(RwLock::new(configure_redis()));
+    let trusted_device_store = Arc::new(RwLock::new(RedisTrustedDeviceStore::new(redis_conn_trusted_devices)));
+    let redis_conn_email_verification = Arc::new(RwLock::new(configure_redis()));
+    let email_verification_token_store = Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(redis_conn_email_verification)));
+    let redis_conn_password_reset = Arc::new(RwLock::new(configure_redis()));
+    let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn_password_reset)));
+    let redis_conn_email_change = Arc::new(RwLock::new(configure_redis()));
+    let email_change_store = Arc::new(RwLock::new(RedisEmailChangeStore::new(redis_conn_email_change)));
+    let redis_conn_phone_verification = Arc::new(RwLock::new(configure_redis()));
+    let phone_verification_store = Arc::new(RwLock::new(RedisPhoneVerificationStore::new(redis_conn_phone_verification)));
     let email_client =  Arc::new(RwLock::new(MockEmailClient{}));
-    let app_state = AppState::new(user_store, banned_token_store, two_fa_token_store, email_client);
+    let sms_client = configure_sms_client();
+    let app_state = AppState::new(user_store, banned_token_store, revocation_feed, two_fa_token_store, recovery_code_store, webauthn_credential_store, webauthn_challenge_store, trusted_device_store, email_verification_token_store, password_reset_token_store, email_change_store, phone_verification_store, refresh_token_store, session_store, api_client_store, key_ring, email_client, sms_client);
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
@@ -24,13 +102,30 @@ async fn main() {
     app.run().await.expect("Failed to run app");
 }
 
+fn redis_client() -> redis::Client {
+    get_redis_client(REDIS_HOST_NAME.to_owned()).expect("Failed to get Redis client")
+}
+
 fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
+    redis_client()
         .get_connection()
         .expect("Failed to get Redis connection")
 }
 
+fn configure_sms_client() -> SmsClientType {
+    let Some(base_url) = SMS_PROVIDER_BASE_URL.as_ref() else {
+        return Arc::new(RwLock::new(MockSmsClient));
+    };
+    let base_url = reqwest::Url::parse(base_url).expect("SMS_PROVIDER_BASE_URL must be a URL");
+    let sender = SMS_SENDER.clone().expect("SMS_SENDER must be set to use an SMS provider");
+    let auth_token = SMS_PROVIDER_AUTH_TOKEN.clone().expect("SMS_PROVIDER_AUTH_TOKEN must be set to use an SMS provider");
+    let http_client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .expect("Failed to build HTTP client");
+    Arc::new(RwLock::new(HttpSmsClient::new(base_url, sender, auth_token, http_client)))
+}
+
 async fn configure_postgresql() -> PgPool {
     // Create a new database connection pool
     let pg_pool = get_postgres_pool(&DATABASE_URL)
@@ -0,0 +1,177 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::PhoneVerificationStoreError;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::webauthn::WebAuthnCredential;
+use crate::utils::auth::{authenticate_cookie, revoke_all_sessions};
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME, REFRESH_TOKEN_COOKIE_PATH};
+use crate::utils::notifications::notify_user;
+use super::sessions::SessionResponse;
+use super::trusted_devices::TrustedDeviceResponse;
+use super::two_fa_channel::TwoFaChannelResponse;
+
+// Delete the logged in user's account and everything stored about them. A stolen session
+// isn't enough for that: the user confirms with their password.
+pub async fn delete_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    if state.user_store.read().await.validate_user(&email, &request.password).await.is_err() {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    // The user goes last, so a failure along the way leaves an account that can be deleted again
+    if let Err(e) = purge_user_data(&email, &state).await {
+        return (jar, Err(e));
+    }
+    if state.user_store.write().await.delete_user(&email).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    if let Err(e) = notify_user(&email, "Your account was deleted", "Your account and its data were deleted.", &state).await {
+        return (jar, Err(e));
+    }
+
+    let jar = jar
+        .remove(Cookie::build(JWT_COOKIE_NAME).path("/"))
+        .remove(Cookie::build(REFRESH_TOKEN_COOKIE_NAME).path(REFRESH_TOKEN_COOKIE_PATH));
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+// Drop what the other stores hold about the user. Postgres would cascade most of it from the
+// users table, the hashmap and Redis stores don't.
+async fn purge_user_data(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    revoke_all_sessions(email, state).await?;
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .remove_devices(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .set_codes(email, vec![])
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .webauthn_credential_store
+        .write()
+        .await
+        .remove_credentials(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    match state.phone_verification_store.write().await.take_verification(email).await {
+        Ok(_) | Err(PhoneVerificationStoreError::VerificationNotFound) => Ok(()),
+        Err(_) => Err(AuthAPIError::UnexpectedError),
+    }
+}
+
+// Everything stored about the logged in user, as one JSON document. Secrets (the password
+// hash, the authenticator app secret, recovery code hashes and passkey public keys) are only
+// reported as present.
+pub async fn export_account(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let export = match collect_account_export(&email, &claims.sid, &state).await {
+        Ok(export) => export,
+        Err(e) => return (jar, Err(e))
+    };
+
+    (jar, Ok(Json(export)))
+}
+
+async fn collect_account_export(email: &Email, current_session_id: &str, state: &AppState) -> Result<AccountExport, AuthAPIError> {
+    let user = state.user_store.read().await.get_user(email).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+    let sessions = state.session_store.read().await.get_sessions(email).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+    let trusted_devices = state.trusted_device_store.read().await.get_devices(email).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+    let recovery_codes = state.recovery_code_store.read().await.get_codes(email).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+    let passkeys = state.webauthn_credential_store.read().await.get_credentials(email).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(AccountExport {
+        email: user.email.as_ref().to_owned(),
+        email_verified: user.email_verified,
+        requires_2fa: user.requires_2fa,
+        authenticator_app: user.has_totp(),
+        two_fa_channel: TwoFaChannelResponse::from(user.two_fa_channel),
+        recovery_codes_left: recovery_codes.len(),
+        sessions: sessions.into_iter().map(|session| SessionResponse::new(session, current_session_id)).collect(),
+        trusted_devices: trusted_devices.into_iter().map(TrustedDeviceResponse::from).collect(),
+        passkeys: passkeys.into_iter().map(PasskeyExport::from).collect(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountExport {
+    pub email: String,
+    #[serde(rename = "emailVerified")]
+    pub email_verified: bool,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "authenticatorApp")]
+    pub authenticator_app: bool,
+    #[serde(rename = "twoFAChannel")]
+    pub two_fa_channel: TwoFaChannelResponse,
+    #[serde(rename = "recoveryCodesLeft")]
+    pub recovery_codes_left: usize,
+    pub sessions: Vec<SessionResponse>,
+    #[serde(rename = "trustedDevices")]
+    pub trusted_devices: Vec<TrustedDeviceResponse>,
+    pub passkeys: Vec<PasskeyExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyExport {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<WebAuthnCredential> for PasskeyExport {
+    fn from(credential: WebAuthnCredential) -> Self {
+        Self {
+            id: credential.id.as_ref().to_owned(),
+            created_at: credential.created_at.to_rfc3339(),
+        }
+    }
+}
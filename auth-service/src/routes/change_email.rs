@@ -0,0 +1,157 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::{Query, State};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{EmailChange, EmailChangeToken, UserStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::utils::auth::{authenticate_cookie, revoke_all_sessions};
+use crate::utils::constants::PUBLIC_BASE_URL;
+use crate::utils::notifications::notify_user;
+
+// Start moving the logged in user to a new email address. The user confirms with their
+// password. The new address gets a link that confirms the change, and the current one a
+// link that cancels it.
+pub async fn change_email(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ChangeEmailRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let new_email = match Email::parse(request.new_email) {
+        Ok(new_email) if new_email != email => new_email,
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    let user_store = state.user_store.read().await;
+    if user_store.validate_user(&email, &request.password).await.is_err() {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+    match user_store.get_user(&new_email).await {
+        Ok(_) => return (jar, Err(AuthAPIError::UserAlreadyExists)),
+        Err(UserStoreError::UserNotFound) => {}
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    }
+    drop(user_store);
+
+    let change = EmailChange::new(email.clone(), new_email.clone());
+    if state.email_change_store.write().await.add_change(change.clone()).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let base_url = PUBLIC_BASE_URL.trim_end_matches('/');
+    let content = format!(
+        "Follow this link to move your account to this address: {}/confirm-email-change?token={}",
+        base_url,
+        change.confirm_token.as_ref()
+    );
+    if let Err(e) = notify_user(&new_email, "Confirm your new email address", &content, &state).await {
+        return (jar, Err(e));
+    }
+    let content = format!(
+        "Your account is being moved to {}. If that wasn't you, follow this link to cancel: {}/cancel-email-change?token={}",
+        new_email.as_ref(),
+        base_url,
+        change.cancel_token.as_ref()
+    );
+    if let Err(e) = notify_user(&email, "Your email address is being changed", &content, &state).await {
+        return (jar, Err(e));
+    }
+
+    let message = "Follow the link sent to the new address to confirm the change".to_owned();
+    (jar, Ok(Json(ChangeEmailResponse { message })))
+}
+
+// Where the link sent to the new address leads. Following it moves the account. Tokens
+// carry the old address as their subject, so every session is revoked and the user logs in
+// again with the new one. Sessions are stored under the address too, so they're revoked
+// before the move, while they can still be found.
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    Query(query): Query<EmailChangeQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = EmailChangeToken::parse(query.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let change = state
+        .email_change_store
+        .write()
+        .await
+        .take_change(&token)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    revoke_all_sessions(&change.email, &state).await?;
+    match state.user_store.write().await.change_email(&change.email, change.new_email.clone()).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    // Logins waiting for 2FA and trusted devices belong to the old address
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(&change.email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .remove_devices(&change.email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let content = format!("Your account was moved to {}.", change.new_email.as_ref());
+    notify_user(&change.email, "Your email address was changed", &content, &state).await?;
+
+    Ok(Json(ChangeEmailResponse { message: "Email address changed".to_owned() }))
+}
+
+// Where the link sent to the current address leads. Following it drops the change.
+pub async fn cancel_email_change(
+    State(state): State<AppState>,
+    Query(query): Query<EmailChangeQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = EmailChangeToken::parse(query.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .email_change_store
+        .write()
+        .await
+        .cancel_change(&token)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    Ok(Json(ChangeEmailResponse { message: "Email change cancelled".to_owned() }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailChangeQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
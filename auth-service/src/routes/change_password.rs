@@ -0,0 +1,83 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::HashedPassword;
+use crate::utils::auth::{authenticate_cookie, revoke_all_sessions, start_session};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::notifications::notify_user;
+
+// Change the password of the logged in user. A stolen session isn't enough for that: the user
+// confirms with their current password. Every other device is logged out, and this one gets
+// a fresh session.
+pub async fn change_password(
+    State(state): State<AppState>,
+    client_info: ClientInfo,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if user.password.verify_raw_password(&request.current_password).await.is_err() {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    let password = match HashedPassword::parse(request.new_password).await {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+    if state.user_store.write().await.set_password(&email, password).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    // Revoking every session includes this one, so it is replaced by a new one carrying
+    // the new session epoch
+    if let Err(e) = revoke_all_sessions(&email, &state).await {
+        return (jar, Err(e));
+    }
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    let (auth_cookie, refresh_cookie) = match start_session(&user, client_info, &state).await {
+        Ok(cookies) => cookies,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    let jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    let content = "The password of your account was changed and your other devices were logged out.";
+    if let Err(e) = notify_user(&email, "Your password was changed", content, &state).await {
+        return (jar, Err(e));
+    }
+
+    (jar, Ok(Json(ChangePasswordResponse { message: "Password changed".to_owned() })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
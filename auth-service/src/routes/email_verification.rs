@@ -0,0 +1,88 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::{Query, State};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{EmailVerificationToken, UserStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::utils::constants::PUBLIC_BASE_URL;
+use crate::utils::notifications::notify_user;
+
+// Where the link in the verification email leads. Following it verifies the address.
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = EmailVerificationToken::parse(query.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Links only work once, and not after they expired
+    let email = state
+        .email_verification_token_store
+        .write()
+        .await
+        .take_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state.user_store.write().await.set_email_verified(&email).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    Ok(Json(VerifyEmailResponse { message: "Email address verified".to_owned() }))
+}
+
+// Send a new verification link, e.g. when the first one expired. The answer is the same
+// whether or not the address belongs to an unverified account, so it can't be used to
+// find out who has one.
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let unverified = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => !user.email_verified,
+        Err(UserStoreError::UserNotFound) => false,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+    if unverified {
+        send_verification_email(&email, &state).await?;
+    }
+
+    let message = "If the address belongs to an unverified account, a new link was sent".to_owned();
+    Ok(Json(VerifyEmailResponse { message }))
+}
+
+// Email the user a link that verifies their address
+pub(crate) async fn send_verification_email(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    let token = EmailVerificationToken::default();
+    state
+        .email_verification_token_store
+        .write()
+        .await
+        .add_token(token.clone(), email.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let link = format!("{}/verify-email?token={}", PUBLIC_BASE_URL.trim_end_matches('/'), token.as_ref());
+    let content = format!("Follow this link to verify your email address: {}", link);
+    notify_user(email, "Verify your email address", &content, state).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
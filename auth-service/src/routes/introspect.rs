@@ -0,0 +1,107 @@
+use axum::extract::{FromRequest, Request, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::{Form, Json};
+use serde::{Deserialize, Serialize};
+use crate::app_state::AppState;
+use crate::domain::error::AuthAPIError;
+use crate::utils::api_client::AuthenticatedClient;
+use crate::utils::auth::authenticate_token;
+
+// Auth tokens aren't scoped down: they grant whatever the logged in user is allowed to do
+pub const AUTH_TOKEN_SCOPE: &str = "session";
+
+// RFC 7662 token introspection. Only registered API clients may ask about tokens.
+// Any token that wouldn't be accepted by /verify-token is reported as inactive.
+pub async fn introspect(
+    State(state): State<AppState>,
+    _client: AuthenticatedClient,
+    request: IntrospectionRequest,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = match authenticate_token(&request.token, &state).await {
+        Ok(claims) => claims,
+        Err(AuthAPIError::UnexpectedError) => return Err(AuthAPIError::UnexpectedError),
+        Err(_) => return Ok(Json(IntrospectionResponse::default())),
+    };
+
+    let response = IntrospectionResponse {
+        active: true,
+        scope: Some(AUTH_TOKEN_SCOPE.to_owned()),
+        // Tokens are issued to the audience service
+        client_id: Some(claims.aud.clone()),
+        token_type: Some("Bearer".to_owned()),
+        sub: Some(claims.sub),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        nbf: Some(claims.nbf),
+        iss: Some(claims.iss),
+        aud: Some(claims.aud),
+        jti: Some(claims.jti),
+        sid: Some(claims.sid),
+        session_epoch: Some(claims.session_epoch),
+    };
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    // Only access tokens can be introspected, so the hint makes no difference
+    #[allow(dead_code)]
+    pub token_type_hint: Option<String>,
+}
+
+// RFC 7662 asks for a form body, but JSON is accepted as well
+impl<S> FromRequest<S> for IntrospectionRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+
+        if is_json {
+            let Json(request) = Json::<Self>::from_request(req, state).await.map_err(IntoResponse::into_response)?;
+            Ok(request)
+        } else {
+            let Form(request) = Form::<Self>::from_request(req, state).await.map_err(IntoResponse::into_response)?;
+            Ok(request)
+        }
+    }
+}
+
+// Inactive tokens only carry `active: false`
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    // Extensions, so the response holds every claim of the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_epoch: Option<i64>,
+}
@@ -0,0 +1,14 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::Json;
+use crate::app_state::AppState;
+
+// Publish the public verification keys so other services can check tokens locally.
+// Verify-only keys stay listed until they are retired.
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(state.key_ring.read().await.jwks()),
+    )
+}
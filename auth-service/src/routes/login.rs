@@ -1,18 +1,17 @@
-use std::sync::Arc;
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use axum::extract::State;
 use axum_extra::extract::CookieJar;
-use serde::{Deserialize, Serialize};
-use log::log;
-use crate::{app_state::AppState, domain::user::User};
+use crate::app_state::AppState;
 use crate::domain::data_stores::{LoginAttemptId, TwoFaCode};
 use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
 use crate::domain::HashedPassword;
-use crate::routes::SignupResponse;
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::user::{TwoFaChannel, User};
+use crate::utils::auth::{create_login_attempt_cookie, is_trusted_device, start_session};
+use crate::utils::client_info::ClientInfo;
 
 pub async fn login(State(state): State<AppState>,
+                   client_info: ClientInfo,
                    jar: CookieJar,
                    Json(request): Json<LoginRequest>) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>){
 
@@ -26,27 +25,35 @@ pub async fn login(State(state): State<AppState>,
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
     };
 
-    let user_store = &state.user_store.read().await;
+    // The user store lock is released before any other store is used
+    let user = {
+        let user_store = state.user_store.read().await;
 
-    if user_store.validate_user(&email, request.password.as_ref()).await.is_err() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials))
-    }
+        if user_store.validate_user(&email, request.password.as_ref()).await.is_err() {
+            return (jar, Err(AuthAPIError::IncorrectCredentials))
+        }
 
-    let user = match user_store.get_user(&email).await {
-        Ok(user) => user,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        match user_store.get_user(&email).await {
+            Ok(user) => user,
+            Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        }
     };
 
-    // Handle request based on user's 2FA configuration
-    match user.requires_2fa {
-        true => handle_2fa( &email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+    if !user.email_verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified))
+    }
+
+    // Handle request based on user's 2FA configuration. Browsers the user chose to remember
+    // don't need a second factor.
+    match user.requires_2fa && !is_trusted_device(&jar, &user, &state).await {
+        true => handle_2fa(&user, &state, jar).await,
+        false => handle_no_2fa(&user, client_info, &state, jar).await,
     }
 }
 
 async fn handle_2fa(
-    email: &Email, // New!
-    state: &AppState, // New!
+    user: &User,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
@@ -55,20 +62,15 @@ async fn handle_2fa(
     // First, we must generate a new random login attempt ID and 2FA code
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFaCode::default();
-    let mut store = state.two_fa_code_store.write().await;
-
-    if store.add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone()).await.is_err() {
+    if state.two_fa_code_store.write().await.add_code(user.email.clone(), login_attempt_id.clone(), two_fa_code.clone()).await.is_err() {
         return (jar, Err(AuthAPIError::UnexpectedError))
     }
 
-    // send 2FA code via the email client. Return `AuthAPIError::UnexpectedError` if the operation fails.
-    let mail_client = state.email_client.write().await;
-    let send_res = mail_client.send_email(
-        email,
-        "Your 2fa code",
-        format!("Your 2fa code is: {:?}", two_fa_code.as_ref()).as_str()).await;
-    if send_res.is_err() {
-        return (jar, Err(AuthAPIError::UnexpectedError))
+    // Users with an authenticator app take the code from there, nothing is sent
+    if !user.has_totp() {
+        if let Err(e) = send_2fa_code(user, &two_fa_code, state).await {
+            return (jar, Err(e))
+        }
     }
     // Finally, we need to return the login attempt ID to the client
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
@@ -76,50 +78,45 @@ async fn handle_2fa(
         login_attempt_id: login_attempt_id.as_ref().to_string(), // Add the generated login attempt ID
     }));
 
-    (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
+    // Only this browser can finish the login
+    let updated_jar = jar.add(create_login_attempt_cookie(&login_attempt_id));
+
+    (updated_jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
+}
+
+// Send the 2FA code over the user's preferred channel. Return `AuthAPIError::UnexpectedError` if the operation fails.
+pub(crate) async fn send_2fa_code(user: &User, code: &TwoFaCode, state: &AppState) -> Result<(), AuthAPIError> {
+    let content = format!("Your 2fa code is: {:?}", code.as_ref());
+    let result = match &user.two_fa_channel {
+        TwoFaChannel::Email => state.email_client.read().await.send_email(&user.email, "Your 2fa code", &content).await,
+        TwoFaChannel::Sms(phone_number) => state.sms_client.read().await.send_sms(phone_number, &content).await,
+    };
+    result.map_err(|_| AuthAPIError::UnexpectedError)
 }
 
 // New!
 async fn handle_no_2fa(
-    email: &Email,
+    user: &User,
+    client_info: ClientInfo,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    // Call the generate_auth_cookie function defined in the auth module.
-    // If the function call fails return AuthAPIError::UnexpectedError.
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    // A fresh login starts a new session, with its own refresh token family.
+    // If the call fails return AuthAPIError::UnexpectedError.
+    let (auth_cookie, refresh_cookie) = match start_session(user, client_info, state).await {
+        Ok(cookies) => cookies,
         Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
 
     (updated_jar, Ok((StatusCode::OK, Json(LoginResponse::RegularAuth))))
 }
 
 
-#[derive(Deserialize)]
-pub struct LoginRequest {
-    pub email: String,
-    pub password: String,
-}
-
-// The login route can return 2 possible success responses.
-// This enum models each response!
-#[derive(Debug, Serialize)]
-#[serde(untagged)]
-pub enum LoginResponse {
-    RegularAuth,
-    TwoFactorAuth(TwoFactorAuthResponse),
-}
-
-// If a user requires 2FA, this JSON body should be returned!
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TwoFactorAuthResponse {
-    pub message: String,
-    #[serde(rename = "loginAttemptId")]
-    pub login_attempt_id: String,
-}
\ No newline at end of file
+// Request and response types are shared with auth-service-client
+pub use auth_service_client::types::{LoginRequest, LoginResponse, TwoFactorAuthResponse};
@@ -1,12 +1,14 @@
 use axum::{http::StatusCode, response::IntoResponse};
 use axum::extract::State;
+use axum_extra::extract::cookie::Cookie;
 use axum_extra::extract::CookieJar;
 
 use crate::{
 
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    utils::{auth::{ban_session_tokens, validate_token}, constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME, REFRESH_TOKEN_COOKIE_PATH}},
 };
 use crate::app_state::AppState;
+use crate::domain::data_stores::{SessionId, SessionStoreError};
 use crate::domain::error::AuthAPIError;
 
 pub async fn logout(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -19,19 +21,36 @@ pub async fn logout(State(state): State<AppState>, jar: CookieJar) -> (CookieJar
 
     let token = cookie.value().to_owned();
 
-    // TODO: Validate JWT token by calling `validate_token` from the auth service.
-    // If the token is valid you can ignore the returned claims for now.
+    // Validate JWT token. Its `jti` and `exp` are needed to ban it below.
     // Return AuthAPIError::InvalidToken is validation fails.
-    if validate_token(&token).await.is_err() {
-        return (jar, Err(AuthAPIError::InvalidToken));
-    }
+    let claims = match validate_token(&token, &state.key_ring).await {
+        Ok(claims) => claims,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
 
-    // Remove JWT cookie from the CookieJar
-    let jar = jar.clone().remove(cookie.clone());
+    // Remove JWT and refresh cookies from the CookieJar
+    let jar = jar.clone()
+        .remove(cookie.clone())
+        .remove(Cookie::build(REFRESH_TOKEN_COOKIE_NAME).path(REFRESH_TOKEN_COOKIE_PATH));
 
-    if state.banned_token_store.write().await.add_token(token).await.is_err(){
+    if state.banned_token_store.write().await.add_token(claims.jti, claims.exp as i64).await.is_err(){
         return (jar, Err(AuthAPIError::UnexpectedError));
     }
 
+    // End the session along with the refresh tokens it was given. Access tokens issued
+    // earlier in the session are banned too, not just the one presented.
+    if let Ok(session_id) = SessionId::parse(claims.sid) {
+        match state.session_store.write().await.remove_session(&session_id).await {
+            Ok(_) | Err(SessionStoreError::SessionNotFound) => {}
+            Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+        }
+        if state.refresh_token_store.write().await.revoke_family(&session_id.clone().into()).await.is_err() {
+            return (jar, Err(AuthAPIError::UnexpectedError));
+        }
+        if ban_session_tokens(&session_id, &state).await.is_err() {
+            return (jar, Err(AuthAPIError::UnexpectedError));
+        }
+    }
+
     (jar, Ok(StatusCode::OK))
 }
\ No newline at end of file
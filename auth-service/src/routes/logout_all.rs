@@ -0,0 +1,37 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use axum::extract::State;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+
+use crate::app_state::AppState;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::utils::{auth::{authenticate_token, revoke_all_sessions}, constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME, REFRESH_TOKEN_COOKIE_PATH}};
+
+// Log the user out of every device by invalidating all the tokens issued to them so far
+pub async fn logout_all(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match jar.get(JWT_COOKIE_NAME) {
+        Some(cookie) => cookie.clone(),
+        None => return (jar, Err(AuthAPIError::MissingToken))
+    };
+
+    let claims = match authenticate_token(cookie.value(), &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    if let Err(e) = revoke_all_sessions(&email, &state).await {
+        return (jar, Err(e));
+    }
+
+    let jar = jar
+        .remove(cookie)
+        .remove(Cookie::build(REFRESH_TOKEN_COOKIE_NAME).path(REFRESH_TOKEN_COOKIE_PATH));
+
+    (jar, Ok(StatusCode::OK))
+}
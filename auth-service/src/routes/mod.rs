@@ -1,12 +1,48 @@
+mod account;
+mod change_email;
+mod change_password;
+mod email_verification;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod password_reset;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod revocations;
+mod sessions;
 mod signup;
+mod totp;
+mod trusted_devices;
+mod two_fa;
+mod two_fa_channel;
 mod verify_2fa;
 mod verify_token;
+mod webauthn;
 
 // re-export items from sub-modules
+pub use account::*;
+pub use change_email::*;
+pub use change_password::*;
+pub use email_verification::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use logout_all::*;
+pub use password_reset::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use resend_2fa::*;
+pub use revocations::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
+pub use trusted_devices::*;
+pub use two_fa::*;
+pub use two_fa_channel::*;
 pub use verify_2fa::*;
-pub use verify_token::*;
\ No newline at end of file
+pub use verify_token::*;
+pub use webauthn::*;
\ No newline at end of file
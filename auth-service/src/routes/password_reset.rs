@@ -0,0 +1,89 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{PasswordResetToken, UserStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::HashedPassword;
+use crate::utils::auth::revoke_all_sessions;
+use crate::utils::notifications::notify_user;
+
+// Email a password reset token. The answer is the same whether or not the address belongs
+// to an account, so it can't be used to find out who has one.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let has_account = match state.user_store.read().await.get_user(&email).await {
+        Ok(_) => true,
+        Err(UserStoreError::UserNotFound) => false,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+    if has_account {
+        let token = PasswordResetToken::default();
+        state
+            .password_reset_token_store
+            .write()
+            .await
+            .add_token(token.clone(), email.clone())
+            .await
+            .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+        let content = format!("Use this token to reset your password within the hour: {}", token.as_ref());
+        notify_user(&email, "Reset your password", &content, &state).await?;
+    }
+
+    let message = "If the address belongs to an account, a reset token was sent".to_owned();
+    Ok(Json(PasswordResetResponse { message }))
+}
+
+// Set a new password with an emailed reset token. Every session is revoked, so whoever
+// knew the old password is logged out.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = PasswordResetToken::parse(request.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    // Checked before taking the token, so a rejected password doesn't use it up
+    let password = HashedPassword::parse(request.new_password).await.map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let email = state
+        .password_reset_token_store
+        .write()
+        .await
+        .take_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state.user_store.write().await.set_password(&email, password).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    revoke_all_sessions(&email, &state).await?;
+    notify_user(&email, "Your password was reset", "Your password was reset and you were logged out everywhere.", &state).await?;
+
+    Ok(Json(PasswordResetResponse { message: "Password reset".to_owned() }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordResetResponse {
+    pub message: String,
+}
@@ -0,0 +1,88 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::RecoveryCode;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::utils::auth::authenticate_cookie;
+use crate::utils::notifications::notify_user;
+use super::two_fa::confirm_user;
+
+// Replace the user's recovery codes with a new set, e.g. after using some of them up.
+// The old codes stop working. A stolen session isn't enough for that: the user confirms with
+// their password or second factor, and is emailed about the new set.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if !user.requires_2fa {
+        return (jar, Err(AuthAPIError::TwoFaNotEnabled));
+    }
+    if let Err(e) = confirm_user(&user, request.password, request.two_fa_code, &state).await {
+        return (jar, Err(e));
+    }
+
+    let recovery_codes = match issue_recovery_codes(&email, &state).await {
+        Ok(recovery_codes) => recovery_codes,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let content = "Your recovery codes were replaced with a new set. The old codes no longer work.";
+    if let Err(e) = notify_user(&email, "New recovery codes", content, &state).await {
+        return (jar, Err(e));
+    }
+
+    (jar, Ok(Json(RecoveryCodesResponse { recovery_codes })))
+}
+
+// Generates a fresh set of codes for the user. Only their hashes are kept, so the returned
+// codes must be shown to the user right away.
+pub(crate) async fn issue_recovery_codes(email: &Email, state: &AppState) -> Result<Vec<String>, AuthAPIError> {
+    let codes: Vec<RecoveryCode> = (0..RecoveryCode::COUNT).map(|_| RecoveryCode::default()).collect();
+
+    let mut hashes = Vec::with_capacity(codes.len());
+    for code in &codes {
+        hashes.push(code.hash().await.map_err(|_| AuthAPIError::UnexpectedError)?);
+    }
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .set_codes(email, hashes)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    Ok(codes.iter().map(|code| code.as_ref().to_owned()).collect())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: Option<String>,
+    // An authenticator app code or a recovery code
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,77 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use crate::app_state::AppState;
+use crate::domain::data_stores::{RefreshToken, RefreshTokenStoreError, SessionId, SessionStoreError, UserStoreError};
+use crate::domain::error::AuthAPIError;
+use crate::utils::auth::{generate_auth_cookie, generate_refresh_cookie};
+use crate::utils::constants::REFRESH_TOKEN_COOKIE_NAME;
+
+pub async fn refresh(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let token = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return (jar, Err(AuthAPIError::MissingToken))
+    };
+
+    let token = match RefreshToken::parse(token) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let record = match state.refresh_token_store.read().await.get_token(&token).await {
+        Ok(record) => record,
+        Err(RefreshTokenStoreError::TokenNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    // A refresh token can only be exchanged once. Seeing it again means it leaked,
+    // so every token descending from the same login is revoked. Marking the token used
+    // is atomic in the store, so no store lock is held while the other stores are checked.
+    let marked = state.refresh_token_store.write().await.mark_token_used(&token).await;
+    match marked {
+        Ok(_) => {}
+        Err(RefreshTokenStoreError::TokenAlreadyUsed) => {
+            if state.refresh_token_store.write().await.revoke_family(&record.family_id).await.is_err() {
+                return (jar, Err(AuthAPIError::UnexpectedError));
+            }
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(RefreshTokenStoreError::TokenNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    }
+
+    let user = match state.user_store.read().await.get_user(&record.email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    // The user logged out everywhere since this family was started,
+    // or the session it belongs to was revoked
+    let session_id = SessionId::from(record.family_id.clone());
+    let session_ended = match state.session_store.write().await.touch_session(&session_id).await {
+        Ok(_) => user.session_epoch != record.session_epoch,
+        Err(SessionStoreError::SessionNotFound) => true,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if session_ended {
+        if state.refresh_token_store.write().await.revoke_family(&record.family_id).await.is_err() {
+            return (jar, Err(AuthAPIError::UnexpectedError));
+        }
+        return (jar, Err(AuthAPIError::InvalidToken));
+    }
+
+    let auth_cookie = match generate_auth_cookie(&user, &session_id, &state.key_ring).await {
+        Ok(cookie) => cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let refresh_cookie = match generate_refresh_cookie(&user, record.family_id, &state.refresh_token_store).await {
+        Ok(cookie) => cookie,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
@@ -0,0 +1,67 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{LoginAttemptId, TwoFACodeStoreError, TwoFaCode};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use super::login::send_2fa_code;
+
+// Send a new code for a pending login attempt, e.g. when the first message got lost.
+// The old code stops working.
+pub async fn resend_2fa(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<Resend2FARequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    let login_attempt_id = match LoginAttemptId::parse(request.login_attempt_id) {
+        Ok(login_attempt_id) => login_attempt_id,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    match state.two_fa_code_store.read().await.get_code(&login_attempt_id).await {
+        Ok((pending_email, _)) if pending_email == email => {}
+        _ => return (jar, Err(AuthAPIError::IncorrectCredentials))
+    }
+
+    // Authenticator apps make their own codes
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if user.has_totp() => return (jar, Err(AuthAPIError::TotpAlreadyEnabled)),
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let two_fa_code = TwoFaCode::default();
+    // The attempt may have been finished in the meantime, which the store reports as not found
+    match state.two_fa_code_store.write().await.resend_code(&login_attempt_id, two_fa_code.clone()).await {
+        Ok(_) => {}
+        Err(TwoFACodeStoreError::ResendTooSoon) => return (jar, Err(AuthAPIError::ResendTooSoon)),
+        Err(TwoFACodeStoreError::TooManyResends) => return (jar, Err(AuthAPIError::TooManyAttempts)),
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    }
+
+    match send_2fa_code(&user, &two_fa_code, &state).await {
+        Ok(_) => (jar, Ok(Json(Resend2FAResponse { message: "2FA code sent".to_owned() }))),
+        Err(e) => (jar, Err(e))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Resend2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Resend2FAResponse {
+    pub message: String,
+}
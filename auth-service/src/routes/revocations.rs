@@ -0,0 +1,47 @@
+use std::convert::Infallible;
+
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::IntoResponse;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::BannedToken;
+use crate::domain::error::AuthAPIError;
+use crate::services::revocation_feed::RevocationEvent;
+use crate::utils::api_client::AuthenticatedClient;
+
+// Server-sent events stream of banned jtis and session ids, for revocation caches.
+// It starts with every ban still in force followed by a `ready` event, then carries new
+// bans as they happen. The stream ends whenever bans may have been missed; clients then
+// reconnect and start over from a fresh snapshot.
+pub async fn revocations(
+    State(state): State<AppState>,
+    _client: AuthenticatedClient,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Subscribe before taking the snapshot so no ban falls in between
+    let receiver = state.revocation_feed.subscribe();
+    let snapshot = match state.banned_token_store.read().await.get_banned_tokens().await {
+        Ok(tokens) => tokens,
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+
+    let bootstrap = snapshot
+        .into_iter()
+        .map(revoked_event)
+        .chain(std::iter::once(Event::default().event("ready").data("")));
+    let live = BroadcastStream::new(receiver).map_while(|event| match event {
+        Ok(RevocationEvent::Revoked(token)) => Some(revoked_event(token)),
+        // Resync, or this subscriber lagged behind and lost events
+        Ok(RevocationEvent::Resync) | Err(_) => None,
+    });
+    let stream = tokio_stream::iter(bootstrap).chain(live).map(Ok::<_, Infallible>);
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn revoked_event(token: BannedToken) -> Event {
+    let data = serde_json::to_string(&token).expect("BannedToken serializes to JSON");
+    Event::default().event("revoked").data(data)
+}
@@ -0,0 +1,104 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::{Path, State};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{Session, SessionId, SessionStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::utils::auth::{authenticate_cookie, ban_session_tokens};
+
+// List the devices the user is logged in on
+pub async fn get_sessions(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let sessions = match state.session_store.read().await.get_sessions(&email).await {
+        Ok(sessions) => sessions,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, &claims.sid))
+        .collect::<Vec<_>>();
+
+    (jar, Ok(Json(sessions)))
+}
+
+// Log a single device out. Its tokens are rejected right away.
+pub async fn delete_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let session_id = match SessionId::parse(id) {
+        Ok(session_id) => session_id,
+        Err(_) => return (jar, Err(AuthAPIError::SessionNotFound))
+    };
+
+    let mut session_store = state.session_store.write().await;
+
+    // Other users' sessions are reported as missing
+    match session_store.get_session(&session_id).await {
+        Ok(session) if session.email.as_ref() == claims.sub => {}
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => return (jar, Err(AuthAPIError::SessionNotFound)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    }
+
+    if session_store.remove_session(&session_id).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+    drop(session_store);
+
+    if state.refresh_token_store.write().await.revoke_family(&session_id.clone().into()).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    if ban_session_tokens(&session_id, &state).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastSeen")]
+    pub last_seen: String,
+    // Whether this is the session making the request
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub(crate) fn new(session: Session, current_session_id: &str) -> Self {
+        Self {
+            current: session.id.as_ref() == current_session_id,
+            id: session.id.as_ref().to_owned(),
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at.to_rfc3339(),
+            last_seen: session.last_seen.to_rfc3339(),
+        }
+    }
+}
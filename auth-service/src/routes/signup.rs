@@ -1,11 +1,11 @@
-use std::sync::Arc;
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use axum::extract::State;
-use serde::{Deserialize, Serialize};
 use crate::{app_state::AppState, domain::user::User};
 use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
 use crate::domain::HashedPassword;
+use super::email_verification::send_verification_email;
+use super::recovery_codes::issue_recovery_codes;
 
 pub async fn signup(State(state): State<AppState>,
                     Json(request): Json<SignupRequest>) -> Result<impl IntoResponse, AuthAPIError>{
@@ -13,7 +13,7 @@ pub async fn signup(State(state): State<AppState>,
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
     let password = HashedPassword::parse( request.password).await.map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user = User::new(email, password, request.requires_2fa);
+    let user = User::new(email.clone(), password, request.requires_2fa);
     let mut user_store = state.user_store.write().await;
 
 
@@ -23,22 +23,24 @@ pub async fn signup(State(state): State<AppState>,
     if user_store.add_user(user).await.is_err() {
         return Err(AuthAPIError::UnexpectedError);
     }
+    drop(user_store);
+
+    // The account can only be logged into once the address is verified
+    send_verification_email(&email, &state).await?;
+
+    // Accounts starting out with 2FA get their recovery codes right away
+    let recovery_codes = match request.requires_2fa {
+        true => Some(issue_recovery_codes(&email, &state).await?),
+        false => None,
+    };
 
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
 
     Ok((StatusCode::CREATED, response))
 }
 
-#[derive(Deserialize)]
-pub struct SignupRequest {
-    pub email: String,
-    pub password: String,
-    #[serde(rename = "requires2FA")]
-    pub requires_2fa: bool,
-}
-#[derive(Serialize)]
-pub struct SignupResponse {
-    pub message: String,
-}
\ No newline at end of file
+// Request and response types are shared with auth-service-client
+pub use auth_service_client::types::{SignupRequest, SignupResponse};
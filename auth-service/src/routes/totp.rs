@@ -0,0 +1,155 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::TwoFaCode;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::totp::TotpSecret;
+use crate::utils::auth::authenticate_cookie;
+use crate::utils::notifications::notify_user;
+use super::recovery_codes::issue_recovery_codes;
+use super::two_fa::confirm_user;
+use crate::utils::constants::{TOTP_ENCRYPTION_KEY, TOTP_ISSUER};
+
+// Start enrolling an authenticator app. Logins only ask for its codes once one of them
+// was confirmed through /confirm-totp; enrolling again before that starts over. A stolen
+// session isn't enough to add an app: the user confirms with their password or second factor.
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<EnrollTotpRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if user.has_totp() {
+        return (jar, Err(AuthAPIError::TotpAlreadyEnabled));
+    }
+    if let Err(e) = confirm_user(&user, request.password, request.two_fa_code, &state).await {
+        return (jar, Err(e));
+    }
+
+    let secret = TotpSecret::default();
+    let encrypted_secret = match secret.encrypt(&TOTP_ENCRYPTION_KEY) {
+        Ok(encrypted_secret) => encrypted_secret,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if state.user_store.write().await.set_totp_secret(&email, encrypted_secret).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let response = TotpEnrollmentResponse {
+        otpauth_uri: secret.otpauth_uri(TOTP_ISSUER, &email),
+        secret: secret.encode(),
+    };
+    (jar, Ok(Json(response)))
+}
+
+// Prove the authenticator app was set up by entering one of its codes. From then on it's
+// the user's second factor. The user confirms again like for /enroll-totp, and is emailed
+// once the app is added.
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let code = match TwoFaCode::parse(request.code) {
+        Ok(code) => code,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    let totp = match &user.totp {
+        Some(totp) if totp.confirmed => return (jar, Err(AuthAPIError::TotpAlreadyEnabled)),
+        Some(totp) => totp.clone(),
+        None => return (jar, Err(AuthAPIError::TotpNotEnrolled))
+    };
+    if let Err(e) = confirm_user(&user, request.password, request.two_fa_code, &state).await {
+        return (jar, Err(e));
+    }
+
+    let step = match totp.verify(&code, &TOTP_ENCRYPTION_KEY, Utc::now().timestamp()) {
+        Ok(Some(step)) => step,
+        Ok(None) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if state.user_store.write().await.confirm_totp(&email, step).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    // Turning 2FA on hands out recovery codes. Users who had emailed codes already have theirs.
+    let recovery_codes = match user.requires_2fa {
+        true => None,
+        false => match issue_recovery_codes(&email, &state).await {
+            Ok(recovery_codes) => Some(recovery_codes),
+            Err(e) => return (jar, Err(e))
+        },
+    };
+
+    let content = "An authenticator app was added to your account. Logins now ask for its codes.";
+    if let Err(e) = notify_user(&email, "Authenticator app added", content, &state).await {
+        return (jar, Err(e));
+    }
+
+    (jar, Ok(Json(ConfirmTotpResponse { recovery_codes })))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollmentResponse {
+    // Base32, for entering by hand
+    pub secret: String,
+    // For a QR code authenticator apps can scan
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnrollTotpRequest {
+    pub password: Option<String>,
+    // An authenticator app code or a recovery code, for users with 2FA on
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmTotpRequest {
+    // From the app being added
+    pub code: String,
+    pub password: Option<String>,
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfirmTotpResponse {
+    #[serde(rename = "recoveryCodes", default, skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
+}
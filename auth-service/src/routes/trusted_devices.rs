@@ -0,0 +1,88 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::{Path, State};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{TrustedDevice, TrustedDeviceId, TrustedDeviceStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::utils::auth::authenticate_cookie;
+
+// List the browsers that skip 2FA at login
+pub async fn get_trusted_devices(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let devices = match state.trusted_device_store.read().await.get_devices(&email).await {
+        Ok(devices) => devices,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let devices = devices.into_iter().map(TrustedDeviceResponse::from).collect::<Vec<_>>();
+    (jar, Ok(Json(devices)))
+}
+
+// Stop trusting a browser. Its next login asks for 2FA again.
+pub async fn delete_trusted_device(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let device_id = match TrustedDeviceId::parse(id) {
+        Ok(device_id) => device_id,
+        Err(_) => return (jar, Err(AuthAPIError::TrustedDeviceNotFound))
+    };
+
+    let mut trusted_device_store = state.trusted_device_store.write().await;
+
+    // Other users' devices are reported as missing
+    match trusted_device_store.get_device(&device_id).await {
+        Ok(device) if device.email.as_ref() == claims.sub => {}
+        Ok(_) | Err(TrustedDeviceStoreError::DeviceNotFound) => return (jar, Err(AuthAPIError::TrustedDeviceNotFound)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    }
+
+    match trusted_device_store.remove_device(&device_id).await {
+        Ok(_) => (jar, Ok(StatusCode::NO_CONTENT)),
+        Err(TrustedDeviceStoreError::DeviceNotFound) => (jar, Err(AuthAPIError::TrustedDeviceNotFound)),
+        Err(_) => (jar, Err(AuthAPIError::UnexpectedError))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrustedDeviceResponse {
+    pub id: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+impl From<TrustedDevice> for TrustedDeviceResponse {
+    fn from(device: TrustedDevice) -> Self {
+        Self {
+            id: device.id.as_ref().to_owned(),
+            user_agent: device.user_agent,
+            ip_address: device.ip_address,
+            created_at: device.created_at.to_rfc3339(),
+            expires_at: device.expires_at.to_rfc3339(),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use axum::{response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{RecoveryCode, TwoFaCode};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::user::User;
+use crate::utils::auth::authenticate_cookie;
+use crate::utils::notifications::notify_user;
+use super::recovery_codes::{issue_recovery_codes, RecoveryCodesResponse};
+use super::verify_2fa::{use_recovery_code, use_totp_code};
+
+// Turn on 2FA for an existing account. Codes are sent over the user's 2FA channel, and a
+// fresh set of recovery codes is handed out like at signup.
+pub async fn enable_2fa(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let mut user_store = state.user_store.write().await;
+    match user_store.get_user(&email).await {
+        Ok(user) if user.requires_2fa => return (jar, Err(AuthAPIError::TwoFaAlreadyEnabled)),
+        Ok(_) => {}
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    }
+    if user_store.set_requires_2fa(&email, true).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+    drop(user_store);
+
+    let recovery_codes = match issue_recovery_codes(&email, &state).await {
+        Ok(recovery_codes) => recovery_codes,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let content = "Two-factor authentication was turned on for your account.";
+    if let Err(e) = notify_user(&email, "2FA enabled", content, &state).await {
+        return (jar, Err(e));
+    }
+
+    (jar, Ok(Json(RecoveryCodesResponse { recovery_codes })))
+}
+
+// Turn off 2FA. A stolen session isn't enough for that: the user confirms with their password,
+// or with a code from their authenticator app or a recovery code.
+pub async fn disable_2fa(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<Disable2FARequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if !user.requires_2fa {
+        return (jar, Err(AuthAPIError::TwoFaNotEnabled));
+    }
+
+    if let Err(e) = confirm_user(&user, request.password, request.two_fa_code, &state).await {
+        return (jar, Err(e));
+    }
+
+    if state.user_store.write().await.set_requires_2fa(&email, false).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+    // Recovery codes only make sense with 2FA on
+    if state.recovery_code_store.write().await.set_codes(&email, vec![]).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let content = "Two-factor authentication was turned off for your account.";
+    if let Err(e) = notify_user(&email, "2FA disabled", content, &state).await {
+        return (jar, Err(e));
+    }
+
+    (jar, Ok(Json(Disable2FAResponse { message: "2FA disabled".to_owned() })))
+}
+
+// Checks that the user behind a session is the account holder, for changes a stolen session
+// mustn't be able to make. They confirm with their password or, with 2FA on, a code from their
+// authenticator app or a recovery code.
+pub(crate) async fn confirm_user(
+    user: &User,
+    password: Option<String>,
+    two_fa_code: Option<String>,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let is_confirmed = match (password, two_fa_code) {
+        (Some(password), None) => state.user_store.read().await.validate_user(&user.email, &password).await.is_ok(),
+        (None, Some(code)) if user.requires_2fa => match TwoFaCode::parse(code.clone()) {
+            Ok(code) => use_totp_code(user, &code, state).await?,
+            Err(_) => match RecoveryCode::parse(code) {
+                Ok(code) => use_recovery_code(&user.email, &code, state).await?,
+                Err(_) => return Err(AuthAPIError::InvalidCredentials)
+            }
+        },
+        _ => return Err(AuthAPIError::InvalidCredentials)
+    };
+    match is_confirmed {
+        true => Ok(()),
+        false => Err(AuthAPIError::IncorrectCredentials)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Disable2FARequest {
+    pub password: Option<String>,
+    // An authenticator app code or a recovery code
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Disable2FAResponse {
+    pub message: String,
+}
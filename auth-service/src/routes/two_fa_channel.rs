@@ -0,0 +1,186 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{PhoneVerification, PhoneVerificationStoreError, TwoFaCode};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::phone_number::PhoneNumber;
+use crate::domain::user::TwoFaChannel;
+use crate::utils::auth::authenticate_cookie;
+use crate::utils::constants::TWO_FA_CODE_HMAC_KEY;
+use crate::utils::notifications::notify_user;
+use super::two_fa::confirm_user;
+
+// Choose where 2FA codes are sent at login: by email (the default), or by SMS to a phone number.
+// Users with an authenticator app keep using it either way. A stolen session isn't enough to
+// redirect codes: the user confirms with their password or second factor. Email applies right
+// away; a phone number is texted a code and only used once /2fa-channel/confirm gets it back.
+pub async fn set_two_fa_channel(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<TwoFaChannelRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let channel = match (request.channel.as_str(), request.phone_number) {
+        ("email", None) => TwoFaChannel::Email,
+        ("sms", Some(phone_number)) => match PhoneNumber::parse(phone_number) {
+            Ok(phone_number) => TwoFaChannel::Sms(phone_number),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+        },
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if let Err(e) = confirm_user(&user, request.password, request.two_fa_code, &state).await {
+        return (jar, Err(e));
+    }
+
+    let phone_number = match channel {
+        TwoFaChannel::Email => {
+            return match switch_channel(&email, channel, &state).await {
+                Ok(response) => (jar, Ok((StatusCode::OK, Json(TwoFaChannelUpdate::Changed(response))))),
+                Err(e) => (jar, Err(e))
+            };
+        }
+        TwoFaChannel::Sms(phone_number) => phone_number,
+    };
+
+    let code = TwoFaCode::default();
+    let verification = PhoneVerification {
+        code_hash: code.hash(&phone_number, &TWO_FA_CODE_HMAC_KEY),
+        phone_number: phone_number.clone(),
+    };
+    if state.phone_verification_store.write().await.add_verification(email, verification).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let content = format!("Your code to confirm this number for 2FA is: {}", code.as_ref());
+    if state.sms_client.read().await.send_sms(&phone_number, &content).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let message = "Enter the code texted to the new number to start using it".to_owned();
+    (jar, Ok((StatusCode::ACCEPTED, Json(TwoFaChannelUpdate::Pending(PhoneVerificationResponse { message })))))
+}
+
+// Finish moving 2FA codes to a phone number by entering the code texted to it. A wrong code
+// drops the pending number, so codes can't be guessed; the user starts over instead.
+pub async fn confirm_two_fa_channel(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ConfirmTwoFaChannelRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let code = match TwoFaCode::parse(request.code) {
+        Ok(code) => code,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    let verification = match state.phone_verification_store.write().await.take_verification(&email).await {
+        Ok(verification) => verification,
+        Err(PhoneVerificationStoreError::VerificationNotFound) => return (jar, Err(AuthAPIError::PhoneVerificationNotFound)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    if !verification.code_hash.matches(&code, &verification.phone_number, &TWO_FA_CODE_HMAC_KEY) {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    match switch_channel(&email, TwoFaChannel::Sms(verification.phone_number), &state).await {
+        Ok(response) => (jar, Ok(Json(response))),
+        Err(e) => (jar, Err(e))
+    }
+}
+
+async fn switch_channel(email: &Email, channel: TwoFaChannel, state: &AppState) -> Result<TwoFaChannelResponse, AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .set_two_fa_channel(email, channel.clone())
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let content = match &channel {
+        TwoFaChannel::Email => "Your 2FA codes are now sent by email.".to_owned(),
+        TwoFaChannel::Sms(phone_number) => format!("Your 2FA codes are now texted to {}.", phone_number.as_ref()),
+    };
+    notify_user(email, "2FA channel changed", &content, state).await?;
+
+    Ok(TwoFaChannelResponse::from(channel))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFaChannelRequest {
+    // "email" or "sms"
+    pub channel: String,
+    // Required for "sms", in E.164 format
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Option<String>,
+    pub password: Option<String>,
+    // An authenticator app code or a recovery code
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmTwoFaChannelRequest {
+    // The code texted to the new number
+    pub code: String,
+}
+
+// Email applies right away, a phone number waits for its code
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TwoFaChannelUpdate {
+    Changed(TwoFaChannelResponse),
+    Pending(PhoneVerificationResponse),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhoneVerificationResponse {
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFaChannelResponse {
+    pub channel: String,
+    // The number as stored, normalized to E.164
+    #[serde(rename = "phoneNumber", default, skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+}
+
+impl From<TwoFaChannel> for TwoFaChannelResponse {
+    fn from(channel: TwoFaChannel) -> Self {
+        match channel {
+            TwoFaChannel::Email => Self { channel: "email".to_owned(), phone_number: None },
+            TwoFaChannel::Sms(phone_number) => Self {
+                channel: "sms".to_owned(),
+                phone_number: Some(phone_number.as_ref().to_owned()),
+            },
+        }
+    }
+}
@@ -1,15 +1,20 @@
 use axum::{http::StatusCode, response::IntoResponse, Json};
 use axum::extract::State;
+use axum_extra::extract::cookie::Cookie;
 use axum_extra::extract::CookieJar;
-use serde::Deserialize;
+use chrono::Utc;
 use crate::app_state::AppState;
-use crate::domain::data_stores::{LoginAttemptId, TwoFaCode};
+use crate::domain::data_stores::{LoginAttemptId, RecoveryCode, RecoveryCodeStoreError, TwoFACodeStoreError, TwoFaCode, TwoFaCodeHash, UserStoreError};
 use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::user::User;
+use crate::utils::auth::{has_login_attempt_cookie, start_session, trust_device};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{LOGIN_ATTEMPT_COOKIE_NAME, LOGIN_ATTEMPT_COOKIE_PATH, TOTP_ENCRYPTION_KEY, TWO_FA_CODE_HMAC_KEY};
 
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    client_info: ClientInfo,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>){
     let email = match Email::parse(request.email) {
@@ -22,48 +27,158 @@ pub async fn verify_2fa(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
     };
 
-    let two_fa_code = match TwoFaCode::parse( request.two_fa_code.clone()) {
-        Ok(two_fa_code) => two_fa_code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+    let second_factor = match TwoFaCode::parse(request.two_fa_code.clone()) {
+        Ok(two_fa_code) => SecondFactor::Code(two_fa_code),
+        Err(_) => match RecoveryCode::parse(request.two_fa_code.clone()) {
+            Ok(recovery_code) => SecondFactor::RecoveryCode(recovery_code),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials))
+        }
     };
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-    // Call `two_fa_code_store.get_code`. If the call fails
-    // return a `AuthAPIError::IncorrectCredentials`.
-    let code_tuple = match two_fa_code_store.get_code(&email).await {
-        Ok(code_tuple) => code_tuple,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials))
+    // The login must be finished by the browser that started it
+    if !has_login_attempt_cookie(&jar, &login_attempt_id) {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    // The 2FA code store lock is only held to look up and count the attempt. Checking a
+    // recovery code runs Argon2 over each of the user's codes, which mustn't block every
+    // other login.
+    let code_tuple = {
+        let mut two_fa_code_store = state.two_fa_code_store.write().await;
+        // Call `two_fa_code_store.get_code`. If the call fails
+        // return a `AuthAPIError::IncorrectCredentials`.
+        let code_tuple = match two_fa_code_store.get_code(&login_attempt_id).await {
+            Ok(code_tuple) => code_tuple,
+            Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials))
+        };
+
+        // The login attempt must be one of the user's
+        if code_tuple.0 != email {
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+
+        // Wrong codes use up the login attempt, so codes can't be guessed
+        match two_fa_code_store.record_attempt(&login_attempt_id).await {
+            Ok(_) => {}
+            Err(TwoFACodeStoreError::TooManyAttempts) => return (jar, Err(AuthAPIError::TooManyAttempts)),
+            Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        code_tuple
     };
 
-    if code_tuple.0.as_ref() != login_attempt_id.as_ref() || code_tuple.1.as_ref() != two_fa_code.as_ref()   {
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let code_is_valid = match second_factor {
+        SecondFactor::Code(two_fa_code) => check_code(&user, &two_fa_code, &code_tuple.1, &login_attempt_id, &state).await,
+        SecondFactor::RecoveryCode(recovery_code) => use_recovery_code(&email, &recovery_code, &state).await,
+    };
+    match code_is_valid {
+        Ok(true) => {}
+        Ok(false) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(e) => return (jar, Err(e))
+    }
+
+    // remove 2fa code from store. Only one of several requests with the same code gets
+    // to remove it, so each code starts a single session.
+    if state.two_fa_code_store.write().await.remove_code(&login_attempt_id).await.is_err() {
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
-    // Call the generate_auth_cookie function defined in the auth module.
-    // If the function call fails return AuthAPIError::UnexpectedError.
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    // Later logins from this browser skip 2FA. The device is trusted before the session is
+    // started, so a failure can't leave the client with session cookies next to the error.
+    let device_cookie = match request.remember_device {
+        true => match trust_device(&user, client_info.clone(), &state).await {
+            Ok(device_cookie) => Some(device_cookie),
+            Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+        },
+        false => None,
+    };
+
+    // Start a new session for the user.
+    // If the call fails return AuthAPIError::UnexpectedError.
+    let (auth_cookie, refresh_cookie) = match start_session(&user, client_info, &state).await {
+        Ok(cookies) => cookies,
         Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let mut updated_jar = jar
+        .add(auth_cookie)
+        .add(refresh_cookie)
+        .remove(Cookie::build(LOGIN_ATTEMPT_COOKIE_NAME).path(LOGIN_ATTEMPT_COOKIE_PATH));
+    if let Some(device_cookie) = device_cookie {
+        updated_jar = updated_jar.add(device_cookie);
+    }
+
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+// What the user entered as their second factor
+enum SecondFactor {
+    Code(TwoFaCode),
+    // Stands in for the code when the user lost access to their second factor
+    RecoveryCode(RecoveryCode),
+}
 
-    // remove 2fa code from store
-    match two_fa_code_store.remove_code(&email).await {
-        Ok(_) =>  (updated_jar, Ok(StatusCode::OK.into_response())),
-        Err(_) => (updated_jar, Err(AuthAPIError::IncorrectCredentials))
+// Users with an authenticator app enter its code rather than the one we generated
+async fn check_code(
+    user: &User,
+    code: &TwoFaCode,
+    expected: &TwoFaCodeHash,
+    login_attempt_id: &LoginAttemptId,
+    state: &AppState,
+) -> Result<bool, AuthAPIError> {
+    if !user.has_totp() {
+        return Ok(expected.matches(code, login_attempt_id, &TWO_FA_CODE_HMAC_KEY));
     }
+    use_totp_code(user, code, state).await
+}
 
+// Checks the code against the user's authenticator app, and uses up its time step if it matches
+pub(crate) async fn use_totp_code(user: &User, code: &TwoFaCode, state: &AppState) -> Result<bool, AuthAPIError> {
+    let totp = match &user.totp {
+        Some(totp) if totp.confirmed => totp,
+        _ => return Ok(false),
+    };
 
+    let step = match totp.verify(code, &TOTP_ENCRYPTION_KEY, Utc::now().timestamp()) {
+        Ok(Some(step)) => step,
+        Ok(None) => return Ok(false),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+    // Each step is only accepted once, even by concurrent requests
+    match state.user_store.write().await.use_totp_step(&user.email, step).await {
+        Ok(_) => Ok(true),
+        Err(UserStoreError::TotpStepAlreadyUsed) => Ok(false),
+        Err(_) => Err(AuthAPIError::UnexpectedError),
+    }
 }
 
+// Checks the code against the user's unused recovery codes, and uses it up if it matches
+pub(crate) async fn use_recovery_code(email: &Email, code: &RecoveryCode, state: &AppState) -> Result<bool, AuthAPIError> {
+    let hashes = state
+        .recovery_code_store
+        .read()
+        .await
+        .get_codes(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
 
-#[derive(Deserialize)]
-pub struct Verify2FARequest {
-    pub email: String,
-    #[serde(rename = "loginAttemptId")]
-    pub login_attempt_id: String,
-    #[serde(rename = "2FACode")]
-    pub two_fa_code: String
+    for hash in hashes {
+        if hash.verify_raw_password(code.as_ref()).await.is_err() {
+            continue;
+        }
+        // Removing fails if a concurrent request used the code first
+        return match state.recovery_code_store.write().await.remove_code(email, &hash).await {
+            Ok(_) => Ok(true),
+            Err(RecoveryCodeStoreError::CodeNotFound) => Ok(false),
+            Err(_) => Err(AuthAPIError::UnexpectedError),
+        };
+    }
+    Ok(false)
+}
 
-}
\ No newline at end of file
+// Shared with auth-service-client
+pub use auth_service_client::types::Verify2FARequest;
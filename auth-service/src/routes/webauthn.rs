@@ -0,0 +1,401 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::extract::State;
+use axum_extra::extract::CookieJar;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{LoginAttemptId, WebAuthnCredentialStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::webauthn::{
+    parse_attestation_object, user_handle, AuthenticatorData, ClientData, CredentialId, WebAuthnCeremony,
+    WebAuthnChallenge, WebAuthnCredential, COSE_ALGORITHM_ES256, WEBAUTHN_TIMEOUT_SECONDS,
+};
+use crate::utils::auth::{authenticate_cookie, start_session};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID, WEBAUTHN_RP_NAME};
+
+// Start registering a passkey for the logged in user. The options are meant for
+// `navigator.credentials.create()`, once the binary fields are decoded.
+pub async fn passkey_registration_options(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    // Authenticators refuse to register a second passkey for the same account
+    let exclude_credentials = match state.webauthn_credential_store.read().await.get_credentials(&email).await {
+        Ok(credentials) => credentials.into_iter().map(CredentialDescriptor::from).collect(),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let challenge = WebAuthnChallenge::default();
+    let ceremony = WebAuthnCeremony::Registration { email: email.clone() };
+    if state.webauthn_challenge_store.write().await.add_challenge(challenge.clone(), ceremony).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let options = CreationOptions {
+        rp: RelyingParty { id: WEBAUTHN_RP_ID.to_owned(), name: WEBAUTHN_RP_NAME.to_owned() },
+        user: UserEntity {
+            id: user_handle(&email),
+            name: email.as_ref().to_owned(),
+            display_name: email.as_ref().to_owned(),
+        },
+        challenge: challenge.as_ref().to_owned(),
+        pub_key_cred_params: vec![CredentialParameters { credential_type: PUBLIC_KEY.to_owned(), alg: COSE_ALGORITHM_ES256 }],
+        timeout: WEBAUTHN_TIMEOUT_SECONDS * 1000,
+        exclude_credentials,
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "preferred".to_owned(),
+            user_verification: "preferred".to_owned(),
+        },
+        attestation: "none".to_owned(),
+    };
+    (jar, Ok(Json(OptionsResponse { public_key: options })))
+}
+
+// Finish registering a passkey with what `navigator.credentials.create()` returned
+pub async fn register_passkey(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<RegistrationCredential>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match authenticate_cookie(&jar, &state).await {
+        Ok(claims) => claims,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let email = match Email::parse(claims.sub) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken))
+    };
+
+    let (client_data_json, attestation_object) = match (decode(&request.response.client_data_json), decode(&request.response.attestation_object)) {
+        (Ok(client_data_json), Ok(attestation_object)) => (client_data_json, attestation_object),
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    let ceremony = match take_ceremony(&client_data_json, "webauthn.create", &state).await {
+        Ok(ceremony) => ceremony,
+        Err(e) => return (jar, Err(e))
+    };
+    // The challenge must have been handed out to this user, for a registration
+    if ceremony != (WebAuthnCeremony::Registration { email: email.clone() }) {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    let attested_credential = match parse_attestation_object(&attestation_object, &WEBAUTHN_RP_ID) {
+        Ok(AuthenticatorData { attested_credential: Some(credential), .. }) => credential,
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+    if attested_credential.id.as_ref() != request.id {
+        return (jar, Err(AuthAPIError::InvalidCredentials));
+    }
+
+    let credential = WebAuthnCredential::new(attested_credential.id, email, attested_credential.public_key, 0);
+    let response = PasskeyResponse { id: credential.id.as_ref().to_owned() };
+    match state.webauthn_credential_store.write().await.add_credential(credential).await {
+        Ok(_) => (jar, Ok((StatusCode::CREATED, Json(response)))),
+        Err(WebAuthnCredentialStoreError::CredentialAlreadyExists) => (jar, Err(AuthAPIError::PasskeyAlreadyRegistered)),
+        Err(_) => (jar, Err(AuthAPIError::UnexpectedError))
+    }
+}
+
+// Start logging in with a passkey, for `navigator.credentials.get()`.
+// With the login attempt of a password login that asked for 2FA, the passkey is the second
+// factor. Otherwise it's a passwordless login, and the authenticator picks the passkey.
+pub async fn passkey_login_options(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<PasskeyLoginOptionsRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (ceremony, allow_credentials) = match (request.email, request.login_attempt_id) {
+        (Some(email), Some(login_attempt_id)) => {
+            let (email, login_attempt_id) = match (Email::parse(email), LoginAttemptId::parse(login_attempt_id)) {
+                (Ok(email), Ok(login_attempt_id)) => (email, login_attempt_id),
+                _ => return (jar, Err(AuthAPIError::InvalidCredentials))
+            };
+            if let Err(e) = check_login_attempt(&email, &login_attempt_id, &state).await {
+                return (jar, Err(e));
+            }
+
+            let credentials = match state.webauthn_credential_store.read().await.get_credentials(&email).await {
+                Ok(credentials) => credentials,
+                Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+            };
+            if credentials.is_empty() {
+                return (jar, Err(AuthAPIError::PasskeyNotFound));
+            }
+            let allow_credentials = credentials.into_iter().map(CredentialDescriptor::from).collect();
+            (WebAuthnCeremony::SecondFactor { email, login_attempt_id }, allow_credentials)
+        }
+        (None, None) => (WebAuthnCeremony::Login, vec![]),
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    // The password already was the first factor. Without one, the authenticator must verify the user.
+    let user_verification = match ceremony {
+        WebAuthnCeremony::Login => "required",
+        _ => "preferred",
+    };
+
+    let challenge = WebAuthnChallenge::default();
+    if state.webauthn_challenge_store.write().await.add_challenge(challenge.clone(), ceremony).await.is_err() {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    let options = RequestOptions {
+        challenge: challenge.as_ref().to_owned(),
+        timeout: WEBAUTHN_TIMEOUT_SECONDS * 1000,
+        rp_id: WEBAUTHN_RP_ID.to_owned(),
+        allow_credentials,
+        user_verification: user_verification.to_owned(),
+    };
+    (jar, Ok(Json(OptionsResponse { public_key: options })))
+}
+
+// Finish logging in with what `navigator.credentials.get()` returned
+pub async fn passkey_login(
+    State(state): State<AppState>,
+    client_info: ClientInfo,
+    jar: CookieJar,
+    Json(request): Json<AssertionCredential>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let decoded = (
+        decode(&request.response.client_data_json),
+        decode(&request.response.authenticator_data),
+        decode(&request.response.signature),
+        CredentialId::parse(request.id),
+    );
+    let (client_data_json, authenticator_data, signature, credential_id) = match decoded {
+        (Ok(client_data_json), Ok(authenticator_data), Ok(signature), Ok(credential_id)) => {
+            (client_data_json, authenticator_data, signature, credential_id)
+        }
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials))
+    };
+
+    let ceremony = match take_ceremony(&client_data_json, "webauthn.get", &state).await {
+        Ok(ceremony) => ceremony,
+        Err(e) => return (jar, Err(e))
+    };
+
+    let credential = match state.webauthn_credential_store.read().await.get_credential(&credential_id).await {
+        Ok(credential) => credential,
+        Err(WebAuthnCredentialStoreError::CredentialNotFound) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    let parsed_authenticator_data = match AuthenticatorData::parse(&authenticator_data, &WEBAUTHN_RP_ID) {
+        Ok(parsed_authenticator_data) => parsed_authenticator_data,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials))
+    };
+
+    match &ceremony {
+        WebAuthnCeremony::SecondFactor { email, login_attempt_id } => {
+            if &credential.email != email {
+                return (jar, Err(AuthAPIError::IncorrectCredentials));
+            }
+            // The login attempt may have been finished some other way in the meantime
+            if let Err(e) = check_login_attempt(email, login_attempt_id, &state).await {
+                return (jar, Err(e));
+            }
+        }
+        WebAuthnCeremony::Login => {
+            if !parsed_authenticator_data.user_verified() {
+                return (jar, Err(AuthAPIError::IncorrectCredentials));
+            }
+        }
+        WebAuthnCeremony::Registration { .. } => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    }
+
+    if credential.verify_signature(&authenticator_data, &client_data_json, &signature).is_err() {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+    // A counter going backwards means the passkey was cloned
+    if !credential.is_sign_count_valid(parsed_authenticator_data.sign_count) {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+    if state
+        .webauthn_credential_store
+        .write()
+        .await
+        .update_sign_count(&credential.id, parsed_authenticator_data.sign_count)
+        .await
+        .is_err()
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError));
+    }
+
+    if let WebAuthnCeremony::SecondFactor { login_attempt_id, .. } = &ceremony {
+        if state.two_fa_code_store.write().await.remove_code(login_attempt_id).await.is_err() {
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+    }
+
+    let user = match state.user_store.read().await.get_user(&credential.email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+    // Passwordless logins skip `login`, so its check is repeated here
+    if !user.email_verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
+    let (auth_cookie, refresh_cookie) = match start_session(&user, client_info, &state).await {
+        Ok(cookies) => cookies,
+        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError))
+    };
+
+    (jar.add(auth_cookie).add(refresh_cookie), Ok(StatusCode::OK.into_response()))
+}
+
+// Checks the client data and takes its challenge, so the response can't be replayed
+async fn take_ceremony(client_data_json: &[u8], ceremony_type: &str, state: &AppState) -> Result<WebAuthnCeremony, AuthAPIError> {
+    let client_data = ClientData::parse(client_data_json, ceremony_type, &WEBAUTHN_ORIGIN)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    let challenge = client_data.challenge().map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .take_challenge(&challenge)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)
+}
+
+// The login attempt must be one of the user's pending ones
+async fn check_login_attempt(email: &Email, login_attempt_id: &LoginAttemptId, state: &AppState) -> Result<(), AuthAPIError> {
+    match state.two_fa_code_store.read().await.get_code(login_attempt_id).await {
+        Ok((pending_email, _)) if &pending_email == email => Ok(()),
+        _ => Err(AuthAPIError::IncorrectCredentials),
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, AuthAPIError> {
+    URL_SAFE_NO_PAD.decode(value).map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+const PUBLIC_KEY: &str = "public-key";
+
+// Binary fields are base64url encoded, here and in the requests
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OptionsResponse<T> {
+    #[serde(rename = "publicKey")]
+    pub public_key: T,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub challenge: String,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    // In milliseconds
+    pub timeout: u64,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub attestation: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    // In milliseconds
+    pub timeout: u64,
+    pub rp_id: String,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+impl From<WebAuthnCredential> for CredentialDescriptor {
+    fn from(credential: WebAuthnCredential) -> Self {
+        Self { credential_type: PUBLIC_KEY.to_owned(), id: credential.id.as_ref().to_owned() }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyLoginOptionsRequest {
+    pub email: Option<String>,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyResponse {
+    pub id: String,
+}
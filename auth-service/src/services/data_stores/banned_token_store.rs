@@ -1,54 +1,103 @@
-use std::collections::HashSet;
-use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError, UserStoreError};
+use std::collections::HashMap;
+use chrono::Utc;
+use crate::domain::data_stores::{BannedToken, BannedTokenStore, BannedTokenStoreError};
+use crate::services::revocation_feed::{RevocationEvent, RevocationFeed};
 
 #[derive(Default, Debug, Clone)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    // Each banned jti is stored next to the expiration timestamp of its token
+    tokens: HashMap<String, i64>,
+    // Single instance setups publish their bans straight to the feed
+    feed: Option<RevocationFeed>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
-        if self.tokens.contains(&token) {
+    async fn add_token(&mut self, jti: String, exp: i64) -> Result<(), BannedTokenStoreError> {
+        // Expired tokens no longer need a ban
+        let now = Utc::now().timestamp();
+        self.tokens.retain(|_, token_exp| *token_exp > now);
+
+        if self.tokens.contains_key(&jti) {
             return Err(BannedTokenStoreError::TokenAlreadyBanned)
         }
-        self.tokens.insert(token);
+        self.tokens.insert(jti.clone(), exp);
+        if let Some(feed) = &self.feed {
+            feed.publish(RevocationEvent::Revoked(BannedToken { id: jti, exp }));
+        }
         Ok(())
     }
 
-    async fn is_token_banned(&self, token: String) -> bool {
-        self.tokens.contains(&token)
+    async fn is_token_banned(&self, jti: String) -> bool {
+        matches!(self.tokens.get(&jti), Some(exp) if *exp > Utc::now().timestamp())
+    }
+
+    async fn get_banned_tokens(&self) -> Result<Vec<BannedToken>, BannedTokenStoreError> {
+        let now = Utc::now().timestamp();
+        Ok(self
+            .tokens
+            .iter()
+            .filter(|(_, exp)| **exp > now)
+            .map(|(id, exp)| BannedToken { id: id.clone(), exp: *exp })
+            .collect())
     }
 }
 
 
 impl HashsetBannedTokenStore {
     pub fn new() -> Self {
-        Self { tokens: HashSet::new() }
+        Self { tokens: HashMap::new(), feed: None }
+    }
+
+    pub fn with_feed(feed: RevocationFeed) -> Self {
+        Self { tokens: HashMap::new(), feed: Some(feed) }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashSet;
 
+    fn exp_in(seconds: i64) -> i64 {
+        Utc::now().timestamp() + seconds
+    }
 
     #[tokio::test]
     pub async fn test_add_token() {
         let mut store = HashsetBannedTokenStore::new();
-        store.add_token("token".to_string()).await.unwrap();
-        assert!(store.is_token_banned("token".to_string()).await);
+        store.add_token("jti".to_string(), exp_in(600)).await.unwrap();
+        assert!(store.is_token_banned("jti".to_string()).await);
     }
 
     #[tokio::test]
     pub async fn test_is_token_banned() {
         let mut store = HashsetBannedTokenStore::new();
-        assert!(!store.is_token_banned("token".to_string()).await);
+        assert!(!store.is_token_banned("jti".to_string()).await);
 
         // add token to store
-        store.add_token("token2".to_string()).await.unwrap();
-        assert!(store.is_token_banned("token2".to_string()).await);
+        store.add_token("jti2".to_string(), exp_in(600)).await.unwrap();
+        assert!(store.is_token_banned("jti2".to_string()).await);
+    }
+
+    #[tokio::test]
+    pub async fn test_ban_ends_when_token_expires() {
+        let mut store = HashsetBannedTokenStore::new();
+        store.add_token("jti".to_string(), exp_in(-1)).await.unwrap();
+        assert!(!store.is_token_banned("jti".to_string()).await);
+        assert_eq!(store.get_banned_tokens().await, Ok(vec![]));
     }
 
-}
\ No newline at end of file
+    #[tokio::test]
+    pub async fn test_bans_are_published() {
+        let feed = RevocationFeed::new();
+        let mut receiver = feed.subscribe();
+        let mut store = HashsetBannedTokenStore::with_feed(feed);
+
+        let exp = exp_in(600);
+        store.add_token("jti".to_string(), exp).await.unwrap();
+
+        let token = BannedToken { id: "jti".to_string(), exp };
+        assert_eq!(receiver.recv().await.unwrap(), RevocationEvent::Revoked(token.clone()));
+        assert_eq!(store.get_banned_tokens().await, Ok(vec![token]));
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use crate::domain::data_stores::{ApiClient, ApiClientId, ApiClientStore, ApiClientStoreError};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapApiClientStore {
+    clients: HashMap<ApiClientId, ApiClient>,
+}
+
+#[async_trait::async_trait]
+impl ApiClientStore for HashmapApiClientStore {
+    async fn add_client(&mut self, client: ApiClient) -> Result<(), ApiClientStoreError> {
+        if self.clients.contains_key(&client.id) {
+            return Err(ApiClientStoreError::ClientAlreadyExists);
+        }
+        self.clients.insert(client.id.clone(), client);
+        Ok(())
+    }
+
+    async fn get_client(&self, id: &ApiClientId) -> Result<ApiClient, ApiClientStoreError> {
+        self.clients.get(id).cloned().ok_or(ApiClientStoreError::ClientNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::data_stores::ApiClientSecret;
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapApiClientStore::default();
+        let id = ApiClientId::parse("app-service".to_owned()).unwrap();
+        let client = ApiClient::new(id.clone(), &ApiClientSecret::default());
+
+        assert_eq!(store.add_client(client.clone()).await, Ok(()));
+        assert_eq!(store.get_client(&id).await, Ok(client.clone()));
+        assert_eq!(store.add_client(client).await, Err(ApiClientStoreError::ClientAlreadyExists));
+
+        let unknown = ApiClientId::parse("unknown".to_owned()).unwrap();
+        assert_eq!(store.get_client(&unknown).await, Err(ApiClientStoreError::ClientNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_verify_secret() {
+        let secret = ApiClientSecret::default();
+        let client = ApiClient::new(ApiClientId::parse("app-service".to_owned()).unwrap(), &secret);
+
+        assert!(client.verify_secret(&secret));
+        assert!(!client.verify_secret(&ApiClientSecret::default()));
+        // Only the hash is kept around
+        assert_ne!(client.secret_hash, secret.as_ref());
+    }
+}
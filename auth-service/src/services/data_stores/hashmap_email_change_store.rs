@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use crate::domain::data_stores::{
+    EmailChange, EmailChangeStore, EmailChangeStoreError, EmailChangeToken, EMAIL_CHANGE_TTL_SECONDS,
+};
+
+// Changes are keyed by their confirm token
+#[derive(Default, Debug, Clone)]
+pub struct HashmapEmailChangeStore {
+    changes: HashMap<EmailChangeToken, (EmailChange, DateTime<Utc>)>,
+}
+
+impl HashmapEmailChangeStore {
+    fn remove(&mut self, confirm_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError> {
+        match self.changes.remove(confirm_token) {
+            Some((change, expires_at)) if expires_at > Utc::now() => Ok(change),
+            _ => Err(EmailChangeStoreError::ChangeNotFound),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailChangeStore for HashmapEmailChangeStore {
+    async fn add_change(&mut self, change: EmailChange) -> Result<(), EmailChangeStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(EMAIL_CHANGE_TTL_SECONDS as i64);
+        // Expired changes are never taken, so drop them here
+        self.changes.retain(|_, (_, expiry)| *expiry > Utc::now());
+        self.changes.insert(change.confirm_token.clone(), (change, expires_at));
+        Ok(())
+    }
+
+    async fn take_change(&mut self, confirm_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError> {
+        self.remove(confirm_token)
+    }
+
+    async fn cancel_change(&mut self, cancel_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError> {
+        let confirm_token = self.changes.iter()
+            .find(|(_, (change, _))| &change.cancel_token == cancel_token)
+            .map(|(confirm_token, _)| confirm_token.clone())
+            .ok_or(EmailChangeStoreError::ChangeNotFound)?;
+        self.remove(&confirm_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::email::Email;
+
+    fn change() -> EmailChange {
+        let email = Email::parse("old@test.com".to_owned()).unwrap();
+        let new_email = Email::parse("new@test.com".to_owned()).unwrap();
+        EmailChange::new(email, new_email)
+    }
+
+    #[tokio::test]
+    async fn test_take_change() {
+        let mut store = HashmapEmailChangeStore::default();
+        let change = change();
+
+        assert_eq!(store.add_change(change.clone()).await, Ok(()));
+        assert_eq!(store.take_change(&change.confirm_token).await, Ok(change.clone()));
+        assert_eq!(store.take_change(&change.confirm_token).await, Err(EmailChangeStoreError::ChangeNotFound));
+        // Confirmed changes can't be cancelled anymore
+        assert_eq!(store.cancel_change(&change.cancel_token).await, Err(EmailChangeStoreError::ChangeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_change() {
+        let mut store = HashmapEmailChangeStore::default();
+        let change = change();
+
+        store.add_change(change.clone()).await.unwrap();
+        assert_eq!(store.cancel_change(&change.cancel_token).await, Ok(change.clone()));
+        assert_eq!(store.take_change(&change.confirm_token).await, Err(EmailChangeStoreError::ChangeNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expired_changes_are_not_taken() {
+        let mut store = HashmapEmailChangeStore::default();
+        let change = change();
+        store.changes.insert(change.confirm_token.clone(), (change.clone(), Utc::now() - Duration::seconds(1)));
+
+        assert_eq!(store.take_change(&change.confirm_token).await, Err(EmailChangeStoreError::ChangeNotFound));
+    }
+}
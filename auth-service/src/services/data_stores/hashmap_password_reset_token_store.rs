@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use crate::domain::data_stores::{
+    PasswordResetToken, PasswordResetTokenStore, PasswordResetTokenStoreError, PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+use crate::domain::email::Email;
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapPasswordResetTokenStore {
+    tokens: HashMap<PasswordResetToken, (Email, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(&mut self, token: PasswordResetToken, email: Email) -> Result<(), PasswordResetTokenStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(PASSWORD_RESET_TOKEN_TTL_SECONDS as i64);
+        // Expired tokens are never taken, so drop them here
+        self.tokens.retain(|_, (_, expiry)| *expiry > Utc::now());
+        self.tokens.insert(token, (email, expires_at));
+        Ok(())
+    }
+
+    async fn take_token(&mut self, token: &PasswordResetToken) -> Result<Email, PasswordResetTokenStoreError> {
+        match self.tokens.remove(token) {
+            Some((email, expires_at)) if expires_at > Utc::now() => Ok(email),
+            _ => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tokens_are_single_use() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let token = PasswordResetToken::default();
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+
+        assert_eq!(store.add_token(token.clone(), email.clone()).await, Ok(()));
+        assert_eq!(store.take_token(&token).await, Ok(email));
+        assert_eq!(store.take_token(&token).await, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expired_tokens_are_not_taken() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let token = PasswordResetToken::default();
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+        store.tokens.insert(token.clone(), (email, Utc::now() - Duration::seconds(1)));
+
+        assert_eq!(store.take_token(&token).await, Err(PasswordResetTokenStoreError::TokenNotFound));
+    }
+}
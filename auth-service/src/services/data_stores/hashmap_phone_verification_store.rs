@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use crate::domain::data_stores::{
+    PhoneVerification, PhoneVerificationStore, PhoneVerificationStoreError, PHONE_VERIFICATION_TTL_SECONDS,
+};
+use crate::domain::email::Email;
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapPhoneVerificationStore {
+    verifications: HashMap<Email, (PhoneVerification, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationStore for HashmapPhoneVerificationStore {
+    async fn add_verification(&mut self, email: Email, verification: PhoneVerification) -> Result<(), PhoneVerificationStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(PHONE_VERIFICATION_TTL_SECONDS as i64);
+        // Expired verifications are never taken, so drop them here
+        self.verifications.retain(|_, (_, expiry)| *expiry > Utc::now());
+        self.verifications.insert(email, (verification, expires_at));
+        Ok(())
+    }
+
+    async fn take_verification(&mut self, email: &Email) -> Result<PhoneVerification, PhoneVerificationStoreError> {
+        match self.verifications.remove(email) {
+            Some((verification, expires_at)) if expires_at > Utc::now() => Ok(verification),
+            _ => Err(PhoneVerificationStoreError::VerificationNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::data_stores::TwoFaCode;
+    use crate::domain::phone_number::PhoneNumber;
+
+    fn verification(number: &str) -> PhoneVerification {
+        let phone_number = PhoneNumber::parse(number.to_owned()).unwrap();
+        let code_hash = TwoFaCode::default().hash(&phone_number, &[7; 32]);
+        PhoneVerification { phone_number, code_hash }
+    }
+
+    #[tokio::test]
+    async fn test_verifications_are_single_use() {
+        let mut store = HashmapPhoneVerificationStore::default();
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+        let verification = verification("+14155550123");
+
+        assert_eq!(store.add_verification(email.clone(), verification.clone()).await, Ok(()));
+        assert_eq!(store.take_verification(&email).await, Ok(verification));
+        assert_eq!(store.take_verification(&email).await, Err(PhoneVerificationStoreError::VerificationNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_new_verification_replaces_pending_one() {
+        let mut store = HashmapPhoneVerificationStore::default();
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+        store.add_verification(email.clone(), verification("+14155550123")).await.unwrap();
+        let second = verification("+14155550199");
+        store.add_verification(email.clone(), second.clone()).await.unwrap();
+
+        assert_eq!(store.take_verification(&email).await, Ok(second));
+    }
+
+    #[tokio::test]
+    async fn test_expired_verifications_are_not_taken() {
+        let mut store = HashmapPhoneVerificationStore::default();
+        let email = Email::parse("test@test.com".to_owned()).unwrap();
+        store.verifications.insert(email.clone(), (verification("+14155550123"), Utc::now() - Duration::seconds(1)));
+
+        assert_eq!(store.take_verification(&email).await, Err(PhoneVerificationStoreError::VerificationNotFound));
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use crate::domain::data_stores::{RecoveryCodeStore, RecoveryCodeStoreError};
+use crate::domain::email::Email;
+use crate::domain::HashedPassword;
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapRecoveryCodeStore {
+    codes: HashMap<Email, Vec<HashedPassword>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn set_codes(&mut self, email: &Email, codes: Vec<HashedPassword>) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn get_codes(&self, email: &Email) -> Result<Vec<HashedPassword>, RecoveryCodeStoreError> {
+        Ok(self.codes.get(email).cloned().unwrap_or_default())
+    }
+
+    async fn remove_code(&mut self, email: &Email, code: &HashedPassword) -> Result<(), RecoveryCodeStoreError> {
+        let codes = self.codes.get_mut(email).ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+        let position = codes.iter().position(|c| c == code).ok_or(RecoveryCodeStoreError::CodeNotFound)?;
+        codes.remove(position);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::data_stores::RecoveryCode;
+
+    #[tokio::test]
+    async fn test_codes_are_single_use() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let code = RecoveryCode::default().hash().await.unwrap();
+        let other_code = RecoveryCode::default().hash().await.unwrap();
+
+        store.set_codes(&email, vec![code.clone(), other_code.clone()]).await.unwrap();
+        assert_eq!(store.remove_code(&email, &code).await, Ok(()));
+        assert_eq!(store.remove_code(&email, &code).await, Err(RecoveryCodeStoreError::CodeNotFound));
+        assert_eq!(store.get_codes(&email).await, Ok(vec![other_code]));
+
+        // A new set replaces the old one
+        store.set_codes(&email, vec![code.clone()]).await.unwrap();
+        assert_eq!(store.get_codes(&email).await, Ok(vec![code]));
+    }
+
+    #[tokio::test]
+    async fn test_recovery_code_format() {
+        let code = RecoveryCode::default();
+        assert_eq!(RecoveryCode::parse(code.as_ref().to_owned()), Ok(code.clone()));
+        assert_eq!(RecoveryCode::parse(code.as_ref().to_uppercase().replace('-', "")), Ok(code.clone()));
+        assert!(RecoveryCode::parse("123456".to_owned()).is_err());
+
+        let hash = code.hash().await.unwrap();
+        assert!(hash.verify_raw_password(code.as_ref()).await.is_ok());
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use chrono::Utc;
+use crate::domain::{
+    data_stores::{RefreshToken, RefreshTokenFamilyId, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+    email::Email,
+};
+use crate::utils::auth::REFRESH_TOKEN_TTL_SECONDS;
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapRefreshTokenStore {
+    // Each record is stored next to its expiration timestamp
+    tokens: HashMap<RefreshToken, (RefreshTokenRecord, i64)>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+        session_epoch: i64,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS;
+        let record = RefreshTokenRecord { email, family_id, used: false, session_epoch };
+        self.tokens.insert(token, (record, expires_at));
+        Ok(())
+    }
+
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        match self.tokens.get(token) {
+            Some((record, expires_at)) if *expires_at > Utc::now().timestamp() => Ok(record.clone()),
+            _ => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        match self.tokens.get_mut(token) {
+            Some((record, _)) if record.used => Err(RefreshTokenStoreError::TokenAlreadyUsed),
+            Some((record, _)) => {
+                record.used = true;
+                Ok(())
+            }
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn revoke_family(&mut self, family_id: &RefreshTokenFamilyId) -> Result<(), RefreshTokenStoreError> {
+        self.tokens.retain(|_, (record, _)| &record.family_id != family_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let mail = Email("test@test.com".to_string());
+        let token = RefreshToken::default();
+        let family_id = RefreshTokenFamilyId::default();
+        let add_res = store.add_token(token.clone(), mail.clone(), family_id.clone(), 1).await;
+        assert_eq!(add_res, Ok(()));
+
+        let record = store.get_token(&token).await.unwrap();
+        assert_eq!(record, RefreshTokenRecord { email: mail, family_id, used: false, session_epoch: 1 });
+
+        // Not found
+        let get_err = store.get_token(&RefreshToken::default()).await;
+        assert_eq!(get_err, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_token_used() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let mail = Email("test@test.com".to_string());
+        let token = RefreshToken::default();
+        store.add_token(token.clone(), mail, RefreshTokenFamilyId::default(), 0).await.unwrap();
+
+        assert_eq!(store.mark_token_used(&token).await, Ok(()));
+        assert!(store.get_token(&token).await.unwrap().used);
+        assert_eq!(store.mark_token_used(&token).await, Err(RefreshTokenStoreError::TokenAlreadyUsed));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let mail = Email("test@test.com".to_string());
+        let family_id = RefreshTokenFamilyId::default();
+        let token_1 = RefreshToken::default();
+        let token_2 = RefreshToken::default();
+        let other_token = RefreshToken::default();
+        store.add_token(token_1.clone(), mail.clone(), family_id.clone(), 0).await.unwrap();
+        store.add_token(token_2.clone(), mail.clone(), family_id.clone(), 0).await.unwrap();
+        store.add_token(other_token.clone(), mail, RefreshTokenFamilyId::default(), 0).await.unwrap();
+
+        assert_eq!(store.revoke_family(&family_id).await, Ok(()));
+        assert_eq!(store.get_token(&token_1).await, Err(RefreshTokenStoreError::TokenNotFound));
+        assert_eq!(store.get_token(&token_2).await, Err(RefreshTokenStoreError::TokenNotFound));
+        assert!(store.get_token(&other_token).await.is_ok());
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use chrono::Utc;
+use crate::domain::{
+    data_stores::{Session, SessionId, SessionStore, SessionStoreError},
+    email::Email,
+};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<SessionId, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError> {
+        self.sessions.get(id).cloned().ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| &session.email == email)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn touch_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        let session = self.sessions.get_mut(id).ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen = Utc::now();
+        Ok(())
+    }
+
+    async fn remove_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        self.sessions.remove(id).map(|_| ()).ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn remove_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        self.sessions.retain(|_, session| &session.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let mail = Email("test@test.com".to_string());
+        let session = Session::new(mail.clone(), Some("curl/8.0".to_string()), Some("127.0.0.1".to_string()));
+        let other_session = Session::new(Email("other@test.com".to_string()), None, None);
+        assert_eq!(store.add_session(session.clone()).await, Ok(()));
+        store.add_session(other_session).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await, Ok(session.clone()));
+        assert_eq!(store.get_sessions(&mail).await, Ok(vec![session]));
+
+        // Not found
+        let get_err = store.get_session(&SessionId::default()).await;
+        assert_eq!(get_err, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_touch_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = Session::new(Email("test@test.com".to_string()), None, None);
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(store.touch_session(&session.id).await, Ok(()));
+        assert!(store.get_session(&session.id).await.unwrap().last_seen >= session.last_seen);
+        assert_eq!(store.touch_session(&SessionId::default()).await, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_remove_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let mail = Email("test@test.com".to_string());
+        let session_1 = Session::new(mail.clone(), None, None);
+        let session_2 = Session::new(mail.clone(), None, None);
+        let other_session = Session::new(Email("other@test.com".to_string()), None, None);
+        for session in [&session_1, &session_2, &other_session] {
+            store.add_session(session.clone()).await.unwrap();
+        }
+
+        assert_eq!(store.remove_session(&session_1.id).await, Ok(()));
+        assert_eq!(store.remove_session(&session_1.id).await, Err(SessionStoreError::SessionNotFound));
+
+        assert_eq!(store.remove_sessions(&mail).await, Ok(()));
+        assert_eq!(store.get_sessions(&mail).await, Ok(vec![]));
+        assert!(store.get_session(&other_session.id).await.is_ok());
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::Utc;
+use crate::domain::data_stores::{SigningKey, SigningKeyState, SigningKeyStore, SigningKeyStoreError};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapSigningKeyStore {
+    // Kept in insertion order so the newest key comes last
+    keys: Vec<SigningKey>,
+}
+
+impl HashmapSigningKeyStore {
+    fn has_active_key(&self) -> bool {
+        self.keys.iter().any(|key| key.state == SigningKeyState::Active)
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for HashmapSigningKeyStore {
+    async fn add_key(&mut self, key: SigningKey) -> Result<(), SigningKeyStoreError> {
+        if self.keys.iter().any(|existing| existing.kid == key.kid) {
+            return Err(SigningKeyStoreError::UnexpectedError);
+        }
+        if key.state == SigningKeyState::Active && self.has_active_key() {
+            return Err(SigningKeyStoreError::ActiveKeyConflict);
+        }
+        self.keys.push(key);
+        Ok(())
+    }
+
+    async fn get_keys(&self) -> Result<Vec<SigningKey>, SigningKeyStoreError> {
+        Ok(self.keys.clone())
+    }
+
+    async fn rotate_key(&mut self, current_kid: &str, new_key: SigningKey) -> Result<(), SigningKeyStoreError> {
+        let current = self
+            .keys
+            .iter_mut()
+            .find(|key| key.kid == current_kid && key.state == SigningKeyState::Active)
+            .ok_or(SigningKeyStoreError::ActiveKeyConflict)?;
+        current.state = SigningKeyState::VerifyOnly;
+        current.state_changed_at = Utc::now();
+
+        self.keys.push(SigningKey { state: SigningKeyState::Active, ..new_key });
+        Ok(())
+    }
+
+    async fn retire_key(&mut self, kid: &str) -> Result<(), SigningKeyStoreError> {
+        let key = self
+            .keys
+            .iter_mut()
+            .find(|key| key.kid == kid)
+            .ok_or(SigningKeyStoreError::KeyNotFound)?;
+        match key.state {
+            SigningKeyState::Active => Err(SigningKeyStoreError::ActiveKeyConflict),
+            SigningKeyState::Retired => Ok(()),
+            SigningKeyState::VerifyOnly => {
+                key.state = SigningKeyState::Retired;
+                key.state_changed_at = Utc::now();
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::data_stores::EncryptedPrivateKey;
+
+    fn test_key(kid: &str, state: SigningKeyState) -> SigningKey {
+        SigningKey {
+            kid: kid.to_owned(),
+            algorithm: "HS256".to_owned(),
+            private_key: EncryptedPrivateKey::encrypt("c2VjcmV0", &[7; 32]).unwrap(),
+            public_key: None,
+            state,
+            created_at: Utc::now(),
+            state_changed_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_keys() {
+        let mut store = HashmapSigningKeyStore::default();
+        let key = test_key("kid-1", SigningKeyState::Active);
+        assert_eq!(store.add_key(key.clone()).await, Ok(()));
+        assert_eq!(store.get_keys().await, Ok(vec![key]));
+
+        // Only one key can be active at a time
+        let add_res = store.add_key(test_key("kid-2", SigningKeyState::Active)).await;
+        assert_eq!(add_res, Err(SigningKeyStoreError::ActiveKeyConflict));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_key() {
+        let mut store = HashmapSigningKeyStore::default();
+        store.add_key(test_key("kid-1", SigningKeyState::Active)).await.unwrap();
+
+        let rotate_res = store.rotate_key("kid-1", test_key("kid-2", SigningKeyState::Active)).await;
+        assert_eq!(rotate_res, Ok(()));
+
+        let states: Vec<_> = store.get_keys().await.unwrap().into_iter().map(|key| (key.kid, key.state)).collect();
+        assert_eq!(states, vec![
+            ("kid-1".to_owned(), SigningKeyState::VerifyOnly),
+            ("kid-2".to_owned(), SigningKeyState::Active),
+        ]);
+
+        // Another instance already rotated away from kid-1
+        let rotate_res = store.rotate_key("kid-1", test_key("kid-3", SigningKeyState::Active)).await;
+        assert_eq!(rotate_res, Err(SigningKeyStoreError::ActiveKeyConflict));
+    }
+
+    #[tokio::test]
+    async fn test_retire_key() {
+        let mut store = HashmapSigningKeyStore::default();
+        store.add_key(test_key("kid-1", SigningKeyState::Active)).await.unwrap();
+        store.rotate_key("kid-1", test_key("kid-2", SigningKeyState::Active)).await.unwrap();
+
+        assert_eq!(store.retire_key("kid-1").await, Ok(()));
+        assert_eq!(store.retire_key("kid-2").await, Err(SigningKeyStoreError::ActiveKeyConflict));
+        assert_eq!(store.retire_key("unknown").await, Err(SigningKeyStoreError::KeyNotFound));
+
+        let keys = store.get_keys().await.unwrap();
+        assert_eq!(keys[0].state, SigningKeyState::Retired);
+    }
+}
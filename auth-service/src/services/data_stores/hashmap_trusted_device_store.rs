@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use crate::domain::{
+    data_stores::{TrustedDevice, TrustedDeviceId, TrustedDeviceStore, TrustedDeviceStoreError},
+    email::Email,
+};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapTrustedDeviceStore {
+    devices: HashMap<TrustedDeviceId, TrustedDevice>,
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for HashmapTrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.insert(device.id.clone(), device);
+        Ok(())
+    }
+
+    async fn get_device(&self, id: &TrustedDeviceId) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        match self.devices.get(id) {
+            Some(device) if !device.is_expired() => Ok(device.clone()),
+            _ => Err(TrustedDeviceStoreError::DeviceNotFound),
+        }
+    }
+
+    async fn get_devices(&self, email: &Email) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let mut devices: Vec<TrustedDevice> = self
+            .devices
+            .values()
+            .filter(|device| &device.email == email && !device.is_expired())
+            .cloned()
+            .collect();
+        devices.sort_by_key(|device| device.created_at);
+        Ok(devices)
+    }
+
+    async fn remove_device(&mut self, id: &TrustedDeviceId) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.remove(id).map(|_| ()).ok_or(TrustedDeviceStoreError::DeviceNotFound)
+    }
+
+    async fn remove_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.retain(|_, device| &device.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_devices() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let mail = Email("test@test.com".to_string());
+        let device = TrustedDevice::new(mail.clone(), Some("curl/8.0".to_string()), None, Duration::days(30));
+        let other_device = TrustedDevice::new(Email("other@test.com".to_string()), None, None, Duration::days(30));
+        store.add_device(device.clone()).await.unwrap();
+        store.add_device(other_device.clone()).await.unwrap();
+
+        assert_eq!(store.get_device(&device.id).await, Ok(device.clone()));
+        assert_eq!(store.get_devices(&mail).await, Ok(vec![device]));
+    }
+
+    #[tokio::test]
+    async fn test_expired_devices_are_not_returned() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let mail = Email("test@test.com".to_string());
+        let device = TrustedDevice::new(mail.clone(), None, None, Duration::seconds(-1));
+        store.add_device(device.clone()).await.unwrap();
+
+        assert_eq!(store.get_device(&device.id).await, Err(TrustedDeviceStoreError::DeviceNotFound));
+        assert_eq!(store.get_devices(&mail).await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_remove_devices() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let mail = Email("test@test.com".to_string());
+        let device = TrustedDevice::new(mail.clone(), None, None, Duration::days(30));
+        let other_device = TrustedDevice::new(mail.clone(), None, None, Duration::days(30));
+        store.add_device(device.clone()).await.unwrap();
+        store.add_device(other_device.clone()).await.unwrap();
+
+        store.remove_device(&device.id).await.unwrap();
+        assert_eq!(store.get_device(&device.id).await, Err(TrustedDeviceStoreError::DeviceNotFound));
+        assert_eq!(store.remove_device(&device.id).await, Err(TrustedDeviceStoreError::DeviceNotFound));
+
+        store.remove_devices(&mail).await.unwrap();
+        assert_eq!(store.get_devices(&mail).await, Ok(vec![]));
+    }
+}
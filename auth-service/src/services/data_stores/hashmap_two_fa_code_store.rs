@@ -1,13 +1,48 @@
 use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use chrono::{DateTime, Duration, Utc};
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFaCode, TwoFACodeStore, TwoFACodeStoreError},
+    data_stores::{LoginAttemptId, TwoFaCode, TwoFaCodeHash, TwoFaCodeLimits, TwoFACodeStore, TwoFACodeStoreError},
     email::Email,
 };
+use crate::utils::constants::TWO_FA_CODE_HMAC_KEY;
+
+#[derive(Debug, Clone)]
+struct PendingCode {
+    email: Email,
+    hash: TwoFaCodeHash,
+    // Tries at the code so far
+    attempts: u32,
+    resends: u32,
+    sent_at: DateTime<Utc>,
+    // Order the login attempts were started in, the oldest ones go first once a user has too many
+    sequence: u64,
+}
 
 #[derive(Default, Debug, Clone)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFaCode)>,
+    codes: HashMap<LoginAttemptId, PendingCode>,
+    limits: TwoFaCodeLimits,
+    next_sequence: u64,
+}
+
+impl HashmapTwoFACodeStore {
+    pub fn new(limits: TwoFaCodeLimits) -> Self {
+        Self { codes: HashMap::new(), limits, next_sequence: 0 }
+    }
+
+    // Drop the user's oldest login attempts until there's room for one more
+    fn make_room(&mut self, email: &Email) {
+        let mut pending: Vec<(u64, LoginAttemptId)> = self.codes.iter()
+            .filter(|(_, pending)| &pending.email == email)
+            .map(|(id, pending)| (pending.sequence, id.clone()))
+            .collect();
+        pending.sort_by_key(|(sequence, _)| *sequence);
+        let keep = self.limits.max_pending_attempts.saturating_sub(1) as usize;
+        let excess = pending.len().saturating_sub(keep);
+        for (_, id) in pending.into_iter().take(excess) {
+            self.codes.remove(&id);
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -18,33 +53,61 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFaCode,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        self.make_room(&email);
+        let hash = code.hash(&login_attempt_id, &TWO_FA_CODE_HMAC_KEY);
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.codes.insert(login_attempt_id, PendingCode { email, hash, attempts: 0, resends: 0, sent_at: Utc::now(), sequence });
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.entry(email.clone()) {
-            Entry::Occupied(mut entry) => {
-                entry.remove();
-                Ok(())
-            }
-            Entry::Vacant(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+    async fn remove_code(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+        match self.codes.remove(login_attempt_id) {
+            Some(_) => Ok(()),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
 
-    async fn get_code(&self, email: &Email) -> Result<(LoginAttemptId, TwoFaCode), TwoFACodeStoreError> {
-        let result = self.codes.get(email);
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        self.codes.retain(|_, pending| &pending.email != email);
+        Ok(())
+    }
+
+    async fn get_code(&self, login_attempt_id: &LoginAttemptId) -> Result<(Email, TwoFaCodeHash), TwoFACodeStoreError> {
+        let result = self.codes.get(login_attempt_id);
         match result {
-            Some(result) => Ok(result.clone()),
+            Some(pending) => Ok((pending.email.clone(), pending.hash.clone())),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn record_attempt(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+        let pending = self.codes.get_mut(login_attempt_id).ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+        pending.attempts += 1;
+        // Every try before this one was a wrong code
+        if pending.attempts > self.limits.max_failed_attempts {
+            self.codes.remove(login_attempt_id);
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+        Ok(())
+    }
+
+    async fn resend_code(&mut self, login_attempt_id: &LoginAttemptId, code: TwoFaCode) -> Result<(), TwoFACodeStoreError> {
+        let pending = self.codes.get_mut(login_attempt_id).ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+        if Utc::now() < pending.sent_at + Duration::seconds(self.limits.resend_cooldown_seconds as i64) {
+            return Err(TwoFACodeStoreError::ResendTooSoon);
+        }
+        if pending.resends >= self.limits.max_resends {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+        pending.hash = code.hash(login_attempt_id, &TWO_FA_CODE_HMAC_KEY);
+        pending.resends += 1;
+        pending.sent_at = Utc::now();
+        Ok(())
+    }
 }
 #[cfg(test)]
 mod tests {
-    use crate::domain::HashedPassword;
-    use crate::domain::user::User;
-    use crate::services::data_stores::hashmap_user_store::HashmapUserStore;
     use super::*;
 
     #[tokio::test]
@@ -55,9 +118,11 @@ mod tests {
         let code = TwoFaCode::default();
         let add_res = store.add_code(mail.clone(), login_attempt_id.clone(), code.clone()).await;
         assert_eq!(add_res, Ok(()));
-        let result = store.get_code(&mail).await.unwrap();
-        assert_eq!(result.0, login_attempt_id);
-        assert_eq!(result.1, code);
+        let result = store.get_code(&login_attempt_id).await.unwrap();
+        assert_eq!(result.0, mail);
+        // Only the hash is kept around
+        assert_ne!(result.1.as_ref(), code.as_ref());
+        assert!(result.1.matches(&code, &login_attempt_id, &TWO_FA_CODE_HMAC_KEY));
     }
 
     #[tokio::test]
@@ -67,15 +132,34 @@ mod tests {
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFaCode::default();
         let add_res = store.add_code(mail.clone(), login_attempt_id.clone(), code.clone()).await;
-        let remove_res = store.remove_code(&mail).await;
+        assert_eq!(add_res, Ok(()));
+        let remove_res = store.remove_code(&login_attempt_id).await;
         assert_eq!(remove_res, Ok(()));
 
         // Not found
-        let another_mail = Email("test2@test.com".to_string());
-        let remove_err = store.remove_code(&another_mail).await;
+        let remove_err = store.remove_code(&LoginAttemptId::default()).await;
         assert_eq!(remove_err, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
     }
 
+    #[tokio::test]
+    async fn test_remove_codes() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let mail = Email("test@test.com".to_string());
+        let other_mail = Email("other@test.com".to_string());
+        let first_attempt = LoginAttemptId::default();
+        let second_attempt = LoginAttemptId::default();
+        let other_attempt = LoginAttemptId::default();
+        store.add_code(mail.clone(), first_attempt.clone(), TwoFaCode::default()).await.unwrap();
+        store.add_code(mail.clone(), second_attempt.clone(), TwoFaCode::default()).await.unwrap();
+        store.add_code(other_mail, other_attempt.clone(), TwoFaCode::default()).await.unwrap();
+
+        assert_eq!(store.remove_codes(&mail).await, Ok(()));
+        assert_eq!(store.get_code(&first_attempt).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+        assert_eq!(store.get_code(&second_attempt).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+        // Other users' login attempts are kept
+        assert!(store.get_code(&other_attempt).await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_get_code() {
         let mut store = HashmapTwoFACodeStore::default();
@@ -83,13 +167,116 @@ mod tests {
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFaCode::default();
         let add_res = store.add_code(mail.clone(), login_attempt_id.clone(), code.clone()).await;
-        let get_res = store.get_code(&mail).await;
-        assert_eq!(get_res, Ok((login_attempt_id, code)));
+        assert_eq!(add_res, Ok(()));
+        let get_res = store.get_code(&login_attempt_id).await;
+        assert_eq!(get_res, Ok((mail.clone(), code.hash(&login_attempt_id, &TWO_FA_CODE_HMAC_KEY))));
 
         // Not found
-        let another_mail = Email("test2@test.com".to_string());
-        let get_err = store.remove_code(&another_mail).await;
+        let get_err = store.get_code(&LoginAttemptId::default()).await;
         assert_eq!(get_err, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
     }
 
+    #[tokio::test]
+    async fn test_concurrent_login_attempts() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let mail = Email("test@test.com".to_string());
+        let first_attempt_id = LoginAttemptId::default();
+        let second_attempt_id = LoginAttemptId::default();
+        let first_code = TwoFaCode::default();
+        let second_code = TwoFaCode::default();
+        store.add_code(mail.clone(), first_attempt_id.clone(), first_code.clone()).await.unwrap();
+        store.add_code(mail.clone(), second_attempt_id.clone(), second_code.clone()).await.unwrap();
+
+        // Both attempts keep their own code
+        let (_, first_hash) = store.get_code(&first_attempt_id).await.unwrap();
+        assert!(first_hash.matches(&first_code, &first_attempt_id, &TWO_FA_CODE_HMAC_KEY));
+        let (_, second_hash) = store.get_code(&second_attempt_id).await.unwrap();
+        assert!(second_hash.matches(&second_code, &second_attempt_id, &TWO_FA_CODE_HMAC_KEY));
+
+        // Finishing one leaves the other be
+        store.remove_code(&first_attempt_id).await.unwrap();
+        assert_eq!(store.get_code(&second_attempt_id).await.map(|(email, _)| email), Ok(mail));
+    }
+
+    #[tokio::test]
+    async fn test_max_pending_attempts() {
+        let mut store = HashmapTwoFACodeStore::new(TwoFaCodeLimits { max_pending_attempts: 2, ..Default::default() });
+        let mail = Email("test@test.com".to_string());
+        let other_mail = Email("test2@test.com".to_string());
+        let attempt_ids: Vec<LoginAttemptId> = (0..3).map(|_| LoginAttemptId::default()).collect();
+        let other_attempt_id = LoginAttemptId::default();
+        store.add_code(other_mail.clone(), other_attempt_id.clone(), TwoFaCode::default()).await.unwrap();
+        for id in &attempt_ids {
+            store.add_code(mail.clone(), id.clone(), TwoFaCode::default()).await.unwrap();
+        }
+
+        // The oldest attempt made room for the newest
+        assert_eq!(store.get_code(&attempt_ids[0]).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+        assert!(store.get_code(&attempt_ids[1]).await.is_ok());
+        assert!(store.get_code(&attempt_ids[2]).await.is_ok());
+        // Other users' attempts don't count
+        assert!(store.get_code(&other_attempt_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_record_attempt() {
+        let mut store = HashmapTwoFACodeStore::new(TwoFaCodeLimits { max_failed_attempts: 2, ..Default::default() });
+        let mail = Email("test@test.com".to_string());
+        let login_attempt_id = LoginAttemptId::default();
+        store.add_code(mail.clone(), login_attempt_id.clone(), TwoFaCode::default()).await.unwrap();
+
+        // Two wrong codes use the attempt up
+        for _ in 0..2 {
+            assert_eq!(store.record_attempt(&login_attempt_id).await, Ok(()));
+        }
+        assert_eq!(store.record_attempt(&login_attempt_id).await, Err(TwoFACodeStoreError::TooManyAttempts));
+        assert_eq!(store.get_code(&login_attempt_id).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+        // A new login attempt starts counting over
+        let new_attempt_id = LoginAttemptId::default();
+        store.add_code(mail.clone(), new_attempt_id.clone(), TwoFaCode::default()).await.unwrap();
+        assert_eq!(store.record_attempt(&new_attempt_id).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_resend_code() {
+        let limits = TwoFaCodeLimits { max_resends: 1, resend_cooldown_seconds: 0, ..Default::default() };
+        let mut store = HashmapTwoFACodeStore::new(limits);
+        let mail = Email("test@test.com".to_string());
+        let login_attempt_id = LoginAttemptId::default();
+        store.add_code(mail.clone(), login_attempt_id.clone(), TwoFaCode::default()).await.unwrap();
+        store.record_attempt(&login_attempt_id).await.unwrap();
+
+        let code = TwoFaCode::default();
+        assert_eq!(store.resend_code(&login_attempt_id, code.clone()).await, Ok(()));
+        let (resent_email, hash) = store.get_code(&login_attempt_id).await.unwrap();
+        assert_eq!(resent_email, mail);
+        assert!(hash.matches(&code, &login_attempt_id, &TWO_FA_CODE_HMAC_KEY));
+        // Resending doesn't give back tries
+        assert_eq!(store.codes[&login_attempt_id].attempts, 1);
+
+        assert_eq!(store.resend_code(&login_attempt_id, TwoFaCode::default()).await, Err(TwoFACodeStoreError::TooManyResends));
+    }
+
+    #[tokio::test]
+    async fn test_resend_cooldown() {
+        let mut store = HashmapTwoFACodeStore::default();
+        let mail = Email("test@test.com".to_string());
+        let login_attempt_id = LoginAttemptId::default();
+        store.add_code(mail.clone(), login_attempt_id.clone(), TwoFaCode::default()).await.unwrap();
+
+        assert_eq!(store.resend_code(&login_attempt_id, TwoFaCode::default()).await, Err(TwoFACodeStoreError::ResendTooSoon));
+        let unknown = LoginAttemptId::default();
+        assert_eq!(store.resend_code(&unknown, TwoFaCode::default()).await, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[test]
+    fn test_code_hash_is_keyed() {
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFaCode::default();
+        let hash = code.hash(&login_attempt_id, &TWO_FA_CODE_HMAC_KEY);
+        assert!(hash.matches(&code, &login_attempt_id, &TWO_FA_CODE_HMAC_KEY));
+        // Without the key, the stored hash can't be matched
+        assert!(!hash.matches(&code, &login_attempt_id, &[0; 32]));
+    }
 }
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use crate::domain::data_stores::{UserStore, UserStoreError};
-use crate::domain::email::Email;
 use crate::domain::HashedPassword;
-use crate::domain::user::User;
+use crate::domain::email::Email;
+use crate::domain::totp::{EncryptedTotpSecret, Totp};
+use crate::domain::user::{TwoFaChannel, User};
 
 #[derive(Default, Debug, Clone)]
 pub struct HashmapUserStore {
@@ -32,18 +33,99 @@ impl UserStore for HashmapUserStore {
 
      async  fn validate_user(&self, email: &Email, raw_password: &str ) -> Result<(), UserStoreError> {
         let user = self.get_user(email).await?;
-        if !user.password.verify_raw_password(raw_password).await.is_err() {
+        if user.password.verify_raw_password(raw_password).await.is_ok() {
             Ok(())
         } else {
             Err(UserStoreError::InvalidCredentials)
         }
     }
+
+    async fn increment_session_epoch(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.session_epoch += 1;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn set_totp_secret(&mut self, email: &Email, secret: EncryptedTotpSecret) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.totp = Some(Totp::new(secret));
+        Ok(())
+    }
+
+    async fn confirm_totp(&mut self, email: &Email, step: i64) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        let totp = user.totp.as_mut().ok_or(UserStoreError::UnexpectedError)?;
+        totp.confirmed = true;
+        totp.last_used_step = Some(step);
+        user.requires_2fa = true;
+        Ok(())
+    }
+
+    async fn use_totp_step(&mut self, email: &Email, step: i64) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        match user.totp.as_mut() {
+            Some(totp) if totp.confirmed && totp.last_used_step.is_none_or(|last| step > last) => {
+                totp.last_used_step = Some(step);
+                Ok(())
+            }
+            _ => Err(UserStoreError::TotpStepAlreadyUsed),
+        }
+    }
+
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = requires_2fa;
+        if !requires_2fa {
+            user.totp = None;
+        }
+        Ok(())
+    }
+
+    async fn set_two_fa_channel(&mut self, email: &Email, channel: TwoFaChannel) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.two_fa_channel = channel;
+        Ok(())
+    }
+
+    async fn set_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email_verified = true;
+        Ok(())
+    }
+
+    async fn set_password(&mut self, email: &Email, password: HashedPassword) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.password = password;
+        Ok(())
+    }
+
+    async fn change_email(&mut self, email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        if self.users.contains_key(&new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self.users.remove(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email = new_email.clone();
+        self.users.insert(new_email, user);
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.remove(email) {
+            Some(_) => Ok(()),
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::phone_number::PhoneNumber;
 
     #[tokio::test]
     async fn test_add_user() {
@@ -79,4 +161,132 @@ mod tests {
         assert!(store.validate_user(&user.email, raw_password.as_ref()).await.is_ok());
         assert!(store.validate_user(&user.email, raw_password_wrong.as_ref()).await.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_increment_session_epoch() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+
+        store.increment_session_epoch(&user.email).await.unwrap();
+        assert_eq!(store.get_user(&user.email).await.unwrap().session_epoch, user.session_epoch + 1);
+
+        let unknown = Email("unknown@mail.com".to_string());
+        assert_eq!(store.increment_session_epoch(&unknown).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_totp_steps_are_single_use() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+        let secret = EncryptedTotpSecret::parse("AAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string()).unwrap();
+        store.set_totp_secret(&user.email, secret).await.unwrap();
+
+        // Steps can't be used before the enrollment is confirmed
+        assert_eq!(store.use_totp_step(&user.email, 10).await, Err(UserStoreError::TotpStepAlreadyUsed));
+
+        store.confirm_totp(&user.email, 10).await.unwrap();
+        assert!(store.get_user(&user.email).await.unwrap().requires_2fa);
+        assert_eq!(store.use_totp_step(&user.email, 10).await, Err(UserStoreError::TotpStepAlreadyUsed));
+        assert_eq!(store.use_totp_step(&user.email, 11).await, Ok(()));
+        assert_eq!(store.use_totp_step(&user.email, 9).await, Err(UserStoreError::TotpStepAlreadyUsed));
+    }
+
+    #[tokio::test]
+    async fn test_set_requires_2fa() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+
+        store.set_requires_2fa(&user.email, true).await.unwrap();
+        assert!(store.get_user(&user.email).await.unwrap().requires_2fa);
+
+        // Turning 2FA off drops the authenticator app
+        let secret = EncryptedTotpSecret::parse("AAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string()).unwrap();
+        store.set_totp_secret(&user.email, secret).await.unwrap();
+        store.confirm_totp(&user.email, 10).await.unwrap();
+        store.set_requires_2fa(&user.email, false).await.unwrap();
+        let updated = store.get_user(&user.email).await.unwrap();
+        assert!(!updated.requires_2fa);
+        assert_eq!(updated.totp, None);
+
+        let unknown = Email("unknown@mail.com".to_string());
+        assert_eq!(store.set_requires_2fa(&unknown, true).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_two_fa_channel() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+        assert_eq!(store.get_user(&user.email).await.unwrap().two_fa_channel, TwoFaChannel::Email);
+
+        let channel = TwoFaChannel::Sms(PhoneNumber::parse("+14155550123".to_string()).unwrap());
+        store.set_two_fa_channel(&user.email, channel.clone()).await.unwrap();
+        assert_eq!(store.get_user(&user.email).await.unwrap().two_fa_channel, channel);
+
+        let unknown = Email("unknown@mail.com".to_string());
+        assert_eq!(store.set_two_fa_channel(&unknown, TwoFaChannel::Email).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_email_verified() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+        assert!(!store.get_user(&user.email).await.unwrap().email_verified);
+
+        store.set_email_verified(&user.email).await.unwrap();
+        assert!(store.get_user(&user.email).await.unwrap().email_verified);
+
+        let unknown = Email("unknown@mail.com".to_string());
+        assert_eq!(store.set_email_verified(&unknown).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_password() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+
+        let new_password = HashedPassword("new-password".to_string());
+        store.set_password(&user.email, new_password.clone()).await.unwrap();
+        assert_eq!(store.get_user(&user.email).await.unwrap().password, new_password);
+
+        let unknown = Email("unknown@mail.com".to_string());
+        assert_eq!(store.set_password(&unknown, new_password).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        let other = User::new(Email("usr2@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+        store.add_user(other.clone()).await.unwrap();
+
+        assert_eq!(store.change_email(&user.email, other.email.clone()).await, Err(UserStoreError::UserAlreadyExists));
+
+        let new_email = Email("new@mail.com".to_string());
+        store.change_email(&user.email, new_email.clone()).await.unwrap();
+        assert_eq!(store.get_user(&user.email).await, Err(UserStoreError::UserNotFound));
+        let moved = store.get_user(&new_email).await.unwrap();
+        assert_eq!(moved.email, new_email);
+        assert_eq!(moved.password, user.password);
+
+        let unknown = Email("unknown@mail.com".to_string());
+        assert_eq!(store.change_email(&unknown, Email("other@mail.com".to_string())).await, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let mut store = HashmapUserStore::default();
+        let user = User::new(Email("usr1@mail.com".to_string()), HashedPassword("password".to_string()), false);
+        store.add_user(user.clone()).await.unwrap();
+
+        assert_eq!(store.delete_user(&user.email).await, Ok(()));
+        assert_eq!(store.get_user(&user.email).await, Err(UserStoreError::UserNotFound));
+        assert_eq!(store.delete_user(&user.email).await, Err(UserStoreError::UserNotFound));
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use crate::domain::data_stores::{WebAuthnChallengeStore, WebAuthnChallengeStoreError};
+use crate::domain::webauthn::{WebAuthnCeremony, WebAuthnChallenge, WEBAUTHN_TIMEOUT_SECONDS};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapWebAuthnChallengeStore {
+    challenges: HashMap<WebAuthnChallenge, (WebAuthnCeremony, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for HashmapWebAuthnChallengeStore {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge, ceremony: WebAuthnCeremony) -> Result<(), WebAuthnChallengeStoreError> {
+        let expires_at = Utc::now() + Duration::seconds(WEBAUTHN_TIMEOUT_SECONDS as i64);
+        // Expired challenges are never taken, so drop them here
+        self.challenges.retain(|_, (_, expiry)| *expiry > Utc::now());
+        self.challenges.insert(challenge, (ceremony, expires_at));
+        Ok(())
+    }
+
+    async fn take_challenge(&mut self, challenge: &WebAuthnChallenge) -> Result<WebAuthnCeremony, WebAuthnChallengeStoreError> {
+        match self.challenges.remove(challenge) {
+            Some((ceremony, expires_at)) if expires_at > Utc::now() => Ok(ceremony),
+            _ => Err(WebAuthnChallengeStoreError::ChallengeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_challenges_are_single_use() {
+        let mut store = HashmapWebAuthnChallengeStore::default();
+        let challenge = WebAuthnChallenge::default();
+
+        assert_eq!(store.add_challenge(challenge.clone(), WebAuthnCeremony::Login).await, Ok(()));
+        assert_eq!(store.take_challenge(&challenge).await, Ok(WebAuthnCeremony::Login));
+        assert_eq!(store.take_challenge(&challenge).await, Err(WebAuthnChallengeStoreError::ChallengeNotFound));
+    }
+}
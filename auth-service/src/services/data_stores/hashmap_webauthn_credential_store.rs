@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use crate::domain::data_stores::{WebAuthnCredentialStore, WebAuthnCredentialStoreError};
+use crate::domain::email::Email;
+use crate::domain::webauthn::{CredentialId, WebAuthnCredential};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapWebAuthnCredentialStore {
+    credentials: HashMap<CredentialId, WebAuthnCredential>,
+}
+
+#[async_trait::async_trait]
+impl WebAuthnCredentialStore for HashmapWebAuthnCredentialStore {
+    async fn add_credential(&mut self, credential: WebAuthnCredential) -> Result<(), WebAuthnCredentialStoreError> {
+        if self.credentials.contains_key(&credential.id) {
+            return Err(WebAuthnCredentialStoreError::CredentialAlreadyExists);
+        }
+        self.credentials.insert(credential.id.clone(), credential);
+        Ok(())
+    }
+
+    async fn get_credential(&self, id: &CredentialId) -> Result<WebAuthnCredential, WebAuthnCredentialStoreError> {
+        self.credentials.get(id).cloned().ok_or(WebAuthnCredentialStoreError::CredentialNotFound)
+    }
+
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, WebAuthnCredentialStoreError> {
+        Ok(self.credentials.values().filter(|credential| &credential.email == email).cloned().collect())
+    }
+
+    async fn update_sign_count(&mut self, id: &CredentialId, sign_count: u32) -> Result<(), WebAuthnCredentialStoreError> {
+        let credential = self.credentials.get_mut(id).ok_or(WebAuthnCredentialStoreError::CredentialNotFound)?;
+        credential.sign_count = sign_count;
+        Ok(())
+    }
+
+    async fn remove_credentials(&mut self, email: &Email) -> Result<(), WebAuthnCredentialStoreError> {
+        self.credentials.retain(|_, credential| &credential.email != email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_credentials() {
+        let mut store = HashmapWebAuthnCredentialStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let id = CredentialId::parse("Y3JlZA".to_owned()).unwrap();
+        let credential = WebAuthnCredential::new(id.clone(), email.clone(), vec![4; 65], 0);
+
+        assert_eq!(store.add_credential(credential.clone()).await, Ok(()));
+        assert_eq!(store.add_credential(credential.clone()).await, Err(WebAuthnCredentialStoreError::CredentialAlreadyExists));
+        assert_eq!(store.get_credentials(&email).await, Ok(vec![credential.clone()]));
+
+        assert_eq!(store.update_sign_count(&id, 3).await, Ok(()));
+        assert_eq!(store.get_credential(&id).await.unwrap().sign_count, 3);
+
+        let unknown = CredentialId::parse("b3RoZXI".to_owned()).unwrap();
+        assert_eq!(store.get_credential(&unknown).await, Err(WebAuthnCredentialStoreError::CredentialNotFound));
+
+        assert_eq!(store.remove_credentials(&email).await, Ok(()));
+        assert_eq!(store.get_credentials(&email).await, Ok(vec![]));
+    }
+}
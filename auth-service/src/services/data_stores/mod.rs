@@ -3,6 +3,31 @@ pub mod hashmap_user_store;
 pub mod banned_token_store;
 
 pub mod hashmap_two_fa_code_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_signing_key_store;
+pub mod hashmap_session_store;
+pub mod hashmap_api_client_store;
+pub mod hashmap_recovery_code_store;
+pub mod hashmap_webauthn_credential_store;
+pub mod hashmap_webauthn_challenge_store;
+pub mod hashmap_trusted_device_store;
+pub mod hashmap_email_verification_token_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_email_change_store;
+pub mod hashmap_phone_verification_store;
 pub mod postgres_user_store;
+pub mod postgres_refresh_token_store;
+pub mod postgres_signing_key_store;
+pub mod postgres_session_store;
+pub mod postgres_api_client_store;
+pub mod postgres_recovery_code_store;
+pub mod postgres_webauthn_credential_store;
 pub mod redis_banned_token_store;
 pub mod redis_two_fa_code_store;
+pub mod redis_refresh_token_store;
+pub mod redis_webauthn_challenge_store;
+pub mod redis_trusted_device_store;
+pub mod redis_email_verification_token_store;
+pub mod redis_password_reset_token_store;
+pub mod redis_email_change_store;
+pub mod redis_phone_verification_store;
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::data_stores::{ApiClient, ApiClientId, ApiClientStore, ApiClientStoreError};
+
+// Intermediate struct that matches the DB columns exactly.
+struct PgApiClientRow {
+    id: String,
+    secret_hash: String,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<PgApiClientRow> for ApiClient {
+    type Error = ApiClientStoreError;
+
+    fn try_from(row: PgApiClientRow) -> Result<Self, Self::Error> {
+        let id = ApiClientId::parse(row.id).map_err(|_| ApiClientStoreError::UnexpectedError)?;
+        Ok(ApiClient { id, secret_hash: row.secret_hash, created_at: row.created_at })
+    }
+}
+
+pub struct PostgresApiClientStore {
+    pool: PgPool,
+}
+
+impl PostgresApiClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiClientStore for PostgresApiClientStore {
+    async fn add_client(&mut self, client: ApiClient) -> Result<(), ApiClientStoreError> {
+        sqlx::query!(
+            "INSERT INTO api_clients (id, secret_hash, created_at) VALUES ($1, $2, $3)",
+            client.id.as_ref(),
+            client.secret_hash,
+            client.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => ApiClientStoreError::ClientAlreadyExists,
+            _ => ApiClientStoreError::UnexpectedError,
+        })?;
+
+        Ok(())
+    }
+
+    async fn get_client(&self, id: &ApiClientId) -> Result<ApiClient, ApiClientStoreError> {
+        sqlx::query_as!(
+            PgApiClientRow,
+            "SELECT id, secret_hash, created_at FROM api_clients WHERE id = $1",
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| ApiClientStoreError::UnexpectedError)?
+        .ok_or(ApiClientStoreError::ClientNotFound)?
+        .try_into()
+    }
+}
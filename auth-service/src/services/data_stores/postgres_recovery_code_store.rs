@@ -0,0 +1,71 @@
+use sqlx::PgPool;
+
+use crate::domain::data_stores::{RecoveryCodeStore, RecoveryCodeStoreError};
+use crate::domain::email::Email;
+use crate::domain::HashedPassword;
+
+pub struct PostgresRecoveryCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+    async fn set_codes(&mut self, email: &Email, codes: Vec<HashedPassword>) -> Result<(), RecoveryCodeStoreError> {
+        // The old set stays valid unless the new one is stored completely
+        let mut transaction = self.pool.begin().await.map_err(|_| RecoveryCodeStoreError::UnexpectedError)?;
+
+        sqlx::query!("DELETE FROM recovery_codes WHERE email = $1", email.as_ref())
+            .execute(&mut *transaction)
+            .await
+            .map_err(|_| RecoveryCodeStoreError::UnexpectedError)?;
+
+        for code in codes {
+            sqlx::query!(
+                "INSERT INTO recovery_codes (email, code_hash) VALUES ($1, $2)",
+                email.as_ref(),
+                code.as_ref()
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|_| RecoveryCodeStoreError::UnexpectedError)?;
+        }
+
+        transaction.commit().await.map_err(|_| RecoveryCodeStoreError::UnexpectedError)
+    }
+
+    async fn get_codes(&self, email: &Email) -> Result<Vec<HashedPassword>, RecoveryCodeStoreError> {
+        let hashes = sqlx::query_scalar!("SELECT code_hash FROM recovery_codes WHERE email = $1", email.as_ref())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| RecoveryCodeStoreError::UnexpectedError)?;
+
+        hashes
+            .into_iter()
+            .map(|hash| HashedPassword::parse_password_hash(hash).map_err(|_| RecoveryCodeStoreError::UnexpectedError))
+            .collect()
+    }
+
+    async fn remove_code(&mut self, email: &Email, code: &HashedPassword) -> Result<(), RecoveryCodeStoreError> {
+        // Only one of two concurrent requests using the same code gets to delete it
+        let result = sqlx::query!(
+            "DELETE FROM recovery_codes WHERE email = $1 AND code_hash = $2",
+            email.as_ref(),
+            code.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RecoveryCodeStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RecoveryCodeStoreError::CodeNotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::{
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenFamilyId, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+        email::Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+// Intermediate struct that matches the DB columns exactly.
+struct PgRefreshTokenRow {
+    email: String,
+    family_id: String,
+    used: bool,
+    session_epoch: i64,
+}
+
+impl TryFrom<PgRefreshTokenRow> for RefreshTokenRecord {
+    type Error = RefreshTokenStoreError;
+
+    fn try_from(row: PgRefreshTokenRow) -> Result<Self, Self::Error> {
+        let email = Email::parse(row.email)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        let family_id = RefreshTokenFamilyId::parse(row.family_id)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        Ok(RefreshTokenRecord { email, family_id, used: row.used, session_epoch: row.session_epoch })
+    }
+}
+
+pub struct PostgresRefreshTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresRefreshTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for PostgresRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+        session_epoch: i64,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let expires_at = Utc::now() + chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS);
+
+        sqlx::query!(
+            "INSERT INTO refresh_tokens (token, email, family_id, session_epoch, expires_at) VALUES ($1, $2, $3, $4, $5)",
+            token.as_ref(),
+            email.as_ref(),
+            family_id.as_ref(),
+            session_epoch,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        sqlx::query_as!(
+            PgRefreshTokenRow,
+            "SELECT email, family_id, used, session_epoch FROM refresh_tokens WHERE token = $1 AND expires_at > NOW()",
+            token.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| RefreshTokenStoreError::UnexpectedError)?
+        .ok_or(RefreshTokenStoreError::TokenNotFound)?
+        .try_into()
+    }
+
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        // The `used = FALSE` guard makes the check-and-set atomic across instances
+        let result = sqlx::query!(
+            "UPDATE refresh_tokens SET used = TRUE WHERE token = $1 AND used = FALSE AND expires_at > NOW()",
+            token.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            // Tell apart a replayed token from one that does not exist
+            return match self.get_token(token).await {
+                Ok(_) => Err(RefreshTokenStoreError::TokenAlreadyUsed),
+                Err(e) => Err(e),
+            };
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_family(&mut self, family_id: &RefreshTokenFamilyId) -> Result<(), RefreshTokenStoreError> {
+        sqlx::query!(
+            "DELETE FROM refresh_tokens WHERE family_id = $1",
+            family_id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
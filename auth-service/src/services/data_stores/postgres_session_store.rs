@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{Session, SessionId, SessionStore, SessionStoreError},
+    email::Email,
+};
+
+// Intermediate struct that matches the DB columns exactly.
+struct PgSessionRow {
+    id: String,
+    email: String,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+    created_at: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+}
+
+impl TryFrom<PgSessionRow> for Session {
+    type Error = SessionStoreError;
+
+    fn try_from(row: PgSessionRow) -> Result<Self, Self::Error> {
+        let id = SessionId::parse(row.id)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+        let email = Email::parse(row.email)
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+        Ok(Session {
+            id,
+            email,
+            user_agent: row.user_agent,
+            ip_address: row.ip_address,
+            created_at: row.created_at,
+            last_seen: row.last_seen,
+        })
+    }
+}
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, email, user_agent, ip_address, created_at, last_seen)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            session.id.as_ref(),
+            session.email.as_ref(),
+            session.user_agent,
+            session.ip_address,
+            session.created_at,
+            session.last_seen
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &SessionId) -> Result<Session, SessionStoreError> {
+        sqlx::query_as!(
+            PgSessionRow,
+            "SELECT id, email, user_agent, ip_address, created_at, last_seen FROM sessions WHERE id = $1",
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?
+        .ok_or(SessionStoreError::SessionNotFound)?
+        .try_into()
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        sqlx::query_as!(
+            PgSessionRow,
+            r#"
+            SELECT id, email, user_agent, ip_address, created_at, last_seen
+            FROM sessions
+            WHERE email = $1
+            ORDER BY created_at
+            "#,
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?
+        .into_iter()
+        .map(Session::try_from)
+        .collect()
+    }
+
+    async fn touch_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "UPDATE sessions SET last_seen = NOW() WHERE id = $1",
+            id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn remove_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!("DELETE FROM sessions WHERE id = $1", id.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn remove_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        sqlx::query!("DELETE FROM sessions WHERE email = $1", email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::data_stores::{EncryptedPrivateKey, SigningKey, SigningKeyState, SigningKeyStore, SigningKeyStoreError};
+
+// Intermediate struct that matches the DB columns exactly.
+struct PgSigningKeyRow {
+    kid: String,
+    algorithm: String,
+    private_key: String,
+    public_key: Option<String>,
+    state: String,
+    created_at: DateTime<Utc>,
+    state_changed_at: DateTime<Utc>,
+}
+
+impl TryFrom<PgSigningKeyRow> for SigningKey {
+    type Error = SigningKeyStoreError;
+
+    fn try_from(row: PgSigningKeyRow) -> Result<Self, Self::Error> {
+        let state = SigningKeyState::parse(&row.state)
+            .map_err(|_| SigningKeyStoreError::UnexpectedError)?;
+        let private_key = EncryptedPrivateKey::parse(row.private_key)
+            .map_err(|_| SigningKeyStoreError::UnexpectedError)?;
+        Ok(SigningKey {
+            kid: row.kid,
+            algorithm: row.algorithm,
+            private_key,
+            public_key: row.public_key,
+            state,
+            created_at: row.created_at,
+            state_changed_at: row.state_changed_at,
+        })
+    }
+}
+
+// The partial unique index on the active state rejects a second active key
+fn map_insert_error(e: sqlx::Error) -> SigningKeyStoreError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => SigningKeyStoreError::ActiveKeyConflict,
+        _ => SigningKeyStoreError::UnexpectedError,
+    }
+}
+
+pub struct PostgresSigningKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresSigningKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for PostgresSigningKeyStore {
+    async fn add_key(&mut self, key: SigningKey) -> Result<(), SigningKeyStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO signing_keys (kid, algorithm, private_key, public_key, state, created_at, state_changed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            key.kid,
+            key.algorithm,
+            key.private_key.as_ref(),
+            key.public_key,
+            key.state.as_ref(),
+            key.created_at,
+            key.state_changed_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(map_insert_error)?;
+
+        Ok(())
+    }
+
+    async fn get_keys(&self) -> Result<Vec<SigningKey>, SigningKeyStoreError> {
+        sqlx::query_as!(
+            PgSigningKeyRow,
+            r#"
+            SELECT kid, algorithm, private_key, public_key, state, created_at, state_changed_at
+            FROM signing_keys
+            ORDER BY created_at
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|_| SigningKeyStoreError::UnexpectedError)?
+        .into_iter()
+        .map(SigningKey::try_from)
+        .collect()
+    }
+
+    async fn rotate_key(&mut self, current_kid: &str, new_key: SigningKey) -> Result<(), SigningKeyStoreError> {
+        let mut transaction = self.pool.begin().await
+            .map_err(|_| SigningKeyStoreError::UnexpectedError)?;
+
+        // The `state = 'active'` guard makes concurrent rotations from several instances race safely
+        let result = sqlx::query!(
+            "UPDATE signing_keys SET state = 'verify_only', state_changed_at = NOW() WHERE kid = $1 AND state = 'active'",
+            current_kid
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|_| SigningKeyStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SigningKeyStoreError::ActiveKeyConflict);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO signing_keys (kid, algorithm, private_key, public_key, state, created_at, state_changed_at)
+            VALUES ($1, $2, $3, $4, 'active', $5, $6)
+            "#,
+            new_key.kid,
+            new_key.algorithm,
+            new_key.private_key.as_ref(),
+            new_key.public_key,
+            new_key.created_at,
+            new_key.state_changed_at
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(map_insert_error)?;
+
+        transaction.commit().await
+            .map_err(|_| SigningKeyStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn retire_key(&mut self, kid: &str) -> Result<(), SigningKeyStoreError> {
+        let result = sqlx::query!(
+            "UPDATE signing_keys SET state = 'retired', state_changed_at = NOW() WHERE kid = $1 AND state = 'verify_only'",
+            kid
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| SigningKeyStoreError::UnexpectedError)?;
+
+        if result.rows_affected() > 0 {
+            return Ok(());
+        }
+
+        // Tell apart the active key from one that is unknown or already retired
+        let state = sqlx::query_scalar!("SELECT state FROM signing_keys WHERE kid = $1", kid)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| SigningKeyStoreError::UnexpectedError)?
+            .ok_or(SigningKeyStoreError::KeyNotFound)?;
+
+        match SigningKeyState::parse(&state) {
+            Ok(SigningKeyState::Active) => Err(SigningKeyStoreError::ActiveKeyConflict),
+            Ok(_) => Ok(()),
+            Err(_) => Err(SigningKeyStoreError::UnexpectedError),
+        }
+    }
+}
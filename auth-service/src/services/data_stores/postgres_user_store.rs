@@ -4,7 +4,9 @@ use crate::domain::{
     data_stores::{UserStore, UserStoreError},
     email::Email,
     HashedPassword,
-    user::User,
+    phone_number::PhoneNumber,
+    totp::{EncryptedTotpSecret, Totp},
+    user::{TwoFaChannel, User},
 };
 
 // Intermediate struct that matches the DB columns exactly.
@@ -13,6 +15,13 @@ struct PgUserRow {
     email: String,
     password_hash: String,
     requires_2fa: bool,
+    session_epoch: i64,
+    totp_secret: Option<String>,
+    totp_confirmed: bool,
+    totp_last_used_step: Option<i64>,
+    two_fa_channel: String,
+    phone_number: Option<String>,
+    email_verified: bool,
 }
 
 impl TryFrom<PgUserRow> for User {
@@ -23,7 +32,28 @@ impl TryFrom<PgUserRow> for User {
             .map_err(|_| UserStoreError::UnexpectedError)?;
         let password = HashedPassword::parse_password_hash(row.password_hash)
             .map_err(|_| UserStoreError::UnexpectedError)?;
-        Ok(User::new(email, password, row.requires_2fa))
+        let totp = match row.totp_secret {
+            Some(secret) => Some(Totp {
+                secret: EncryptedTotpSecret::parse(secret).map_err(|_| UserStoreError::UnexpectedError)?,
+                confirmed: row.totp_confirmed,
+                last_used_step: row.totp_last_used_step,
+            }),
+            None => None,
+        };
+        let two_fa_channel = match (row.two_fa_channel.as_str(), row.phone_number) {
+            ("email", _) => TwoFaChannel::Email,
+            ("sms", Some(number)) => TwoFaChannel::Sms(
+                PhoneNumber::parse(number).map_err(|_| UserStoreError::UnexpectedError)?,
+            ),
+            _ => return Err(UserStoreError::UnexpectedError),
+        };
+        Ok(User {
+            session_epoch: row.session_epoch,
+            totp,
+            two_fa_channel,
+            email_verified: row.email_verified,
+            ..User::new(email, password, row.requires_2fa)
+        })
     }
 }
 
@@ -42,10 +72,11 @@ impl UserStore for PostgresUserStore {
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
         // sqlx::query! checks the SQL at compile time, and bind params are passed inline
         sqlx::query!(
-            "INSERT INTO users (email, password_hash, requires_2fa) VALUES ($1, $2, $3)",
+            "INSERT INTO users (email, password_hash, requires_2fa, email_verified) VALUES ($1, $2, $3, $4)",
             user.email.as_ref(),
             user.password.as_ref(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.email_verified
         )
         .execute(&self.pool)
         .await
@@ -67,7 +98,11 @@ impl UserStore for PostgresUserStore {
         // directly into PgUserRow fields by name.
         sqlx::query_as!(
             PgUserRow,
-            "SELECT email, password_hash, requires_2fa FROM users WHERE email = $1",
+            r#"
+            SELECT email, password_hash, requires_2fa, session_epoch, totp_secret, totp_confirmed, totp_last_used_step,
+                   two_fa_channel, phone_number, email_verified
+            FROM users WHERE email = $1
+            "#,
             email.as_ref()
         )
         .fetch_optional(&self.pool)
@@ -87,5 +122,194 @@ impl UserStore for PostgresUserStore {
 
         Ok(())
     }
-    // TODO: Implement all required methods. Note that you will need to make SQL queries against our PostgreSQL instance inside these methods. Ensure to parse the password_hash.
-}
\ No newline at end of file
+
+    async fn increment_session_epoch(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET session_epoch = session_epoch + 1 WHERE email = $1",
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn set_totp_secret(&mut self, email: &Email, secret: EncryptedTotpSecret) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET totp_secret = $2, totp_confirmed = FALSE, totp_last_used_step = NULL
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+            secret.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn confirm_totp(&mut self, email: &Email, step: i64) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET totp_confirmed = TRUE, totp_last_used_step = $2, requires_2fa = TRUE
+            WHERE email = $1 AND totp_secret IS NOT NULL
+            "#,
+            email.as_ref(),
+            step
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn use_totp_step(&mut self, email: &Email, step: i64) -> Result<(), UserStoreError> {
+        // Compare and set in one statement, so concurrent requests can't both use the step
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET totp_last_used_step = $2
+            WHERE email = $1 AND totp_confirmed AND (totp_last_used_step IS NULL OR totp_last_used_step < $2)
+            "#,
+            email.as_ref(),
+            step
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::TotpStepAlreadyUsed);
+        }
+
+        Ok(())
+    }
+
+    async fn set_requires_2fa(&mut self, email: &Email, requires_2fa: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET requires_2fa = $2,
+                totp_secret = CASE WHEN $2 THEN totp_secret END,
+                totp_confirmed = totp_confirmed AND $2,
+                totp_last_used_step = CASE WHEN $2 THEN totp_last_used_step END
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+            requires_2fa
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn set_two_fa_channel(&mut self, email: &Email, channel: TwoFaChannel) -> Result<(), UserStoreError> {
+        let (channel, phone_number) = match &channel {
+            TwoFaChannel::Email => ("email", None),
+            TwoFaChannel::Sms(number) => ("sms", Some(number.as_ref())),
+        };
+        let result = sqlx::query!(
+            "UPDATE users SET two_fa_channel = $2, phone_number = $3 WHERE email = $1",
+            email.as_ref(),
+            channel,
+            phone_number
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn set_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET email_verified = TRUE WHERE email = $1",
+            email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn set_password(&mut self, email: &Email, password: HashedPassword) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET password_hash = $2 WHERE email = $1",
+            email.as_ref(),
+            password.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn change_email(&mut self, email: &Email, new_email: Email) -> Result<(), UserStoreError> {
+        // The tables referencing users follow along through ON UPDATE CASCADE, in the same statement
+        let result = sqlx::query!(
+            "UPDATE users SET email = $2 WHERE email = $1",
+            email.as_ref(),
+            new_email.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => UserStoreError::UserAlreadyExists,
+            _ => UserStoreError::UnexpectedError,
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        // Rows referencing the user go with it through ON DELETE CASCADE
+        let result = sqlx::query!("DELETE FROM users WHERE email = $1", email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| UserStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
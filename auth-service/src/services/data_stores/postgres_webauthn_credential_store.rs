@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::data_stores::{WebAuthnCredentialStore, WebAuthnCredentialStoreError};
+use crate::domain::email::Email;
+use crate::domain::webauthn::{CredentialId, WebAuthnCredential};
+
+// Intermediate struct that matches the DB columns exactly.
+struct PgWebAuthnCredentialRow {
+    id: String,
+    email: String,
+    public_key: Vec<u8>,
+    sign_count: i64,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<PgWebAuthnCredentialRow> for WebAuthnCredential {
+    type Error = WebAuthnCredentialStoreError;
+
+    fn try_from(row: PgWebAuthnCredentialRow) -> Result<Self, Self::Error> {
+        Ok(WebAuthnCredential {
+            id: CredentialId::parse(row.id).map_err(|_| WebAuthnCredentialStoreError::UnexpectedError)?,
+            email: Email::parse(row.email).map_err(|_| WebAuthnCredentialStoreError::UnexpectedError)?,
+            public_key: row.public_key,
+            sign_count: row.sign_count.try_into().map_err(|_| WebAuthnCredentialStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+pub struct PostgresWebAuthnCredentialStore {
+    pool: PgPool,
+}
+
+impl PostgresWebAuthnCredentialStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnCredentialStore for PostgresWebAuthnCredentialStore {
+    async fn add_credential(&mut self, credential: WebAuthnCredential) -> Result<(), WebAuthnCredentialStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webauthn_credentials (id, email, public_key, sign_count, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            credential.id.as_ref(),
+            credential.email.as_ref(),
+            credential.public_key,
+            credential.sign_count as i64,
+            credential.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => WebAuthnCredentialStoreError::CredentialAlreadyExists,
+            _ => WebAuthnCredentialStoreError::UnexpectedError,
+        })?;
+
+        Ok(())
+    }
+
+    async fn get_credential(&self, id: &CredentialId) -> Result<WebAuthnCredential, WebAuthnCredentialStoreError> {
+        sqlx::query_as!(
+            PgWebAuthnCredentialRow,
+            "SELECT id, email, public_key, sign_count, created_at FROM webauthn_credentials WHERE id = $1",
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| WebAuthnCredentialStoreError::UnexpectedError)?
+        .ok_or(WebAuthnCredentialStoreError::CredentialNotFound)?
+        .try_into()
+    }
+
+    async fn get_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, WebAuthnCredentialStoreError> {
+        sqlx::query_as!(
+            PgWebAuthnCredentialRow,
+            "SELECT id, email, public_key, sign_count, created_at FROM webauthn_credentials WHERE email = $1",
+            email.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|_| WebAuthnCredentialStoreError::UnexpectedError)?
+        .into_iter()
+        .map(WebAuthnCredential::try_from)
+        .collect()
+    }
+
+    async fn update_sign_count(&mut self, id: &CredentialId, sign_count: u32) -> Result<(), WebAuthnCredentialStoreError> {
+        let result = sqlx::query!(
+            "UPDATE webauthn_credentials SET sign_count = $2 WHERE id = $1",
+            id.as_ref(),
+            sign_count as i64
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| WebAuthnCredentialStoreError::UnexpectedError)?;
+
+        match result.rows_affected() {
+            0 => Err(WebAuthnCredentialStoreError::CredentialNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn remove_credentials(&mut self, email: &Email) -> Result<(), WebAuthnCredentialStoreError> {
+        sqlx::query!("DELETE FROM webauthn_credentials WHERE email = $1", email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| WebAuthnCredentialStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
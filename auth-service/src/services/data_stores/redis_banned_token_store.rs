@@ -1,12 +1,11 @@
 use std::sync::Arc;
 
+use chrono::Utc;
 use redis::{Commands, Connection};
 use tokio::sync::RwLock;
 
-use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
-};
+use crate::domain::data_stores::{BannedToken, BannedTokenStore, BannedTokenStoreError};
+use crate::services::revocation_feed::REVOCATIONS_CHANNEL;
 
 pub struct RedisBannedTokenStore {
     conn: Arc<RwLock<Connection>>,
@@ -20,25 +19,60 @@ impl RedisBannedTokenStore {
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
-    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
-        let key = get_key(&token);
-        let _: () = self.conn.write().await.set_ex(key, true, TOKEN_TTL_SECONDS as u64)
+    async fn add_token(&mut self, jti: String, exp: i64) -> Result<(), BannedTokenStoreError> {
+        // The ban expires together with the token. Expired tokens need no ban.
+        let ttl = exp - Utc::now().timestamp();
+        if ttl <= 0 {
+            return Ok(());
+        }
+
+        let key = get_key(&jti);
+        let mut conn = self.conn.write().await;
+        let _: () = conn.set_ex(key, true, ttl as u64)
+            .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+
+        // Tell every instance, and through them the revocation caches. The ban is stored
+        // first, so subscribers that snapshot after this never miss it.
+        let message = serde_json::to_string(&BannedToken { id: jti, exp })
+            .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+        let _: () = conn.publish(REVOCATIONS_CHANNEL, message)
             .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
 
         Ok(())
     }
 
-    async fn is_token_banned(&self, token: String) -> bool {
-        let key = get_key(&token);
+    async fn is_token_banned(&self, jti: String) -> bool {
+        let key = get_key(&jti);
         let result: bool = self.conn.write().await.exists(key)
             .unwrap_or(false);
         result
     }
+
+    async fn get_banned_tokens(&self) -> Result<Vec<BannedToken>, BannedTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let keys: Vec<String> = conn.scan_match(format!("{}*", BANNED_TOKEN_KEY_PREFIX))
+            .map_err(|_| BannedTokenStoreError::UnexpectedError)?
+            .collect();
+
+        let now = Utc::now().timestamp();
+        let mut tokens = Vec::with_capacity(keys.len());
+        for key in keys {
+            // The ban ends when its key expires
+            let ttl: i64 = conn.ttl(&key)
+                .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+            if ttl <= 0 {
+                continue;
+            }
+            let id = key.trim_start_matches(BANNED_TOKEN_KEY_PREFIX).to_owned();
+            tokens.push(BannedToken { id, exp: now + ttl });
+        }
+        Ok(tokens)
+    }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn get_key(jti: &str) -> String {
+    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, jti)
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{EmailChange, EmailChangeStore, EmailChangeStoreError, EmailChangeToken, EMAIL_CHANGE_TTL_SECONDS},
+    email::Email,
+};
+
+// Each change is a key named after its confirm token. A second key, named after the cancel
+// token, points to the first one. Both expire with the change.
+pub struct RedisEmailChangeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailChangeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailChangeStore for RedisEmailChangeStore {
+    async fn add_change(&mut self, change: EmailChange) -> Result<(), EmailChangeStoreError> {
+        let json = serde_json::to_string(&StoredChange::from(&change))
+            .map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+        let _: () = conn.set_ex(get_key(&change.confirm_token), json, EMAIL_CHANGE_TTL_SECONDS)
+            .map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+        let _: () = conn.set_ex(get_cancel_key(&change.cancel_token), change.confirm_token.as_ref(), EMAIL_CHANGE_TTL_SECONDS)
+            .map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn take_change(&mut self, confirm_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError> {
+        let mut conn = self.conn.write().await;
+        let change = take_stored_change(&mut conn, confirm_token)?;
+        let _: () = conn.del(get_cancel_key(&change.cancel_token))
+            .map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+
+        Ok(change)
+    }
+
+    async fn cancel_change(&mut self, cancel_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError> {
+        let mut conn = self.conn.write().await;
+        let confirm_token: Option<String> = conn.get_del(get_cancel_key(cancel_token))
+            .map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+        let confirm_token = confirm_token.ok_or(EmailChangeStoreError::ChangeNotFound)?;
+        let confirm_token = EmailChangeToken::parse(confirm_token).map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+
+        take_stored_change(&mut conn, &confirm_token)
+    }
+}
+
+// GETDEL makes sure a change is either confirmed or cancelled, never both
+fn take_stored_change(conn: &mut Connection, confirm_token: &EmailChangeToken) -> Result<EmailChange, EmailChangeStoreError> {
+    let value: Option<String> = conn.get_del(get_key(confirm_token))
+        .map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+    let value = value.ok_or(EmailChangeStoreError::ChangeNotFound)?;
+    let stored: StoredChange = serde_json::from_str(&value)
+        .map_err(|_| EmailChangeStoreError::UnexpectedError)?;
+
+    Ok(EmailChange {
+        email: Email::parse(stored.email).map_err(|_| EmailChangeStoreError::UnexpectedError)?,
+        new_email: Email::parse(stored.new_email).map_err(|_| EmailChangeStoreError::UnexpectedError)?,
+        confirm_token: confirm_token.clone(),
+        cancel_token: EmailChangeToken::parse(stored.cancel_token).map_err(|_| EmailChangeStoreError::UnexpectedError)?,
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredChange {
+    email: String,
+    new_email: String,
+    cancel_token: String,
+}
+
+impl From<&EmailChange> for StoredChange {
+    fn from(change: &EmailChange) -> Self {
+        Self {
+            email: change.email.as_ref().to_owned(),
+            new_email: change.new_email.as_ref().to_owned(),
+            cancel_token: change.cancel_token.as_ref().to_owned(),
+        }
+    }
+}
+
+const EMAIL_CHANGE_PREFIX: &str = "email_change:";
+const EMAIL_CHANGE_CANCEL_PREFIX: &str = "email_change_cancel:";
+
+fn get_key(confirm_token: &EmailChangeToken) -> String {
+    format!("{}{}", EMAIL_CHANGE_PREFIX, confirm_token.as_ref())
+}
+
+fn get_cancel_key(cancel_token: &EmailChangeToken) -> String {
+    format!("{}{}", EMAIL_CHANGE_CANCEL_PREFIX, cancel_token.as_ref())
+}
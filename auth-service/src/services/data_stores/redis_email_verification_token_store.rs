@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{
+        EmailVerificationToken, EmailVerificationTokenStore, EmailVerificationTokenStoreError, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+    },
+    email::Email,
+};
+
+pub struct RedisEmailVerificationTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisEmailVerificationTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for RedisEmailVerificationTokenStore {
+    async fn add_token(&mut self, token: EmailVerificationToken, email: Email) -> Result<(), EmailVerificationTokenStoreError> {
+        let _: () = self.conn.write().await.set_ex(get_key(&token), email.as_ref(), EMAIL_VERIFICATION_TOKEN_TTL_SECONDS)
+            .map_err(|_| EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn take_token(&mut self, token: &EmailVerificationToken) -> Result<Email, EmailVerificationTokenStoreError> {
+        // GETDEL makes sure only one request gets the address
+        let value: Option<String> = self.conn.write().await.get_del(get_key(token))
+            .map_err(|_| EmailVerificationTokenStoreError::UnexpectedError)?;
+        let value = value.ok_or(EmailVerificationTokenStoreError::TokenNotFound)?;
+
+        Email::parse(value).map_err(|_| EmailVerificationTokenStoreError::UnexpectedError)
+    }
+}
+
+const EMAIL_VERIFICATION_TOKEN_PREFIX: &str = "email_verification_token:";
+
+fn get_key(token: &EmailVerificationToken) -> String {
+    format!("{}{}", EMAIL_VERIFICATION_TOKEN_PREFIX, token.as_ref())
+}
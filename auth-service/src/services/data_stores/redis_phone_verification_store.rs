@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{
+        PhoneVerification, PhoneVerificationStore, PhoneVerificationStoreError, TwoFaCodeHash, PHONE_VERIFICATION_TTL_SECONDS,
+    },
+    email::Email,
+    phone_number::PhoneNumber,
+};
+
+// Each user's pending number is a key named after their address
+pub struct RedisPhoneVerificationStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPhoneVerificationStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationStore for RedisPhoneVerificationStore {
+    async fn add_verification(&mut self, email: Email, verification: PhoneVerification) -> Result<(), PhoneVerificationStoreError> {
+        let stored = StoredVerification {
+            phone_number: verification.phone_number,
+            code_hash: verification.code_hash.as_ref().to_owned(),
+        };
+        let json = serde_json::to_string(&stored)
+            .map_err(|_| PhoneVerificationStoreError::UnexpectedError)?;
+
+        let _: () = self.conn.write().await.set_ex(get_key(&email), json, PHONE_VERIFICATION_TTL_SECONDS)
+            .map_err(|_| PhoneVerificationStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn take_verification(&mut self, email: &Email) -> Result<PhoneVerification, PhoneVerificationStoreError> {
+        // GETDEL makes sure each code is only checked once
+        let value: Option<String> = self.conn.write().await.get_del(get_key(email))
+            .map_err(|_| PhoneVerificationStoreError::UnexpectedError)?;
+        let value = value.ok_or(PhoneVerificationStoreError::VerificationNotFound)?;
+        let stored: StoredVerification = serde_json::from_str(&value)
+            .map_err(|_| PhoneVerificationStoreError::UnexpectedError)?;
+
+        Ok(PhoneVerification {
+            phone_number: stored.phone_number,
+            code_hash: TwoFaCodeHash::parse(stored.code_hash).map_err(|_| PhoneVerificationStoreError::UnexpectedError)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredVerification {
+    phone_number: PhoneNumber,
+    code_hash: String,
+}
+
+const PHONE_VERIFICATION_PREFIX: &str = "phone_verification:";
+
+fn get_key(email: &Email) -> String {
+    format!("{}{}", PHONE_VERIFICATION_PREFIX, email.as_ref())
+}
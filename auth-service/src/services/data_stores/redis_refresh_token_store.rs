@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenFamilyId, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+        email::Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        email: Email,
+        family_id: RefreshTokenFamilyId,
+        session_epoch: i64,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let entry = RefreshTokenEntry {
+            email: email.as_ref().to_owned(),
+            family_id: family_id.as_ref().to_owned(),
+            session_epoch,
+        };
+        let json = serde_json::to_string(&entry)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+        let _: () = conn.set_ex(get_token_key(&token), json, REFRESH_TOKEN_TTL_SECONDS as u64)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        // Keep track of the family members so the whole family can be revoked at once
+        let family_key = get_family_key(&family_id);
+        let _: () = conn.sadd(&family_key, token.as_ref())
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        let _: () = conn.expire(&family_key, REFRESH_TOKEN_TTL_SECONDS)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let value: Option<String> = conn.get(get_token_key(token))
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        let value = value.ok_or(RefreshTokenStoreError::TokenNotFound)?;
+        let is_marked_used: bool = conn.exists(get_used_key(token))
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        let entry: RefreshTokenEntry = serde_json::from_str(&value)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        entry.into_record(is_marked_used)
+    }
+
+    // The used marker is a key of its own, set with NX, so of two concurrent requests
+    // presenting the same token only one can mark it used
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let exists: bool = conn.exists(get_token_key(token))
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        if !exists {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        }
+
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(REFRESH_TOKEN_TTL_SECONDS as u64));
+        let set: Option<String> = conn.set_options(get_used_key(token), 1, options)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        match set {
+            Some(_) => Ok(()),
+            None => Err(RefreshTokenStoreError::TokenAlreadyUsed),
+        }
+    }
+
+    async fn revoke_family(&mut self, family_id: &RefreshTokenFamilyId) -> Result<(), RefreshTokenStoreError> {
+        let family_key = get_family_key(family_id);
+        let mut conn = self.conn.write().await;
+
+        let tokens: Vec<String> = conn.smembers(&family_key)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        let mut keys: Vec<String> = tokens
+            .iter()
+            .flat_map(|token| [
+                format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token),
+                format!("{}{}", REFRESH_TOKEN_USED_KEY_PREFIX, token),
+            ])
+            .collect();
+        keys.push(family_key);
+
+        let _: () = conn.del(keys)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenEntry {
+    email: String,
+    family_id: String,
+    session_epoch: i64,
+}
+
+impl RefreshTokenEntry {
+    // Whether the token was used is kept under a key of its own
+    fn into_record(self, used: bool) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let email = Email::parse(self.email)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        let family_id = RefreshTokenFamilyId::parse(self.family_id)
+            .map_err(|_| RefreshTokenStoreError::UnexpectedError)?;
+        Ok(RefreshTokenRecord { email, family_id, used, session_epoch: self.session_epoch })
+    }
+}
+
+// We are using key prefixes to prevent collisions and organize data!
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const REFRESH_TOKEN_FAMILY_KEY_PREFIX: &str = "refresh_token_family:";
+const REFRESH_TOKEN_USED_KEY_PREFIX: &str = "refresh_token_used:";
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token.as_ref())
+}
+
+fn get_used_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_USED_KEY_PREFIX, token.as_ref())
+}
+
+fn get_family_key(family_id: &RefreshTokenFamilyId) -> String {
+    format!("{}{}", REFRESH_TOKEN_FAMILY_KEY_PREFIX, family_id.as_ref())
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{TrustedDevice, TrustedDeviceId, TrustedDeviceStore, TrustedDeviceStoreError},
+    email::Email,
+};
+
+// Each device is a key that expires with the device. A set per user indexes them,
+// and is cleaned of expired devices as it is read.
+pub struct RedisTrustedDeviceStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisTrustedDeviceStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for RedisTrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        let ttl: u64 = (device.expires_at - Utc::now())
+            .num_seconds()
+            .try_into()
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+        let json = serde_json::to_string(&StoredDevice::from(&device))
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+        let _: () = conn.set_ex(get_key(&device.id), json, ttl)
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+        let index_key = get_index_key(&device.email);
+        let _: () = conn.sadd(&index_key, device.id.as_ref())
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+        // Devices are added with the same TTL, so the newest one outlives the others
+        let _: () = conn.expire(&index_key, ttl as i64)
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_device(&self, id: &TrustedDeviceId) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        let value: Option<String> = self.conn.write().await.get(get_key(id))
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+        let value = value.ok_or(TrustedDeviceStoreError::DeviceNotFound)?;
+
+        parse_device(id.clone(), &value)
+    }
+
+    async fn get_devices(&self, email: &Email) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        let index_key = get_index_key(email);
+        let ids: Vec<String> = conn.smembers(&index_key)
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        let mut devices = Vec::with_capacity(ids.len());
+        for id in ids {
+            let id = TrustedDeviceId::parse(id).map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+            let value: Option<String> = conn.get(get_key(&id))
+                .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+            match value {
+                Some(value) => devices.push(parse_device(id, &value)?),
+                None => {
+                    let _: () = conn.srem(&index_key, id.as_ref())
+                        .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+                }
+            }
+        }
+        devices.sort_by_key(|device| device.created_at);
+        Ok(devices)
+    }
+
+    async fn remove_device(&mut self, id: &TrustedDeviceId) -> Result<(), TrustedDeviceStoreError> {
+        let device = self.get_device(id).await?;
+
+        let mut conn = self.conn.write().await;
+        let _: () = conn.del(get_key(id))
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+        let _: () = conn.srem(get_index_key(&device.email), id.as_ref())
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn remove_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        let index_key = get_index_key(email);
+        let ids: Vec<String> = conn.smembers(&index_key)
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        let mut keys: Vec<String> = ids.into_iter().map(|id| format!("{}{}", TRUSTED_DEVICE_PREFIX, id)).collect();
+        keys.push(index_key);
+        let _: () = conn.del(&keys)
+            .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredDevice {
+    email: String,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+    created_at: i64,
+    expires_at: i64,
+}
+
+impl From<&TrustedDevice> for StoredDevice {
+    fn from(device: &TrustedDevice) -> Self {
+        Self {
+            email: device.email.as_ref().to_owned(),
+            user_agent: device.user_agent.clone(),
+            ip_address: device.ip_address.clone(),
+            created_at: device.created_at.timestamp(),
+            expires_at: device.expires_at.timestamp(),
+        }
+    }
+}
+
+fn parse_device(id: TrustedDeviceId, value: &str) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+    let stored: StoredDevice = serde_json::from_str(value)
+        .map_err(|_| TrustedDeviceStoreError::UnexpectedError)?;
+    let timestamp = |seconds| DateTime::from_timestamp(seconds, 0).ok_or(TrustedDeviceStoreError::UnexpectedError);
+
+    Ok(TrustedDevice {
+        id,
+        email: Email::parse(stored.email).map_err(|_| TrustedDeviceStoreError::UnexpectedError)?,
+        user_agent: stored.user_agent,
+        ip_address: stored.ip_address,
+        created_at: timestamp(stored.created_at)?,
+        expires_at: timestamp(stored.expires_at)?,
+    })
+}
+
+const TRUSTED_DEVICE_PREFIX: &str = "trusted_device:";
+const TRUSTED_DEVICES_PREFIX: &str = "trusted_devices:";
+
+fn get_key(id: &TrustedDeviceId) -> String {
+    format!("{}{}", TRUSTED_DEVICE_PREFIX, id.as_ref())
+}
+
+fn get_index_key(email: &Email) -> String {
+    format!("{}{}", TRUSTED_DEVICES_PREFIX, email.as_ref())
+}
@@ -1,21 +1,69 @@
 use std::sync::Arc;
 
-use redis::{Commands, Connection};
+use chrono::Utc;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFaCode, TwoFACodeStore, TwoFACodeStoreError},
+    data_stores::{LoginAttemptId, TwoFaCode, TwoFaCodeHash, TwoFaCodeLimits, TwoFACodeStore, TwoFACodeStoreError},
     email::Email,
 };
+use crate::utils::constants::TWO_FA_CODE_HMAC_KEY;
 
+// Each login attempt is a set of keys that expire with its code. A sorted set per user,
+// ordered by when the attempts were started, indexes them and is cleaned of expired
+// attempts as new ones come in.
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    limits: TwoFaCodeLimits,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: Arc<RwLock<Connection>>, limits: TwoFaCodeLimits) -> Self {
+        Self { conn, limits }
+    }
+
+    // Sets the resend cooldown of the login attempt, unless it is still running
+    fn start_cooldown(&self, conn: &mut Connection, login_attempt_id: &LoginAttemptId) -> Result<bool, TwoFACodeStoreError> {
+        if self.limits.resend_cooldown_seconds == 0 {
+            return Ok(true);
+        }
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(self.limits.resend_cooldown_seconds));
+        let set: Option<String> = conn.set_options(get_cooldown_key(login_attempt_id), 1, options)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        Ok(set.is_some())
+    }
+
+    // Drops the user's expired login attempts from the index, and the oldest live ones
+    // until there's room for one more
+    fn make_room(&self, conn: &mut Connection, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let index_key = get_index_key(email);
+        let ids: Vec<String> = conn.zrange(&index_key, 0, -1)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        let mut live = Vec::with_capacity(ids.len());
+        for id in ids {
+            let id = LoginAttemptId::parse(id).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+            let exists: bool = conn.exists(get_key(&id))
+                .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+            match exists {
+                true => live.push(id),
+                false => {
+                    let _: () = conn.zrem(&index_key, id.as_ref())
+                        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+                }
+            }
+        }
+
+        let keep = self.limits.max_pending_attempts.saturating_sub(1) as usize;
+        let excess = live.len().saturating_sub(keep);
+        for id in live.iter().take(excess) {
+            remove_attempt(conn, email, id)?;
+        }
+        Ok(())
     }
 }
 
@@ -27,66 +75,161 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFaCode,
     ) -> Result<(), TwoFACodeStoreError> {
-        // TODO:
-        // 1. Create a new key using the get_key helper function.
-        // 2. Create a TwoFATuple instance.
-        // 3. Use serde_json::to_string to serialize the TwoFATuple instance into a JSON string.
-        // Return TwoFACodeStoreError::UnexpectedError if serialization fails.
-        // 4. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL).
-        // The value should be the serialized 2FA tuple.
-        // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
-        // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
-        let key = get_key(&email);
-        let tuple = TwoFATuple(login_attempt_id.as_ref().to_string(), code.as_ref().to_string());
+        let tuple = TwoFATuple(email.as_ref().to_string(), code.hash(&login_attempt_id, &TWO_FA_CODE_HMAC_KEY).as_ref().to_string());
         let json = serde_json::to_string(&tuple)
             .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
-        let _: () = self.conn.write().await.set_ex(key, json, TEN_MINUTES_IN_SECONDS)
+
+        let mut conn = self.conn.write().await;
+        self.make_room(&mut conn, &email)?;
+        let _: () = conn.set_ex(get_key(&login_attempt_id), json, TEN_MINUTES_IN_SECONDS)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        self.start_cooldown(&mut conn, &login_attempt_id)?;
+
+        let index_key = get_index_key(&email);
+        let _: () = conn.zadd(&index_key, login_attempt_id.as_ref(), Utc::now().timestamp_millis())
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        // The newest login attempt outlives the others
+        let _: () = conn.expire(&index_key, TEN_MINUTES_IN_SECONDS as i64)
             .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
 
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        // TODO:
-        // 1. Create a new key using the get_key helper function.
-        // 2. Call the del command on the Redis connection to delete the 2FA code entry.
-        // Return TwoFACodeStoreError::UnexpectedError if the operation fails.
-        let key = get_key(&email);
+    async fn remove_code(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        // GETDEL makes sure only one request gets to use up the attempt
+        let value: Option<String> = conn.get_del(get_key(login_attempt_id))
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let tuple: TwoFATuple = serde_json::from_str(&value.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let email = Email::parse(tuple.0).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        remove_attempt(&mut conn, &email, login_attempt_id)
+    }
 
-        let _: () = self.conn.write().await.del(key)
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        let ids: Vec<String> = conn.zrange(get_index_key(email), 0, -1)
             .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
 
+        for id in ids {
+            let id = LoginAttemptId::parse(id).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+            remove_attempt(&mut conn, email, &id)?;
+        }
         Ok(())
     }
 
     async fn get_code(
         &self,
-        email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFaCode), TwoFACodeStoreError> {
-        // TODO:
-        // 1. Create a new key using the get_key helper function.
-        // 2. Call the get command on the Redis connection to get the value stored for the key.
-        // Return TwoFACodeStoreError::LoginAttemptIdNotFound if the operation fails.
-        // If the operation succeeds, call serde_json::from_str to parse the JSON string into a TwoFATuple.
-        // Then, parse the login attempt ID string and 2FA code string into a LoginAttemptId and TwoFACode type respectively.
-        // Return TwoFACodeStoreError::UnexpectedError if parsing fails.
-        let key = get_key(&email);
-        let value: String = self.conn.write().await.get(key)
-            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
-        let tuple: TwoFATuple = serde_json::from_str(&value).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
-
-        let login_attempt = LoginAttemptId::parse(tuple.0).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
-        let two_fa_code = TwoFaCode::parse(tuple.1).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
-        Ok((login_attempt, two_fa_code))
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFaCodeHash), TwoFACodeStoreError> {
+        let tuple = get_tuple(&mut *self.conn.write().await, login_attempt_id)?;
+
+        let email = Email::parse(tuple.0).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let two_fa_code_hash = TwoFaCodeHash::parse(tuple.1).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        Ok((email, two_fa_code_hash))
+    }
+
+    async fn record_attempt(&mut self, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        let tuple = get_tuple(&mut conn, login_attempt_id)?;
+
+        // INCR is atomic, so concurrent tries can't share a count
+        let attempts_key = get_attempts_key(login_attempt_id);
+        let attempts: u32 = conn.incr(&attempts_key, 1)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let _: () = conn.expire(&attempts_key, TEN_MINUTES_IN_SECONDS as i64)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        // Every try before this one was a wrong code
+        if attempts > self.limits.max_failed_attempts {
+            let email = Email::parse(tuple.0).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+            remove_attempt(&mut conn, &email, login_attempt_id)?;
+            return Err(TwoFACodeStoreError::TooManyAttempts);
+        }
+        Ok(())
+    }
+
+    async fn resend_code(&mut self, login_attempt_id: &LoginAttemptId, code: TwoFaCode) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        let tuple = get_tuple(&mut conn, login_attempt_id)?;
+
+        // SET NX and INCR are atomic, so concurrent resends can't slip past the limits
+        if !self.start_cooldown(&mut conn, login_attempt_id)? {
+            return Err(TwoFACodeStoreError::ResendTooSoon);
+        }
+        let resends_key = get_resends_key(login_attempt_id);
+        let resends: u32 = conn.incr(&resends_key, 1)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        if resends > self.limits.max_resends {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+
+        // The new code gets the full lifetime, and so does the rest of the login attempt
+        let tuple = TwoFATuple(tuple.0, code.hash(login_attempt_id, &TWO_FA_CODE_HMAC_KEY).as_ref().to_string());
+        let json = serde_json::to_string(&tuple)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let _: () = conn.set_ex(get_key(login_attempt_id), json, TEN_MINUTES_IN_SECONDS)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        for key in [resends_key, get_attempts_key(login_attempt_id)] {
+            let _: () = conn.expire(key, TEN_MINUTES_IN_SECONDS as i64)
+                .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        }
+        let email = Email::parse(tuple.0).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let _: () = conn.expire(get_index_key(&email), TEN_MINUTES_IN_SECONDS as i64)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        Ok(())
     }
 }
 
+fn get_tuple(conn: &mut Connection, login_attempt_id: &LoginAttemptId) -> Result<TwoFATuple, TwoFACodeStoreError> {
+    let value: Option<String> = conn.get(get_key(login_attempt_id))
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+    serde_json::from_str(&value.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?)
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)
+}
+
+fn remove_attempt(conn: &mut Connection, email: &Email, login_attempt_id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+    let keys = [
+        get_key(login_attempt_id),
+        get_attempts_key(login_attempt_id),
+        get_resends_key(login_attempt_id),
+        get_cooldown_key(login_attempt_id),
+    ];
+    let _: () = conn.del(&keys)
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+    let _: () = conn.zrem(get_index_key(email), login_attempt_id.as_ref())
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+    Ok(())
+}
+
+// The user the login attempt belongs to, and the hash of its code
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const TWO_FA_RESENDS_PREFIX: &str = "two_fa_resends:";
+const TWO_FA_COOLDOWN_PREFIX: &str = "two_fa_resend_cooldown:";
+const TWO_FA_INDEX_PREFIX: &str = "two_fa_login_attempts:";
+
+fn get_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!("{}{}", TWO_FA_CODE_PREFIX, login_attempt_id.as_ref())
+}
+
+fn get_attempts_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!("{}{}", TWO_FA_ATTEMPTS_PREFIX, login_attempt_id.as_ref())
+}
+
+fn get_resends_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!("{}{}", TWO_FA_RESENDS_PREFIX, login_attempt_id.as_ref())
+}
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref())
-}
\ No newline at end of file
+fn get_cooldown_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!("{}{}", TWO_FA_COOLDOWN_PREFIX, login_attempt_id.as_ref())
+}
+
+fn get_index_key(email: &Email) -> String {
+    format!("{}{}", TWO_FA_INDEX_PREFIX, email.as_ref())
+}
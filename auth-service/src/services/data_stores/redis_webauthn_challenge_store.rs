@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{WebAuthnChallengeStore, WebAuthnChallengeStoreError},
+    webauthn::{WebAuthnCeremony, WebAuthnChallenge, WEBAUTHN_TIMEOUT_SECONDS},
+};
+
+pub struct RedisWebAuthnChallengeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisWebAuthnChallengeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for RedisWebAuthnChallengeStore {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge, ceremony: WebAuthnCeremony) -> Result<(), WebAuthnChallengeStoreError> {
+        let json = serde_json::to_string(&ceremony)
+            .map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)?;
+        let _: () = self.conn.write().await.set_ex(get_key(&challenge), json, WEBAUTHN_TIMEOUT_SECONDS)
+            .map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn take_challenge(&mut self, challenge: &WebAuthnChallenge) -> Result<WebAuthnCeremony, WebAuthnChallengeStoreError> {
+        // GETDEL makes sure only one request gets the ceremony
+        let value: Option<String> = self.conn.write().await.get_del(get_key(challenge))
+            .map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)?;
+        let value = value.ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)?;
+
+        serde_json::from_str(&value).map_err(|_| WebAuthnChallengeStoreError::UnexpectedError)
+    }
+}
+
+const WEBAUTHN_CHALLENGE_PREFIX: &str = "webauthn_challenge:";
+
+fn get_key(challenge: &WebAuthnChallenge) -> String {
+    format!("{}{}", WEBAUTHN_CHALLENGE_PREFIX, challenge.as_ref())
+}
@@ -0,0 +1,99 @@
+use reqwest::{Client, Url};
+use serde::Serialize;
+
+use crate::domain::SmsClient;
+use crate::domain::phone_number::PhoneNumber;
+
+// Sends messages through an SMS provider's REST API: a JSON `POST {base_url}/messages`,
+// authenticated with a bearer token
+pub struct HttpSmsClient {
+    http_client: Client,
+    base_url: Url,
+    sender: String,
+    auth_token: String,
+}
+
+impl HttpSmsClient {
+    pub fn new(base_url: Url, sender: String, auth_token: String, http_client: Client) -> Self {
+        Self { http_client, base_url, sender, auth_token }
+    }
+}
+
+#[derive(Serialize)]
+struct SendSmsRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    text: &'a str,
+}
+
+#[async_trait::async_trait]
+impl SmsClient for HttpSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String> {
+        let url = self.base_url.join("messages").map_err(|e| e.to_string())?;
+        let request = SendSmsRequest { from: &self.sender, to: recipient.as_ref(), text: content };
+
+        self.http_client
+            .post(url)
+            .bearer_auth(&self.auth_token)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::post, Json, Router};
+    use serde_json::{json, Value};
+    use super::*;
+
+    type Received = Arc<Mutex<Vec<(Option<String>, Value)>>>;
+
+    // Stands in for the provider, answering with `status` and recording what it was sent
+    async fn spawn_stub(status: StatusCode) -> (Url, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/messages", post(move |State(received): State<Received>, headers: HeaderMap, Json(body): Json<Value>| async move {
+                let authorization = headers.get("authorization").map(|value| value.to_str().unwrap().to_owned());
+                received.lock().unwrap().push((authorization, body));
+                status
+            }))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    fn sms_client(base_url: Url) -> HttpSmsClient {
+        let http_client = Client::builder().timeout(Duration::from_secs(5)).build().unwrap();
+        HttpSmsClient::new(base_url, "+15005550006".to_owned(), "auth-token".to_owned(), http_client)
+    }
+
+    #[tokio::test]
+    async fn test_send_sms() {
+        let (url, received) = spawn_stub(StatusCode::CREATED).await;
+        let recipient = PhoneNumber::parse("+14155550123".to_owned()).unwrap();
+
+        sms_client(url).send_sms(&recipient, "Your 2fa code is: 123456").await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0.as_deref(), Some("Bearer auth-token"));
+        assert_eq!(received[0].1, json!({ "from": "+15005550006", "to": "+14155550123", "text": "Your 2fa code is: 123456" }));
+    }
+
+    #[tokio::test]
+    async fn test_send_sms_fails_if_provider_rejects_message() {
+        let (url, _) = spawn_stub(StatusCode::BAD_REQUEST).await;
+        let recipient = PhoneNumber::parse("+14155550123".to_owned()).unwrap();
+
+        assert!(sms_client(url).send_sms(&recipient, "Your 2fa code is: 123456").await.is_err());
+    }
+}
@@ -0,0 +1,14 @@
+use crate::domain::SmsClient;
+use crate::domain::phone_number::PhoneNumber;
+
+pub struct MockSmsClient;
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String> {
+        // Like the mock email client, this simply logs the message to standard output
+        println!("Sending SMS to {} with content: {}", recipient.as_ref(), content);
+
+        Ok(())
+    }
+}
@@ -1,3 +1,6 @@
 
 pub mod data_stores;
+pub mod http_sms_client;
 pub mod mock_email_client;
+pub mod mock_sms_client;
+pub mod revocation_feed;
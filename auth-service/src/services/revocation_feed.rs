@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use redis::Client;
+use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
+
+use crate::domain::data_stores::BannedToken;
+
+// Redis channel every instance publishes its bans on
+pub const REVOCATIONS_CHANNEL: &str = "revocations";
+// Subscribers falling further behind than this are dropped and have to resync
+const FEED_CAPACITY: usize = 1024;
+const REDIS_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RevocationEvent {
+    Revoked(BannedToken),
+    // Bans may have been missed. Subscribers must start over from a snapshot.
+    Resync,
+}
+
+// Fans the bans of every instance out to this instance's /revocations subscribers
+#[derive(Debug, Clone)]
+pub struct RevocationFeed {
+    sender: broadcast::Sender<RevocationEvent>,
+}
+
+impl RevocationFeed {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(FEED_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: RevocationEvent) {
+        // Nobody listening is fine
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RevocationEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for RevocationFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Forwards the bans published on Redis by `RedisBannedTokenStore` into the feed, reconnecting
+// whenever the subscription drops
+pub async fn run_redis_revocation_listener(client: Client, feed: RevocationFeed) {
+    loop {
+        if let Err(e) = listen(&client, &feed).await {
+            eprintln!("Revocation subscription failed: {}", e);
+        }
+        // Whatever was published from here on until we're subscribed again is lost
+        feed.publish(RevocationEvent::Resync);
+        tokio::time::sleep(REDIS_RECONNECT_DELAY).await;
+    }
+}
+
+async fn listen(client: &Client, feed: &RevocationFeed) -> redis::RedisResult<()> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(REVOCATIONS_CHANNEL).await?;
+    // Bans published before the subscription took hold were missed
+    feed.publish(RevocationEvent::Resync);
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        match serde_json::from_str::<BannedToken>(&payload) {
+            Ok(token) => feed.publish(RevocationEvent::Revoked(token)),
+            Err(e) => eprintln!("Ignoring malformed revocation: {}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderMap};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::app_state::AppState;
+use crate::domain::data_stores::{ApiClientId, ApiClientSecret, ApiClientStoreError};
+use crate::domain::error::AuthAPIError;
+
+// A registered API client, authenticated with HTTP Basic client credentials.
+// Requests without valid credentials are rejected with `AuthAPIError::InvalidClient`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedClient(pub ApiClientId);
+
+impl FromRequestParts<AppState> for AuthenticatedClient {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let (id, secret) = parse_basic_auth(&parts.headers).ok_or(AuthAPIError::InvalidClient)?;
+        let id = ApiClientId::parse(id).map_err(|_| AuthAPIError::InvalidClient)?;
+        let secret = ApiClientSecret::parse(secret).map_err(|_| AuthAPIError::InvalidClient)?;
+
+        let client = match state.api_client_store.read().await.get_client(&id).await {
+            Ok(client) => client,
+            Err(ApiClientStoreError::ClientNotFound) => return Err(AuthAPIError::InvalidClient),
+            Err(_) => return Err(AuthAPIError::UnexpectedError),
+        };
+        if !client.verify_secret(&secret) {
+            return Err(AuthAPIError::InvalidClient);
+        }
+
+        Ok(AuthenticatedClient(id))
+    }
+}
+
+fn parse_basic_auth(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (id, secret) = decoded.split_once(':')?;
+    Some((id.to_owned(), secret.to_owned()))
+}
@@ -1,18 +1,507 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use aws_lc_rs::encoding::{AsDer, Pkcs8V1Der, PublicKeyX509Der};
+use aws_lc_rs::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
 use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
-use serde::{Deserialize, Serialize};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, EllipticCurveKeyType,
+    Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters,
+    RSAKeyType,
+};
+use rand::Rng;
+use thiserror::Error;
 
+use crate::app_state::{AppState, KeyRingType, RefreshTokenStoreType, SigningKeyStoreType};
+use crate::domain::data_stores::{
+    BannedTokenStoreError, EncryptedPrivateKey, LoginAttemptId, RefreshToken, RefreshTokenFamilyId, Session, SessionId, SessionStoreError, SigningKey, SigningKeyState,
+    SigningKeyStoreError, TrustedDevice, TrustedDeviceId, UserStoreError,
+};
 use crate::domain::email::Email;
+use crate::domain::error::{AuthAPIError, SigningKeyError};
+use crate::domain::user::User;
 
-use super::constants::JWT_COOKIE_NAME;
+use super::client_info::ClientInfo;
+use super::constants::{
+    COOKIE_SIGNING_KEY, JWT_ALGORITHM, JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, JWT_KEY_ROTATION_INTERVAL_SECONDS,
+    JWT_PRIVATE_KEY_PATH, JWT_PUBLIC_KEY_PATH, JWT_SECRET, LOGIN_ATTEMPT_COOKIE_NAME, LOGIN_ATTEMPT_COOKIE_PATH,
+    REFRESH_TOKEN_COOKIE_NAME, REFRESH_TOKEN_COOKIE_PATH, SIGNING_KEY_ENCRYPTION_KEY, TRUSTED_DEVICE_COOKIE_NAME,
+    TRUSTED_DEVICE_COOKIE_PATH,
+};
+use super::cookie_signing::{sign_cookie_value, verify_cookie_value};
 
-// This is definitely NOT a good secret. We will update it soon!
-const JWT_SECRET: &str = "secret";
+#[derive(Debug, Error)]
+pub enum JwtKeyError {
+    #[error("unsupported algorithm {0:?}")]
+    UnsupportedAlgorithm(Algorithm),
+    #[error("invalid key: {0}")]
+    InvalidKey(#[from] jsonwebtoken::errors::Error),
+    #[error("malformed public key")]
+    MalformedPublicKey,
+    #[error("malformed shared secret")]
+    MalformedSecret,
+    #[error("failed to read key file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to generate key")]
+    KeyGeneration,
+    #[error(transparent)]
+    Encryption(#[from] SigningKeyError),
+}
+
+// Key material used to sign and verify JWT auth tokens
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    // Public half of the key as published in the JWKS. Never set for shared secrets.
+    jwk: Option<Jwk>,
+}
+
+impl JwtKeys {
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self {
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    // Supports RS256, ES256 (P-256) and EdDSA (Ed25519) key pairs. The public key is
+    // expected in SubjectPublicKeyInfo form ("BEGIN PUBLIC KEY").
+    pub fn from_pem(algorithm: Algorithm, private_key: &[u8], public_key: &[u8]) -> Result<Self, JwtKeyError> {
+        let (encoding_key, decoding_key) = match algorithm {
+            Algorithm::RS256 => (EncodingKey::from_rsa_pem(private_key)?, DecodingKey::from_rsa_pem(public_key)?),
+            Algorithm::ES256 => (EncodingKey::from_ec_pem(private_key)?, DecodingKey::from_ec_pem(public_key)?),
+            Algorithm::EdDSA => (EncodingKey::from_ed_pem(private_key)?, DecodingKey::from_ed_pem(public_key)?),
+            _ => return Err(JwtKeyError::UnsupportedAlgorithm(algorithm)),
+        };
+        let jwk = public_jwk(algorithm, &decoding_key)?;
+
+        Ok(Self { algorithm, encoding_key, decoding_key, jwk: Some(jwk) })
+    }
+
+    // The public keys that downstream services can use to verify tokens locally
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet { keys: self.jwk.iter().cloned().collect() }
+    }
+}
+
+impl TryFrom<&SigningKey> for JwtKeys {
+    type Error = JwtKeyError;
+
+    fn try_from(key: &SigningKey) -> Result<Self, Self::Error> {
+        let algorithm = Algorithm::from_str(&key.algorithm)?;
+        let private_key = key.private_key.decrypt(&SIGNING_KEY_ENCRYPTION_KEY)?;
+        if algorithm == Algorithm::HS256 {
+            let secret = STANDARD.decode(&private_key).map_err(|_| JwtKeyError::MalformedSecret)?;
+            return Ok(JwtKeys::from_secret(&secret));
+        }
+        let public_key = key.public_key.as_ref().ok_or(JwtKeyError::MalformedPublicKey)?;
+        JwtKeys::from_pem(algorithm, private_key.as_bytes(), public_key.as_bytes())
+    }
+}
+
+// Create a fresh active signing key for the given algorithm
+pub fn generate_signing_key(algorithm: Algorithm) -> Result<SigningKey, JwtKeyError> {
+    let (private_key, public_key) = match algorithm {
+        Algorithm::HS256 => {
+            let secret: [u8; 64] = rand::rng().random();
+            return new_signing_key(algorithm, STANDARD.encode(secret), None);
+        }
+        Algorithm::RS256 => {
+            let key_pair = aws_lc_rs::rsa::KeyPair::generate(aws_lc_rs::rsa::KeySize::Rsa2048)
+                .map_err(|_| JwtKeyError::KeyGeneration)?;
+            let private_key: Pkcs8V1Der = key_pair.as_der().map_err(|_| JwtKeyError::KeyGeneration)?;
+            let public_key: PublicKeyX509Der = key_pair.public_key().as_der().map_err(|_| JwtKeyError::KeyGeneration)?;
+            (private_key.as_ref().to_vec(), public_key.as_ref().to_vec())
+        }
+        Algorithm::ES256 => {
+            let key_pair = EcdsaKeyPair::generate(&ECDSA_P256_SHA256_FIXED_SIGNING)
+                .map_err(|_| JwtKeyError::KeyGeneration)?;
+            let private_key = key_pair.to_pkcs8v1().map_err(|_| JwtKeyError::KeyGeneration)?;
+            let public_key: PublicKeyX509Der = key_pair.public_key().as_der().map_err(|_| JwtKeyError::KeyGeneration)?;
+            (private_key.as_ref().to_vec(), public_key.as_ref().to_vec())
+        }
+        Algorithm::EdDSA => {
+            let key_pair = Ed25519KeyPair::generate().map_err(|_| JwtKeyError::KeyGeneration)?;
+            let private_key = key_pair.to_pkcs8v1().map_err(|_| JwtKeyError::KeyGeneration)?;
+            let public_key: PublicKeyX509Der = key_pair.public_key().as_der().map_err(|_| JwtKeyError::KeyGeneration)?;
+            (private_key.as_ref().to_vec(), public_key.as_ref().to_vec())
+        }
+        _ => return Err(JwtKeyError::UnsupportedAlgorithm(algorithm)),
+    };
+
+    new_signing_key(
+        algorithm,
+        pem_encode("PRIVATE KEY", &private_key),
+        Some(pem_encode("PUBLIC KEY", &public_key)),
+    )
+}
+
+// The first key of a new deployment comes from the environment, so existing
+// JWT_SECRET or PEM configuration keeps working. Without PEM files a key pair is generated.
+fn initial_signing_key() -> Result<SigningKey, JwtKeyError> {
+    let algorithm = Algorithm::from_str(&JWT_ALGORITHM)?;
+
+    if algorithm == Algorithm::HS256 {
+        return new_signing_key(algorithm, STANDARD.encode(JWT_SECRET.as_bytes()), None);
+    }
+
+    match (JWT_PRIVATE_KEY_PATH.as_ref(), JWT_PUBLIC_KEY_PATH.as_ref()) {
+        (Some(private_key_path), Some(public_key_path)) => {
+            let private_key = std::fs::read_to_string(private_key_path)?;
+            let public_key = std::fs::read_to_string(public_key_path)?;
+            // Fail early on keys that can't be used
+            JwtKeys::from_pem(algorithm, private_key.as_bytes(), public_key.as_bytes())?;
+            new_signing_key(algorithm, private_key, Some(public_key))
+        }
+        _ => generate_signing_key(algorithm),
+    }
+}
+
+// The private key never reaches the store unencrypted
+fn new_signing_key(algorithm: Algorithm, private_key: String, public_key: Option<String>) -> Result<SigningKey, JwtKeyError> {
+    let now = Utc::now();
+    Ok(SigningKey {
+        kid: uuid::Uuid::new_v4().to_string(),
+        algorithm: format!("{:?}", algorithm),
+        private_key: EncryptedPrivateKey::encrypt(&private_key, &SIGNING_KEY_ENCRYPTION_KEY)?,
+        public_key,
+        state: SigningKeyState::Active,
+        created_at: now,
+        state_changed_at: now,
+    })
+}
+
+fn pem_encode(label: &str, der: &[u8]) -> String {
+    let body = STANDARD.encode(der);
+    let lines: Vec<&str> = body
+        .as_bytes()
+        .chunks(64)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base64 is ASCII"))
+        .collect();
+    format!("-----BEGIN {label}-----\n{}\n-----END {label}-----\n", lines.join("\n"))
+}
+
+// Describe the public key as a JWK (RFC 7517)
+fn public_jwk(algorithm: Algorithm, decoding_key: &DecodingKey) -> Result<Jwk, JwtKeyError> {
+    let public_key = decoding_key.as_bytes();
+
+    let (key_algorithm, parameters) = match algorithm {
+        Algorithm::RS256 => {
+            // PKCS#1 RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+            let blocks = simple_asn1::from_der(public_key).map_err(|_| JwtKeyError::MalformedPublicKey)?;
+            let (n, e) = match blocks.as_slice() {
+                [simple_asn1::ASN1Block::Sequence(_, items)] => match items.as_slice() {
+                    [simple_asn1::ASN1Block::Integer(_, n), simple_asn1::ASN1Block::Integer(_, e)] => {
+                        (n.to_bytes_be().1, e.to_bytes_be().1)
+                    }
+                    _ => return Err(JwtKeyError::MalformedPublicKey),
+                },
+                _ => return Err(JwtKeyError::MalformedPublicKey),
+            };
+            (KeyAlgorithm::RS256, AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(n),
+                e: URL_SAFE_NO_PAD.encode(e),
+            }))
+        }
+        Algorithm::ES256 => {
+            // Uncompressed SEC1 point: 0x04 || x || y
+            if public_key.len() != 65 || public_key[0] != 0x04 {
+                return Err(JwtKeyError::MalformedPublicKey);
+            }
+            (KeyAlgorithm::ES256, AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: EllipticCurve::P256,
+                x: URL_SAFE_NO_PAD.encode(&public_key[1..33]),
+                y: URL_SAFE_NO_PAD.encode(&public_key[33..]),
+            }))
+        }
+        Algorithm::EdDSA => {
+            if public_key.len() != 32 {
+                return Err(JwtKeyError::MalformedPublicKey);
+            }
+            (KeyAlgorithm::EdDSA, AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(public_key),
+            }))
+        }
+        _ => return Err(JwtKeyError::UnsupportedAlgorithm(algorithm)),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+#[derive(Debug, Error)]
+pub enum KeyRingError {
+    #[error("signing key store error: {0:?}")]
+    StoreError(SigningKeyStoreError),
+    #[error(transparent)]
+    InvalidKey(#[from] JwtKeyError),
+    #[error("no active signing key")]
+    NoActiveKey,
+}
+
+impl From<SigningKeyStoreError> for KeyRingError {
+    fn from(e: SigningKeyStoreError) -> Self {
+        KeyRingError::StoreError(e)
+    }
+}
+
+struct RingKey {
+    kid: String,
+    state: SigningKeyState,
+    keys: JwtKeys,
+    created_at: chrono::DateTime<Utc>,
+    state_changed_at: chrono::DateTime<Utc>,
+}
+
+// In-memory copy of the signing keys held by the store. New tokens are signed with the
+// active key and carry its `kid`; tokens signed by verify-only keys are still accepted.
+pub struct KeyRing {
+    store: SigningKeyStoreType,
+    keys: Vec<RingKey>,
+    loaded_at: Instant,
+}
+
+impl KeyRing {
+    // Load the ring, seeding the store with the configured key on first start
+    pub async fn load(store: SigningKeyStoreType) -> Result<Self, KeyRingError> {
+        let mut key_ring = Self { store, keys: Vec::new(), loaded_at: Instant::now() };
+        key_ring.reload().await?;
+
+        if key_ring.active_key().is_none() {
+            // Another instance may be seeding the store at the same time; its key wins
+            match key_ring.store.write().await.add_key(initial_signing_key()?).await {
+                Ok(_) | Err(SigningKeyStoreError::ActiveKeyConflict) => {}
+                Err(e) => return Err(e.into()),
+            }
+            key_ring.reload().await?;
+        }
+
+        Ok(key_ring)
+    }
+
+    // Pick up rotations made by other instances
+    pub async fn reload(&mut self) -> Result<(), KeyRingError> {
+        let keys = self.store.read().await.get_keys().await?;
+
+        self.keys = keys
+            .iter()
+            .filter(|key| key.state != SigningKeyState::Retired)
+            .map(|key| {
+                Ok(RingKey {
+                    kid: key.kid.clone(),
+                    state: key.state,
+                    keys: JwtKeys::try_from(key)?,
+                    created_at: key.created_at,
+                    state_changed_at: key.state_changed_at,
+                })
+            })
+            .collect::<Result<_, JwtKeyError>>()?;
+        self.loaded_at = Instant::now();
+
+        Ok(())
+    }
+
+    pub fn active_kid(&self) -> Option<&str> {
+        self.active_key().map(|key| key.kid.as_str())
+    }
+
+    fn active_key(&self) -> Option<&RingKey> {
+        self.keys.iter().find(|key| key.state == SigningKeyState::Active)
+    }
+
+    fn verification_key(&self, kid: &str) -> Option<&RingKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    // The JWKS advertised at /.well-known/jwks.json
+    pub fn jwks(&self) -> JwkSet {
+        let keys = self
+            .keys
+            .iter()
+            .filter_map(|key| {
+                let mut jwk = key.keys.jwk.clone()?;
+                jwk.common.key_id = Some(key.kid.clone());
+                Some(jwk)
+            })
+            .collect();
 
-// Create cookie with a new JWT auth token
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_auth_token(email)?;
+        JwkSet { keys }
+    }
+
+    // Demote the active key to verify-only and start signing with a new one
+    pub async fn rotate(&mut self) -> Result<String, KeyRingError> {
+        self.reload().await?;
+        let current_kid = self.active_kid().ok_or(KeyRingError::NoActiveKey)?.to_owned();
+
+        let algorithm = Algorithm::from_str(&JWT_ALGORITHM).map_err(JwtKeyError::from)?;
+        let new_key = generate_signing_key(algorithm)?;
+        let new_kid = new_key.kid.clone();
+
+        let rotate_res = self.store.write().await.rotate_key(&current_kid, new_key).await;
+        self.reload().await?;
+        rotate_res?;
+
+        Ok(new_kid)
+    }
+
+    // Stop accepting tokens signed with the key, e.g. after it was compromised
+    pub async fn retire(&mut self, kid: &str) -> Result<(), KeyRingError> {
+        let retire_res = self.store.write().await.retire_key(kid).await;
+        self.reload().await?;
+        retire_res.map_err(KeyRingError::from)
+    }
+
+    // Rotate the active key once it is older than `rotation_interval`, and retire
+    // verify-only keys once no token they signed can still be valid
+    pub async fn rotate_if_due(&mut self, rotation_interval: chrono::Duration) -> Result<(), KeyRingError> {
+        self.reload().await?;
+        let now = Utc::now();
+
+        let active_key = self.active_key().ok_or(KeyRingError::NoActiveKey)?;
+        if active_key.created_at + rotation_interval <= now {
+            match self.rotate().await {
+                // Another instance rotated first
+                Ok(_) | Err(KeyRingError::StoreError(SigningKeyStoreError::ActiveKeyConflict)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Instances may keep signing with a demoted key until their next sync
+        let grace_period = chrono::Duration::seconds(TOKEN_TTL_SECONDS + KEY_RING_SYNC_INTERVAL_SECONDS);
+        let expired_kids: Vec<String> = self
+            .keys
+            .iter()
+            .filter(|key| key.state == SigningKeyState::VerifyOnly && key.state_changed_at + grace_period <= now)
+            .map(|key| key.kid.clone())
+            .collect();
+        for kid in expired_kids {
+            self.retire(&kid).await?;
+        }
+
+        Ok(())
+    }
+
+    fn encode(&self, claims: &Claims) -> Result<String, GenerateTokenError> {
+        let key = self.active_key().ok_or(GenerateTokenError::UnexpectedError)?;
+        create_token(claims, &key.keys, &key.kid).map_err(GenerateTokenError::TokenError)
+    }
+
+    fn decode(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        let kid = decode_header(token)?.kid.ok_or(ErrorKind::InvalidToken)?;
+        let key = self.verification_key(&kid).ok_or(ErrorKind::InvalidSignature)?;
+        decode_token(token, &key.keys)
+    }
+
+    // A kid we don't know may belong to a key another instance just rotated in
+    fn should_reload_for(&self, token: &str) -> bool {
+        let Ok(Some(kid)) = decode_header(token).map(|header| header.kid) else {
+            return false;
+        };
+        self.verification_key(&kid).is_none() && self.loaded_at.elapsed() >= KEY_RING_MIN_RELOAD_INTERVAL
+    }
+}
+
+// Keep the ring in sync with the store and rotate keys on schedule
+pub async fn run_key_rotation(key_ring: KeyRingType) {
+    let rotation_interval = chrono::Duration::seconds(*JWT_KEY_ROTATION_INTERVAL_SECONDS);
+    let mut interval = tokio::time::interval(Duration::from_secs(KEY_RING_SYNC_INTERVAL_SECONDS as u64));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = key_ring.write().await.rotate_if_due(rotation_interval).await {
+            eprintln!("Failed to rotate signing keys: {}", e);
+        }
+    }
+}
+
+// Record a new session for the user and create its auth and refresh cookies
+pub async fn start_session(
+    user: &User,
+    client_info: ClientInfo,
+    state: &AppState,
+) -> Result<(Cookie<'static>, Cookie<'static>), GenerateTokenError> {
+    let session = Session::new(user.email.clone(), client_info.user_agent, client_info.ip_address);
+    let session_id = session.id.clone();
+
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    let auth_cookie = generate_auth_cookie(user, &session_id, &state.key_ring).await?;
+    let refresh_cookie = generate_refresh_cookie(user, session_id.into(), &state.refresh_token_store).await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
+// Ban every access token of the session. No token of the session outlives the ban,
+// and revocation caches learn about it through the revocation feed.
+pub async fn ban_session_tokens(session_id: &SessionId, state: &AppState) -> Result<(), BannedTokenStoreError> {
+    let exp = Utc::now().timestamp() + TOKEN_TTL_SECONDS;
+    match state.banned_token_store.write().await.add_token(session_id.as_ref().to_owned(), exp).await {
+        Ok(_) | Err(BannedTokenStoreError::TokenAlreadyBanned) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+// Log the user out everywhere: tokens carrying the current session epoch are rejected from now
+// on, and every session is dropped along with its refresh tokens, and its access tokens banned
+// for verifiers that only check bans
+pub async fn revoke_all_sessions(email: &Email, state: &AppState) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .increment_session_epoch(email)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)?;
+
+    let mut session_store = state.session_store.write().await;
+    let sessions = session_store.get_sessions(email).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+    session_store.remove_sessions(email).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+    drop(session_store);
+
+    for session in sessions {
+        state
+            .refresh_token_store
+            .write()
+            .await
+            .revoke_family(&session.id.clone().into())
+            .await
+            .map_err(|_| AuthAPIError::UnexpectedError)?;
+        ban_session_tokens(&session.id, state).await.map_err(|_| AuthAPIError::UnexpectedError)?;
+    }
+
+    Ok(())
+}
+
+// Create cookie with a new JWT auth token for the given session
+pub async fn generate_auth_cookie(
+    user: &User,
+    session_id: &SessionId,
+    key_ring: &KeyRingType,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let token = generate_auth_token(user, session_id, key_ring).await?;
     Ok(create_auth_cookie(token))
 }
 
@@ -27,6 +516,109 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+// Create a new refresh token in the given family, persist it and wrap it in a cookie
+pub async fn generate_refresh_cookie(
+    user: &User,
+    family_id: RefreshTokenFamilyId,
+    refresh_token_store: &RefreshTokenStoreType,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let token = RefreshToken::default();
+
+    refresh_token_store
+        .write()
+        .await
+        .add_token(token.clone(), user.email.clone(), family_id, user.session_epoch)
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    Ok(create_refresh_cookie(token))
+}
+
+// Create the long-lived refresh cookie, scoped to the refresh endpoint only
+fn create_refresh_cookie(token: RefreshToken) -> Cookie<'static> {
+    let cookie = Cookie::build((REFRESH_TOKEN_COOKIE_NAME, token.as_ref().to_owned()))
+        .path(REFRESH_TOKEN_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build();
+
+    cookie
+}
+
+// Remember the user's browser, so their logins from it skip 2FA until the device expires
+pub async fn trust_device(
+    user: &User,
+    client_info: ClientInfo,
+    state: &AppState,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let ttl = chrono::Duration::try_seconds(TRUSTED_DEVICE_TTL_SECONDS)
+        .ok_or(GenerateTokenError::UnexpectedError)?;
+    let device = TrustedDevice::new(user.email.clone(), client_info.user_agent, client_info.ip_address, ttl);
+    let device_id = device.id.clone();
+
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .add_device(device)
+        .await
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    Ok(create_trusted_device_cookie(&device_id))
+}
+
+// Whether the request carries a trusted device cookie of the user. Any doubt about the
+// cookie means it isn't trusted, and the user goes through 2FA as usual.
+pub async fn is_trusted_device(jar: &CookieJar, user: &User, state: &AppState) -> bool {
+    let device_id = jar
+        .get(TRUSTED_DEVICE_COOKIE_NAME)
+        .and_then(|cookie| verify_cookie_value(TRUSTED_DEVICE_COOKIE_NAME, cookie.value(), &COOKIE_SIGNING_KEY))
+        .and_then(|id| TrustedDeviceId::parse(id).ok());
+    let Some(device_id) = device_id else {
+        return false;
+    };
+
+    match state.trusted_device_store.read().await.get_device(&device_id).await {
+        Ok(device) => device.email == user.email,
+        Err(_) => false,
+    }
+}
+
+// Create the long-lived trusted device cookie, signed so it can't be forged
+fn create_trusted_device_cookie(device_id: &TrustedDeviceId) -> Cookie<'static> {
+    let value = sign_cookie_value(TRUSTED_DEVICE_COOKIE_NAME, device_id.as_ref(), &COOKIE_SIGNING_KEY);
+    let cookie = Cookie::build((TRUSTED_DEVICE_COOKIE_NAME, value))
+        .path(TRUSTED_DEVICE_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(TRUSTED_DEVICE_TTL_SECONDS))
+        .build();
+
+    cookie
+}
+
+// Create the short-lived login attempt cookie, which ties a login waiting for 2FA to the browser
+// that started it. Someone who only got hold of the 2FA code can't finish the login elsewhere.
+pub fn create_login_attempt_cookie(login_attempt_id: &LoginAttemptId) -> Cookie<'static> {
+    let value = sign_cookie_value(LOGIN_ATTEMPT_COOKIE_NAME, login_attempt_id.as_ref(), &COOKIE_SIGNING_KEY);
+    let cookie = Cookie::build((LOGIN_ATTEMPT_COOKIE_NAME, value))
+        .path(LOGIN_ATTEMPT_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(LOGIN_ATTEMPT_TTL_SECONDS))
+        .build();
+
+    cookie
+}
+
+// Whether the request carries the login attempt cookie of the given login attempt
+pub fn has_login_attempt_cookie(jar: &CookieJar, login_attempt_id: &LoginAttemptId) -> bool {
+    jar.get(LOGIN_ATTEMPT_COOKIE_NAME)
+        .and_then(|cookie| verify_cookie_value(LOGIN_ATTEMPT_COOKIE_NAME, cookie.value(), &COOKIE_SIGNING_KEY))
+        .is_some_and(|id| id == login_attempt_id.as_ref())
+}
+
 #[derive(Debug)]
 pub enum GenerateTokenError {
     TokenError(jsonwebtoken::errors::Error),
@@ -36,62 +628,176 @@ pub enum GenerateTokenError {
 // This value determines how long the JWT auth token is valid for
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
+// This value determines how long a refresh token can be exchanged for a new JWT auth token
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 30; // 30 days
+
+// This value determines how long logins from a trusted device skip 2FA
+pub const TRUSTED_DEVICE_TTL_SECONDS: i64 = 60 * 60 * 24 * 30; // 30 days
+
+// This value determines how long a login can wait for its second factor, as long as its 2FA code
+pub const LOGIN_ATTEMPT_TTL_SECONDS: i64 = 600; // 10 minutes
+
+// How often each instance reloads the key ring from the store
+pub const KEY_RING_SYNC_INTERVAL_SECONDS: i64 = 60;
+
+// Unknown kids trigger a reload at most this often
+const KEY_RING_MIN_RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
 // Create JWT auth token
-fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
+async fn generate_auth_token(user: &User, session_id: &SessionId, key_ring: &KeyRingType) -> Result<String, GenerateTokenError> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .ok_or(GenerateTokenError::UnexpectedError)?;
 
+    let now = Utc::now();
+
     // Create JWT expiration time
-    let exp = Utc::now()
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(GenerateTokenError::UnexpectedError)?
         .timestamp();
 
-    // Cast exp to a usize, which is what Claims expects
+    // Cast timestamps to usize, which is what Claims expects
     let exp: usize = exp
         .try_into()
         .map_err(|_| GenerateTokenError::UnexpectedError)?;
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+
+    let claims = Claims {
+        sub: user.email.as_ref().to_owned(),
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
+        iat,
+        nbf: iat,
+        exp,
+        jti: uuid::Uuid::new_v4().to_string(),
+        sid: session_id.as_ref().to_owned(),
+        session_epoch: user.session_epoch,
+    };
 
-    let sub = email.as_ref().to_owned();
+    key_ring.read().await.encode(&claims)
+}
 
-    let claims = Claims { sub, exp };
+// Check if JWT auth token is valid by decoding it with the key named by its kid
+pub async fn validate_token(token: &str, key_ring: &KeyRingType) -> Result<Claims, jsonwebtoken::errors::Error> {
+    {
+        let key_ring = key_ring.read().await;
+        if !key_ring.should_reload_for(token) {
+            return key_ring.decode(token);
+        }
+    }
 
-    create_token(&claims).map_err(GenerateTokenError::TokenError)
+    let mut key_ring = key_ring.write().await;
+    // Another request may have reloaded while we waited for the lock
+    if key_ring.should_reload_for(token) {
+        // A failed reload leaves the current keys in place
+        key_ring.reload().await.ok();
+    }
+    key_ring.decode(token)
 }
 
-// Check if JWT auth token is valid by decoding it using the JWT secret
-pub async fn validate_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+// Validate a token presented by a client: it must be correctly signed, not banned, and
+// issued after the user last logged out everywhere
+pub async fn authenticate_token(token: &str, state: &AppState) -> Result<Claims, AuthAPIError> {
+    let claims = validate_token(token, &state.key_ring)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Tokens are banned one by one on logout, or by session when a session is revoked
+    let banned_token_store = state.banned_token_store.read().await;
+    if banned_token_store.is_token_banned(claims.jti.clone()).await
+        || banned_token_store.is_token_banned(claims.sid.clone()).await
+    {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    drop(banned_token_store);
+
+    let email = Email::parse(claims.sub.clone()).map_err(|_| AuthAPIError::InvalidToken)?;
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    };
+    if claims.session_epoch != user.session_epoch {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let session_id = SessionId::parse(claims.sid.clone()).map_err(|_| AuthAPIError::InvalidToken)?;
+    match state.session_store.write().await.touch_session(&session_id).await {
+        Ok(_) => {}
+        Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(_) => return Err(AuthAPIError::UnexpectedError),
+    }
+
+    Ok(claims)
+}
+
+// Authenticates the token in the `jwt` cookie, see `authenticate_token`
+pub async fn authenticate_cookie(jar: &CookieJar, state: &AppState) -> Result<Claims, AuthAPIError> {
+    let token = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
+    authenticate_token(token.value(), state).await
+}
+
+fn decode_token(token: &str, keys: &JwtKeys) -> Result<Claims, jsonwebtoken::errors::Error> {
     decode::<Claims>(
         token,
-        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
-        &Validation::default(),
+        &keys.decoding_key,
+        &validation(keys.algorithm),
     )
         .map(|data| data.claims)
 }
 
-// Create JWT auth token by encoding claims using the JWT secret
-fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
+// Only accept tokens we issued for this deployment's audience, signed with the
+// algorithm of the key, whatever the token header claims
+fn validation(algorithm: Algorithm) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+    validation
+}
+
+// Create JWT auth token by encoding claims using the given signing key
+fn create_token(claims: &Claims, keys: &JwtKeys, kid: &str) -> Result<String, jsonwebtoken::errors::Error> {
+    let mut header = Header::new(keys.algorithm);
+    header.kid = Some(kid.to_owned());
+
     encode(
-        &jsonwebtoken::Header::default(),
+        &header,
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+        &keys.encoding_key,
     )
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-    pub sub: String,
-    pub exp: usize,
-}
+// Shared with the services that validate tokens through auth-service-middleware
+pub use auth_service_middleware::Claims;
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    use crate::domain::data_stores::SigningKeyStore;
+    use crate::domain::HashedPassword;
+    use crate::services::data_stores::hashmap_signing_key_store::HashmapSigningKeyStore;
     use super::*;
 
+    fn test_user() -> User {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        User::new(email, HashedPassword("password".to_owned()), false)
+    }
+
+    async fn test_key_ring() -> KeyRingType {
+        let store = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
+        Arc::new(RwLock::new(KeyRing::load(store).await.unwrap()))
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let user = test_user();
+        let cookie = generate_auth_cookie(&user, &SessionId::default(), &test_key_ring().await).await.unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -110,18 +816,56 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_create_refresh_cookie() {
+        let token = RefreshToken::default();
+        let cookie = create_refresh_cookie(token.clone());
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.value(), token.as_ref());
+        assert_eq!(cookie.path(), Some(REFRESH_TOKEN_COOKIE_PATH));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS)));
+    }
+
+    #[test]
+    fn test_login_attempt_cookie() {
+        let login_attempt_id = LoginAttemptId::default();
+        let cookie = create_login_attempt_cookie(&login_attempt_id);
+        assert_eq!(cookie.name(), LOGIN_ATTEMPT_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some(LOGIN_ATTEMPT_COOKIE_PATH));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(LOGIN_ATTEMPT_TTL_SECONDS)));
+
+        let jar = CookieJar::new().add(cookie);
+        assert!(has_login_attempt_cookie(&jar, &login_attempt_id));
+        // Not for another login attempt, and not without the cookie
+        assert!(!has_login_attempt_cookie(&jar, &LoginAttemptId::default()));
+        assert!(!has_login_attempt_cookie(&CookieJar::new(), &login_attempt_id));
+
+        // The value can't be swapped for another login attempt's id
+        let other_attempt_id = LoginAttemptId::default();
+        let forged = Cookie::new(LOGIN_ATTEMPT_COOKIE_NAME, other_attempt_id.as_ref().to_owned());
+        assert!(!has_login_attempt_cookie(&CookieJar::new().add(forged), &other_attempt_id));
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let user = test_user();
+        let key_ring = test_key_ring().await;
+        let result = generate_auth_token(&user, &SessionId::default(), &key_ring).await.unwrap();
         assert_eq!(result.split('.').count(), 3);
+
+        let kid = decode_header(&result).unwrap().kid;
+        assert_eq!(kid.as_deref(), key_ring.read().await.active_kid());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let result = validate_token(&token).await.unwrap();
+        let user = test_user();
+        let key_ring = test_key_ring().await;
+        let token = generate_auth_token(&user, &SessionId::default(), &key_ring).await.unwrap();
+        let result = validate_token(&token, &key_ring).await.unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -135,7 +879,208 @@ mod tests {
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let result = validate_token(&token).await;
+        let result = validate_token(&token, &test_key_ring().await).await;
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    fn test_claims() -> Claims {
+        let now = Utc::now().timestamp() as usize;
+        Claims {
+            sub: "test@example.com".to_owned(),
+            iss: JWT_ISSUER.to_owned(),
+            aud: JWT_AUDIENCE.to_owned(),
+            iat: now,
+            nbf: now,
+            exp: now + 60,
+            jti: uuid::Uuid::new_v4().to_string(),
+            sid: SessionId::default().as_ref().to_owned(),
+            session_epoch: 0,
+        }
+    }
+
+    fn test_keys(algorithm: Algorithm) -> JwtKeys {
+        let (private_key, public_key): (&[u8], &[u8]) = match algorithm {
+            Algorithm::RS256 => (
+                include_bytes!("../../tests/fixtures/keys/rsa_private.pem"),
+                include_bytes!("../../tests/fixtures/keys/rsa_public.pem"),
+            ),
+            Algorithm::ES256 => (
+                include_bytes!("../../tests/fixtures/keys/ec_private.pem"),
+                include_bytes!("../../tests/fixtures/keys/ec_public.pem"),
+            ),
+            Algorithm::EdDSA => (
+                include_bytes!("../../tests/fixtures/keys/ed25519_private.pem"),
+                include_bytes!("../../tests/fixtures/keys/ed25519_public.pem"),
+            ),
+            _ => unreachable!(),
+        };
+        JwtKeys::from_pem(algorithm, private_key, public_key).unwrap()
+    }
+
+    #[test]
+    fn test_asymmetric_keys_sign_and_verify() {
+        for algorithm in [Algorithm::RS256, Algorithm::ES256, Algorithm::EdDSA] {
+            let keys = test_keys(algorithm);
+            let token = create_token(&test_claims(), &keys, "test-kid").unwrap();
+            assert_eq!(jsonwebtoken::decode_header(&token).unwrap().alg, algorithm);
+
+            let claims = decode_token(&token, &keys).unwrap();
+            assert_eq!(claims.sub, "test@example.com");
+        }
+    }
+
+    #[test]
+    fn test_token_signed_with_other_algorithm_is_rejected() {
+        let token = create_token(&test_claims(), &test_keys(Algorithm::RS256), "test-kid").unwrap();
+        assert!(decode_token(&token, &test_keys(Algorithm::ES256)).is_err());
+
+        let token = create_token(&test_claims(), &JwtKeys::from_secret(b"secret"), "test-kid").unwrap();
+        assert!(decode_token(&token, &test_keys(Algorithm::RS256)).is_err());
+    }
+
+    #[test]
+    fn test_jwks_can_verify_tokens() {
+        for algorithm in [Algorithm::RS256, Algorithm::ES256, Algorithm::EdDSA] {
+            let keys = test_keys(algorithm);
+            let token = create_token(&test_claims(), &keys, "test-kid").unwrap();
+
+            let jwks = keys.jwks();
+            assert_eq!(jwks.keys.len(), 1);
+            let decoding_key = DecodingKey::from_jwk(&jwks.keys[0]).unwrap();
+            let claims = decode::<Claims>(&token, &decoding_key, &validation(algorithm)).unwrap().claims;
+            assert_eq!(claims.sub, "test@example.com");
+        }
+    }
+
+    #[test]
+    fn test_jwks_does_not_publish_shared_secret() {
+        assert!(JwtKeys::from_secret(b"secret").jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_token_for_other_issuer_or_audience_is_rejected() {
+        let keys = test_keys(Algorithm::ES256);
+
+        let claims = Claims { iss: "other-issuer".to_owned(), ..test_claims() };
+        let token = create_token(&claims, &keys, "test-kid").unwrap();
+        assert_eq!(decode_token(&token, &keys).unwrap_err().kind(), &ErrorKind::InvalidIssuer);
+
+        let claims = Claims { aud: "other-service".to_owned(), ..test_claims() };
+        let token = create_token(&claims, &keys, "test-kid").unwrap();
+        assert_eq!(decode_token(&token, &keys).unwrap_err().kind(), &ErrorKind::InvalidAudience);
+    }
+
+    #[test]
+    fn test_token_not_yet_valid_is_rejected() {
+        let keys = test_keys(Algorithm::ES256);
+        let claims = test_claims();
+        let claims = Claims { nbf: claims.nbf + 3600, exp: claims.exp + 3600, ..claims };
+        let token = create_token(&claims, &keys, "test-kid").unwrap();
+        assert_eq!(decode_token(&token, &keys).unwrap_err().kind(), &ErrorKind::ImmatureSignature);
+    }
+
+    #[tokio::test]
+    async fn test_generated_tokens_have_unique_jti() {
+        let user = test_user();
+        let key_ring = test_key_ring().await;
+        let token_1 = generate_auth_token(&user, &SessionId::default(), &key_ring).await.unwrap();
+        let token_2 = generate_auth_token(&user, &SessionId::default(), &key_ring).await.unwrap();
+
+        let claims_1 = validate_token(&token_1, &key_ring).await.unwrap();
+        let claims_2 = validate_token(&token_2, &key_ring).await.unwrap();
+        assert_ne!(claims_1.jti, claims_2.jti);
+        assert_eq!(claims_1.iss, *JWT_ISSUER);
+        assert_eq!(claims_1.aud, *JWT_AUDIENCE);
+        assert!(claims_1.iat <= claims_1.nbf && claims_1.nbf < claims_1.exp);
+    }
+
+    #[test]
+    fn test_generated_signing_keys_sign_and_verify() {
+        for algorithm in [Algorithm::HS256, Algorithm::RS256, Algorithm::ES256, Algorithm::EdDSA] {
+            let signing_key = generate_signing_key(algorithm).unwrap();
+            assert_eq!(signing_key.public_key.is_some(), algorithm != Algorithm::HS256);
+            // The private key is only ever held encrypted
+            let private_key = signing_key.private_key.decrypt(&SIGNING_KEY_ENCRYPTION_KEY).unwrap();
+            assert!(!signing_key.private_key.as_ref().contains(&private_key));
+            assert_eq!(signing_key.private_key.decrypt(&[8; 32]), Err(SigningKeyError::Encryption));
+
+            let keys = JwtKeys::try_from(&signing_key).unwrap();
+            let token = create_token(&test_claims(), &keys, &signing_key.kid).unwrap();
+            assert_eq!(decode_token(&token, &keys).unwrap().sub, "test@example.com");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotated_key_keeps_verifying_until_retired() {
+        let user = test_user();
+        let key_ring = test_key_ring().await;
+        let old_kid = key_ring.read().await.active_kid().unwrap().to_owned();
+        let old_token = generate_auth_token(&user, &SessionId::default(), &key_ring).await.unwrap();
+
+        let new_kid = key_ring.write().await.rotate().await.unwrap();
+        assert_ne!(new_kid, old_kid);
+
+        let new_token = generate_auth_token(&user, &SessionId::default(), &key_ring).await.unwrap();
+        assert_eq!(decode_header(&new_token).unwrap().kid, Some(new_kid.clone()));
+        assert!(validate_token(&new_token, &key_ring).await.is_ok());
+        assert!(validate_token(&old_token, &key_ring).await.is_ok());
+
+        // The active key can't be retired, only demoted by a rotation
+        let retire_res = key_ring.write().await.retire(&new_kid).await;
+        assert!(matches!(retire_res, Err(KeyRingError::StoreError(SigningKeyStoreError::ActiveKeyConflict))));
+
+        key_ring.write().await.retire(&old_kid).await.unwrap();
+        assert!(validate_token(&old_token, &key_ring).await.is_err());
+        assert!(validate_token(&new_token, &key_ring).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_if_due() {
+        let key_ring = test_key_ring().await;
+        let kid = key_ring.read().await.active_kid().unwrap().to_owned();
+
+        key_ring.write().await.rotate_if_due(chrono::Duration::days(1)).await.unwrap();
+        assert_eq!(key_ring.read().await.active_kid(), Some(kid.as_str()));
+
+        key_ring.write().await.rotate_if_due(chrono::Duration::zero()).await.unwrap();
+        assert_ne!(key_ring.read().await.active_kid(), Some(kid.as_str()));
+        // Freshly demoted keys stay around for the tokens they signed
+        assert!(key_ring.read().await.verification_key(&kid).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_key_ring_picks_up_rotation_from_another_instance() {
+        let user = test_user();
+        let store: SigningKeyStoreType = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
+        let instance_a: KeyRingType = Arc::new(RwLock::new(KeyRing::load(store.clone()).await.unwrap()));
+        let instance_b: KeyRingType = Arc::new(RwLock::new(KeyRing::load(store).await.unwrap()));
+        assert_eq!(instance_a.read().await.active_kid(), instance_b.read().await.active_kid());
+
+        instance_a.write().await.rotate().await.unwrap();
+        let token = generate_auth_token(&user, &SessionId::default(), &instance_a).await.unwrap();
+
+        // Instance B learns about the new kid when it first sees it
+        instance_b.write().await.loaded_at -= KEY_RING_MIN_RELOAD_INTERVAL;
+        assert!(validate_token(&token, &instance_b).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_jwks_publishes_verification_keys_with_kid() {
+        let store = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
+        store.write().await.add_key(generate_signing_key(Algorithm::ES256).unwrap()).await.unwrap();
+        let mut key_ring = KeyRing::load(store.clone()).await.unwrap();
+        let old_kid = key_ring.active_kid().unwrap().to_owned();
+
+        // Rotation generates keys with the configured algorithm
+        let new_key = generate_signing_key(Algorithm::ES256).unwrap();
+        let new_kid = new_key.kid.clone();
+        store.write().await.rotate_key(&old_kid, new_key).await.unwrap();
+        key_ring.reload().await.unwrap();
+
+        let kids: Vec<_> = key_ring.jwks().keys.into_iter().map(|jwk| jwk.common.key_id.unwrap()).collect();
+        assert_eq!(kids, vec![old_kid.clone(), new_kid]);
+
+        key_ring.retire(&old_kid).await.unwrap();
+        assert_eq!(key_ring.jwks().keys.len(), 1);
+    }
+}
@@ -1,15 +1,71 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use std::env as std_env;
 
+use crate::domain::data_stores::TwoFaCodeLimits;
+
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+// The refresh cookie is only ever sent to the endpoint that consumes it
+pub const REFRESH_TOKEN_COOKIE_PATH: &str = "/refresh";
+pub const TRUSTED_DEVICE_COOKIE_NAME: &str = "trusted_device";
+// Only logins need to know the device is trusted
+pub const TRUSTED_DEVICE_COOKIE_PATH: &str = "/login";
+pub const LOGIN_ATTEMPT_COOKIE_NAME: &str = "login_attempt";
+// Only the 2FA step of a login needs the login attempt
+pub const LOGIN_ATTEMPT_COOKIE_PATH: &str = "/verify-2fa";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1"; // New!
+pub const DEFAULT_JWT_ALGORITHM: &str = "HS256";
+pub const DEFAULT_JWT_ISSUER: &str = "auth-service";
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+pub const DEFAULT_JWT_KEY_ROTATION_INTERVAL_SECONDS: i64 = 60 * 60 * 24 * 7; // 7 days
+// Name authenticator apps list the account under
+pub const TOTP_ISSUER: &str = "auth-service";
+// Wrong 2FA codes a login attempt survives
+pub const DEFAULT_TWO_FA_MAX_FAILED_ATTEMPTS: u32 = 5;
+// New 2FA codes a login attempt can ask for, and how long it has to wait between them
+pub const DEFAULT_TWO_FA_MAX_RESENDS: u32 = 3;
+pub const DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS: u64 = 30;
+// Login attempts waiting for 2FA a user can have at once
+pub const DEFAULT_TWO_FA_MAX_PENDING_ATTEMPTS: u32 = 5;
+// Name authenticators show passkeys under
+pub const WEBAUTHN_RP_NAME: &str = "auth-service";
+pub const DEFAULT_WEBAUTHN_RP_ID: &str = "localhost";
+pub const DEFAULT_WEBAUTHN_ORIGIN: &str = "http://localhost:3000";
+pub const DEFAULT_PUBLIC_BASE_URL: &str = "http://localhost:3000";
 
 
 lazy_static! {
     pub static ref JWT_SECRET: String = set_token();
     pub static ref DATABASE_URL: String = set_db_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host(); // New!
+    pub static ref JWT_ALGORITHM: String = set_jwt_algorithm();
+    pub static ref JWT_PRIVATE_KEY_PATH: Option<String> = set_optional(env::JWT_PRIVATE_KEY_PATH_ENV_VAR);
+    pub static ref JWT_PUBLIC_KEY_PATH: Option<String> = set_optional(env::JWT_PUBLIC_KEY_PATH_ENV_VAR);
+    // Tokens are issued with, and must carry, this issuer and audience
+    pub static ref JWT_ISSUER: String = set_optional(env::JWT_ISSUER_ENV_VAR).unwrap_or(DEFAULT_JWT_ISSUER.to_owned());
+    pub static ref JWT_AUDIENCE: String = set_optional(env::JWT_AUDIENCE_ENV_VAR).unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned());
+    pub static ref JWT_KEY_ROTATION_INTERVAL_SECONDS: i64 = set_key_rotation_interval();
+    // AES-256 key TOTP secrets are encrypted with before they are stored
+    pub static ref TOTP_ENCRYPTION_KEY: [u8; 32] = set_key(env::TOTP_ENCRYPTION_KEY_ENV_VAR);
+    // AES-256 key private signing keys are encrypted with before they are stored
+    pub static ref SIGNING_KEY_ENCRYPTION_KEY: [u8; 32] = set_key(env::SIGNING_KEY_ENCRYPTION_KEY_ENV_VAR);
+    // HMAC-SHA256 key for cookies the server has to be able to trust, like the trusted device cookie
+    pub static ref COOKIE_SIGNING_KEY: [u8; 32] = set_key(env::COOKIE_SIGNING_KEY_ENV_VAR);
+    // HMAC-SHA256 key 2FA codes are hashed with before they are stored
+    pub static ref TWO_FA_CODE_HMAC_KEY: [u8; 32] = set_key(env::TWO_FA_CODE_HMAC_KEY_ENV_VAR);
+    pub static ref TWO_FA_CODE_LIMITS: TwoFaCodeLimits = set_two_fa_code_limits();
+    // Passkeys are bound to this domain, and only accepted from pages served from the origin
+    pub static ref WEBAUTHN_RP_ID: String = set_optional(env::WEBAUTHN_RP_ID_ENV_VAR).unwrap_or(DEFAULT_WEBAUTHN_RP_ID.to_owned());
+    pub static ref WEBAUTHN_ORIGIN: String = set_optional(env::WEBAUTHN_ORIGIN_ENV_VAR).unwrap_or(DEFAULT_WEBAUTHN_ORIGIN.to_owned());
+    // SMS provider 2FA codes are sent through. Without a base URL, messages are only logged.
+    pub static ref SMS_PROVIDER_BASE_URL: Option<String> = set_optional(env::SMS_PROVIDER_BASE_URL_ENV_VAR);
+    pub static ref SMS_PROVIDER_AUTH_TOKEN: Option<String> = set_optional(env::SMS_PROVIDER_AUTH_TOKEN_ENV_VAR);
+    pub static ref SMS_SENDER: Option<String> = set_optional(env::SMS_SENDER_ENV_VAR);
+    // Where users reach auth-service, for the links in emails
+    pub static ref PUBLIC_BASE_URL: String = set_optional(env::PUBLIC_BASE_URL_ENV_VAR).unwrap_or(DEFAULT_PUBLIC_BASE_URL.to_owned());
 }
 
 fn set_db_url() -> String {
@@ -35,11 +91,72 @@ fn set_redis_host() -> String {
     std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
 }
 
+fn set_jwt_algorithm() -> String {
+    set_optional(env::JWT_ALGORITHM_ENV_VAR).unwrap_or(DEFAULT_JWT_ALGORITHM.to_owned())
+}
+
+fn set_key_rotation_interval() -> i64 {
+    set_optional(env::JWT_KEY_ROTATION_INTERVAL_SECONDS_ENV_VAR)
+        .map(|value| value.parse().expect("JWT_KEY_ROTATION_INTERVAL_SECONDS must be a number of seconds."))
+        .unwrap_or(DEFAULT_JWT_KEY_ROTATION_INTERVAL_SECONDS)
+}
+
+fn set_two_fa_code_limits() -> TwoFaCodeLimits {
+    let number = |name: &str, default: u64| {
+        set_optional(name)
+            .map(|value| value.parse().unwrap_or_else(|_| panic!("{} must be a number.", name)))
+            .unwrap_or(default)
+    };
+    TwoFaCodeLimits {
+        max_failed_attempts: number(env::TWO_FA_MAX_FAILED_ATTEMPTS_ENV_VAR, DEFAULT_TWO_FA_MAX_FAILED_ATTEMPTS.into()) as u32,
+        max_resends: number(env::TWO_FA_MAX_RESENDS_ENV_VAR, DEFAULT_TWO_FA_MAX_RESENDS.into()) as u32,
+        resend_cooldown_seconds: number(env::TWO_FA_RESEND_COOLDOWN_SECONDS_ENV_VAR, DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS),
+        max_pending_attempts: number(env::TWO_FA_MAX_PENDING_ATTEMPTS_ENV_VAR, DEFAULT_TWO_FA_MAX_PENDING_ATTEMPTS.into()) as u32,
+    }
+}
+
+fn set_key(name: &str) -> [u8; 32] {
+    dotenv().ok();
+    let key = std_env::var(name).unwrap_or_else(|_| panic!("{} must be set.", name));
+    STANDARD
+        .decode(key)
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .unwrap_or_else(|| panic!("{} must be 32 bytes, base64 encoded.", name))
+}
+
+// Treats a missing or empty variable the same way
+fn set_optional(name: &str) -> Option<String> {
+    dotenv().ok();
+    std_env::var(name).ok().filter(|value| !value.is_empty())
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const DATABASE_URL: &str = "DATABASE_URL";
 
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME"; // New!
+
+    pub const JWT_ALGORITHM_ENV_VAR: &str = "JWT_ALGORITHM";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_PUBLIC_KEY_PATH_ENV_VAR: &str = "JWT_PUBLIC_KEY_PATH";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const JWT_KEY_ROTATION_INTERVAL_SECONDS_ENV_VAR: &str = "JWT_KEY_ROTATION_INTERVAL_SECONDS";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const SIGNING_KEY_ENCRYPTION_KEY_ENV_VAR: &str = "SIGNING_KEY_ENCRYPTION_KEY";
+    pub const COOKIE_SIGNING_KEY_ENV_VAR: &str = "COOKIE_SIGNING_KEY";
+    pub const TWO_FA_CODE_HMAC_KEY_ENV_VAR: &str = "TWO_FA_CODE_HMAC_KEY";
+    pub const TWO_FA_MAX_FAILED_ATTEMPTS_ENV_VAR: &str = "TWO_FA_MAX_FAILED_ATTEMPTS";
+    pub const TWO_FA_MAX_RESENDS_ENV_VAR: &str = "TWO_FA_MAX_RESENDS";
+    pub const TWO_FA_RESEND_COOLDOWN_SECONDS_ENV_VAR: &str = "TWO_FA_RESEND_COOLDOWN_SECONDS";
+    pub const TWO_FA_MAX_PENDING_ATTEMPTS_ENV_VAR: &str = "TWO_FA_MAX_PENDING_ATTEMPTS";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const SMS_PROVIDER_BASE_URL_ENV_VAR: &str = "SMS_PROVIDER_BASE_URL";
+    pub const PUBLIC_BASE_URL_ENV_VAR: &str = "PUBLIC_BASE_URL";
+    pub const SMS_PROVIDER_AUTH_TOKEN_ENV_VAR: &str = "SMS_PROVIDER_AUTH_TOKEN";
+    pub const SMS_SENDER_ENV_VAR: &str = "SMS_SENDER";
 }
 
 
@@ -0,0 +1,14 @@
+use crate::app_state::AppState;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+
+// Emails the user about a change to their account, so they notice changes they didn't make
+pub async fn notify_user(email: &Email, subject: &str, content: &str, state: &AppState) -> Result<(), AuthAPIError> {
+    state
+        .email_client
+        .read()
+        .await
+        .send_email(email, subject, content)
+        .await
+        .map_err(|_| AuthAPIError::UnexpectedError)
+}
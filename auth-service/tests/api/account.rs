@@ -0,0 +1,98 @@
+use auth_service::domain::data_stores::{LoginAttemptId, TwoFACodeStoreError};
+use auth_service::routes::{AccountExport, RecoveryCodesResponse, TwoFactorAuthResponse};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Signs up and logs in, leaving the session in the app's cookie jar. Returns the email and auth token.
+async fn log_in_new_user(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response.cookies().find(|cookie| cookie.name() == JWT_COOKIE_NAME).unwrap().value().to_owned();
+    (email, token)
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    assert_eq!(app.delete_account(&json!({ "password": "password123" })).await.status().as_u16(), 400);
+    assert_eq!(app.get_account_export().await.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_password_is_wrong() {
+    let (email, _) = log_in_new_user(&app).await;
+
+    let response = app.delete_account(&json!({ "password": "wrong-password" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_delete_account_and_its_data() {
+    let (email, token) = log_in_new_user(&app).await;
+    assert_eq!(app.post_enable_2fa().await.status().as_u16(), 200);
+
+    // A login from another browser waits for its 2FA code
+    let response = reqwest::Client::new()
+        .post(format!("{}/login", app.address))
+        .json(&json!({ "email": email, "password": "password123" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    let login_attempt_id = LoginAttemptId::parse(login_attempt_id).unwrap();
+
+    let response = app.delete_account(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "Your account was deleted");
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let result = app.two_fa_code_store.read().await.get_code(&login_attempt_id).await;
+    assert_eq!(result.map(|_| ()), Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The address is free again, and nothing of the old account comes with it
+    let body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let export = app.get_account_export().await.json::<AccountExport>().await.unwrap();
+    assert!(!export.requires_2fa);
+    assert_eq!(export.recovery_codes_left, 0);
+    assert_eq!(export.sessions.len(), 1);
+}
+
+#[test_with_cleanup]
+async fn should_export_account_data() {
+    let (email, _) = log_in_new_user(&app).await;
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let export = response.json::<AccountExport>().await.unwrap();
+    assert_eq!(export.email, email);
+    assert!(export.email_verified);
+    assert!(!export.requires_2fa);
+    assert!(!export.authenticator_app);
+    assert_eq!(export.two_fa_channel.channel, "email");
+    assert_eq!(export.sessions.len(), 1);
+    assert!(export.sessions[0].current);
+    assert!(export.trusted_devices.is_empty());
+    assert!(export.passkeys.is_empty());
+
+    let response = app.post_enable_2fa().await;
+    let recovery_codes = response.json::<RecoveryCodesResponse>().await.unwrap().recovery_codes;
+    let export = app.get_account_export().await.json::<AccountExport>().await.unwrap();
+    assert!(export.requires_2fa);
+    assert_eq!(export.recovery_codes_left, recovery_codes.len());
+}
@@ -0,0 +1,148 @@
+use auth_service::domain::data_stores::{LoginAttemptId, TwoFACodeStoreError};
+use auth_service::routes::{ChangeEmailResponse, TwoFactorAuthResponse};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Signs up and logs in, leaving the session in the app's cookie jar. Returns the email and auth token.
+async fn log_in_new_user(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response.cookies().find(|cookie| cookie.name() == JWT_COOKIE_NAME).unwrap().value().to_owned();
+    (email, token)
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let body = json!({ "newEmail": get_random_email(), "password": "password123" });
+    assert_eq!(app.post_change_email(&body).await.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_reject_invalid_requests() {
+    let (email, _) = log_in_new_user(&app).await;
+
+    let body = json!({ "newEmail": get_random_email(), "password": "wrong-password" });
+    assert_eq!(app.post_change_email(&body).await.status().as_u16(), 401);
+
+    for new_email in ["invalid", email.as_str()] {
+        let body = json!({ "newEmail": new_email, "password": "password123" });
+        assert_eq!(app.post_change_email(&body).await.status().as_u16(), 400);
+    }
+
+    // Addresses of other users can't be taken over
+    let other = get_random_email();
+    let body = json!({ "email": other, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    let body = json!({ "newEmail": other, "password": "password123" });
+    assert_eq!(app.post_change_email(&body).await.status().as_u16(), 409);
+}
+
+#[test_with_cleanup]
+async fn should_move_account_to_confirmed_address() {
+    let (email, token) = log_in_new_user(&app).await;
+    let new_email = get_random_email();
+
+    let response = app.post_change_email(&json!({ "newEmail": new_email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app.emails.last_email_change_token(&email).is_some());
+    let confirm_token = app.emails.last_email_change_token(&new_email).expect("No confirm link sent");
+
+    // Nothing changes until the new address confirms
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_confirm_email_change(&confirm_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.json::<ChangeEmailResponse>().await.unwrap().message, "Email address changed");
+    assert_eq!(app.get_confirm_email_change(&confirm_token).await.status().as_u16(), 401);
+
+    // Tokens issued to the old address are revoked
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_login(&json!({ "email": new_email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "Your email address was changed");
+}
+
+// Sessions are stored under the address, in the hashmap stores as well as in Postgres
+#[tokio::test]
+async fn should_revoke_refresh_tokens_of_old_address() {
+    let mut app = TestApp::with_hashmap_stores().await;
+    let (email, _) = log_in_new_user(&app).await;
+    let new_email = get_random_email();
+
+    let response = app.post_change_email(&json!({ "newEmail": new_email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let confirm_token = app.emails.last_email_change_token(&new_email).unwrap();
+    assert_eq!(app.get_confirm_email_change(&confirm_token).await.status().as_u16(), 200);
+
+    // Someone else takes the old address. The old refresh cookie mustn't log into their account.
+    let body = json!({ "email": email, "password": "password456", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[test_with_cleanup]
+async fn should_cancel_change_from_current_address() {
+    let (email, _) = log_in_new_user(&app).await;
+    let new_email = get_random_email();
+
+    let response = app.post_change_email(&json!({ "newEmail": new_email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let cancel_token = app.emails.last_email_change_token(&email).expect("No cancel link sent");
+    let confirm_token = app.emails.last_email_change_token(&new_email).expect("No confirm link sent");
+
+    let response = app.get_cancel_email_change(&cancel_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.get_cancel_email_change(&cancel_token).await.status().as_u16(), 401);
+    assert_eq!(app.get_confirm_email_change(&confirm_token).await.status().as_u16(), 401);
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_drop_logins_waiting_for_2fa() {
+    let (email, _) = log_in_new_user(&app).await;
+    assert_eq!(app.post_enable_2fa().await.status().as_u16(), 200);
+
+    // A login from another browser waits for its 2FA code
+    let response = reqwest::Client::new()
+        .post(format!("{}/login", app.address))
+        .json(&json!({ "email": email, "password": "password123" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    let login_attempt_id = LoginAttemptId::parse(login_attempt_id).unwrap();
+
+    let new_email = get_random_email();
+    let response = app.post_change_email(&json!({ "newEmail": new_email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let confirm_token = app.emails.last_email_change_token(&new_email).unwrap();
+    assert_eq!(app.get_confirm_email_change(&confirm_token).await.status().as_u16(), 200);
+
+    let result = app.two_fa_code_store.read().await.get_code(&login_attempt_id).await;
+    assert_eq!(result.map(|_| ()), Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+}
+
+#[test_with_cleanup]
+async fn should_reject_invalid_tokens() {
+    assert_eq!(app.get_confirm_email_change("not-a-token").await.status().as_u16(), 400);
+    let unknown = "a".repeat(64);
+    assert_eq!(app.get_confirm_email_change(&unknown).await.status().as_u16(), 401);
+    assert_eq!(app.get_cancel_email_change(&unknown).await.status().as_u16(), 401);
+}
@@ -0,0 +1,82 @@
+use auth_service::routes::{ChangePasswordResponse, SessionResponse};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Signs up and logs in, leaving the session in the app's cookie jar
+async fn log_in_new_user(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    email
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let body = json!({ "currentPassword": "password123", "newPassword": "new-password123" });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_current_password_is_wrong() {
+    let email = log_in_new_user(&app).await;
+
+    let body = json!({ "currentPassword": "wrong-password", "newPassword": "new-password123" });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_new_password_is_too_weak() {
+    log_in_new_user(&app).await;
+
+    let body = json!({ "currentPassword": "password123", "newPassword": "short" });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_change_password_and_log_out_other_devices() {
+    let email = log_in_new_user(&app).await;
+
+    // Log in from another device, which has its own cookie jar
+    let response = reqwest::Client::new()
+        .post(format!("{}/login", app.address))
+        .json(&json!({ "email": email, "password": "password123" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let other_device_token = response.cookies().find(|cookie| cookie.name() == JWT_COOKIE_NAME).unwrap().value().to_owned();
+
+    let body = json!({ "currentPassword": "password123", "newPassword": "new-password123" });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+    assert_eq!(response.json::<ChangePasswordResponse>().await.unwrap().message, "Password changed");
+
+    let response = app.post_verify_token(&json!({ "token": other_device_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // This device carries on with its new session, the only one left
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let sessions = response.json::<Vec<SessionResponse>>().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_login(&json!({ "email": email, "password": "new-password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(app.emails.last_email_to(&email).is_some_and(|sent| sent.subject == "Your password was changed"));
+}
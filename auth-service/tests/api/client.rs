@@ -0,0 +1,83 @@
+use auth_service_client::{
+    AuthServiceClient, AuthServiceError, LoginOutcome, LoginRequest, SignupRequest, Verify2FARequest,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+fn signup_request(email: &str, requires_2fa: bool) -> SignupRequest {
+    SignupRequest { email: email.to_owned(), password: "password123".to_owned(), requires_2fa }
+}
+
+fn login_request(email: &str) -> LoginRequest {
+    LoginRequest { email: email.to_owned(), password: "password123".to_owned() }
+}
+
+#[test_with_cleanup]
+async fn should_signup_login_verify_and_logout() {
+    let client = AuthServiceClient::new(&app.address);
+    let email = get_random_email();
+
+    let response = client.signup(&signup_request(&email, false)).await.unwrap();
+    assert_eq!(response.message, "User created successfully!");
+
+    // Logins wait for the address to be verified
+    let result = client.login(&login_request(&email)).await;
+    assert!(matches!(result, Err(AuthServiceError::Forbidden(_))));
+    assert_eq!(app.verify_email(&email).await.status().as_u16(), 200);
+
+    let tokens = match client.login(&login_request(&email)).await.unwrap() {
+        LoginOutcome::LoggedIn(tokens) => tokens,
+        outcome => panic!("Unexpected login outcome: {:?}", outcome),
+    };
+    assert!(tokens.refresh_token.is_some());
+    client.verify_token(&tokens.auth_token).await.unwrap();
+
+    client.logout(&tokens.auth_token).await.unwrap();
+    let result = client.verify_token(&tokens.auth_token).await;
+    assert!(matches!(result, Err(AuthServiceError::Unauthorized(_))));
+}
+
+#[test_with_cleanup]
+async fn should_map_error_responses() {
+    let client = AuthServiceClient::new(&app.address);
+    let email = get_random_email();
+    client.signup(&signup_request(&email, false)).await.unwrap();
+
+    let result = client.signup(&signup_request(&email, false)).await;
+    assert!(matches!(result, Err(AuthServiceError::UserAlreadyExists)));
+
+    let result = client.signup(&signup_request("invalid", false)).await;
+    assert!(matches!(result, Err(AuthServiceError::InvalidInput(message)) if message == "Invalid credentials"));
+
+    let wrong_password = LoginRequest { password: "wrong-password".to_owned(), ..login_request(&email) };
+    let result = client.login(&wrong_password).await;
+    assert!(matches!(result, Err(AuthServiceError::Unauthorized(_))));
+}
+
+#[test_with_cleanup]
+async fn should_complete_2fa_login() {
+    let client = AuthServiceClient::new(&app.address);
+    let email = get_random_email();
+    client.signup(&signup_request(&email, true)).await.unwrap();
+    assert_eq!(app.verify_email(&email).await.status().as_u16(), 200);
+
+    let (two_fa, login_attempt_cookie) = match client.login(&login_request(&email)).await.unwrap() {
+        LoginOutcome::TwoFactorRequired { response, login_attempt_cookie } => (response, login_attempt_cookie),
+        outcome => panic!("Unexpected login outcome: {:?}", outcome),
+    };
+    let code = app.emails.last_2fa_code(&email);
+
+    let request = Verify2FARequest {
+        email,
+        login_attempt_id: two_fa.login_attempt_id,
+        two_fa_code: code,
+        remember_device: false,
+    };
+    // The login can't be finished without the login attempt cookie
+    let result = client.verify_2fa(&request, "forged").await;
+    assert!(matches!(result, Err(AuthServiceError::Unauthorized(_))));
+
+    let tokens = client.verify_2fa(&request, &login_attempt_cookie).await.unwrap();
+    client.verify_token(&tokens.auth_token).await.unwrap();
+}
@@ -0,0 +1,87 @@
+use auth_service::domain::error::ErrorResponse;
+use auth_service::routes::VerifyEmailResponse;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+async fn signup_unverified(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup_unverified(&body).await.status().as_u16(), 201);
+    email
+}
+
+#[test_with_cleanup]
+async fn should_return_403_on_login_before_verification() {
+    let email = signup_unverified(&app).await;
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let body = response.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(body.error, "Email address not verified");
+
+    // The password is still checked first
+    let response = app.post_login(&json!({ "email": email, "password": "wrong-password" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_verify_email_with_single_use_link() {
+    let email = signup_unverified(&app).await;
+    let token = app.emails.last_verification_token(&email);
+
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.json::<VerifyEmailResponse>().await.unwrap().message, "Email address verified");
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(app.get_verify_email(&token).await.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_reject_invalid_tokens() {
+    assert_eq!(app.get_verify_email("not-a-token").await.status().as_u16(), 400);
+    let unknown = "a".repeat(64);
+    assert_eq!(app.get_verify_email(&unknown).await.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_resend_verification_link() {
+    let email = signup_unverified(&app).await;
+    let first_token = app.emails.last_verification_token(&email);
+
+    let response = app.post_resend_verification(&json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let second_token = app.emails.last_verification_token(&email);
+    assert_ne!(first_token, second_token);
+
+    assert_eq!(app.get_verify_email(&second_token).await.status().as_u16(), 200);
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_answer_resends_the_same_for_every_address() {
+    let unverified = signup_unverified(&app).await;
+    let verified = get_random_email();
+    let body = json!({ "email": verified, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    let verified_token = app.emails.last_verification_token(&verified);
+
+    let mut messages = Vec::new();
+    for email in [&unverified, &verified, &get_random_email()] {
+        let response = app.post_resend_verification(&json!({ "email": email })).await;
+        assert_eq!(response.status().as_u16(), 200);
+        messages.push(response.json::<VerifyEmailResponse>().await.unwrap().message);
+    }
+    assert!(messages.windows(2).all(|pair| pair[0] == pair[1]));
+
+    // Verified accounts aren't sent another link
+    assert_eq!(app.emails.last_verification_token(&verified), verified_token);
+
+    let response = app.post_resend_verification(&json!({ "email": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -6,15 +6,46 @@ use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use auth_service::{get_postgres_pool, Application};
-use auth_service::app_state::{AppState, BannedTokenStoreType, EmailClientType, TwoFaCodeStoreType, UserStoreType};
+use auth_service::app_state::{ApiClientStoreType, AppState, BannedTokenStoreType, EmailChangeStoreType, EmailClientType, EmailVerificationTokenStoreType, KeyRingType, PasswordResetTokenStoreType, PhoneVerificationStoreType, RecoveryCodeStoreType, RefreshTokenStoreType, SessionStoreType, TrustedDeviceStoreType, TwoFaCodeStoreType, UserStoreType, WebAuthnChallengeStoreType, WebAuthnCredentialStoreType};
 use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
 use auth_service::{get_redis_client};
 use auth_service::utils::constants::REDIS_HOST_NAME;
 use auth_service::services::data_stores::redis_two_fa_code_store::RedisTwoFACodeStore;
-use auth_service::services::data_stores::hashmap_user_store::HashmapUserStore;
 use auth_service::services::data_stores::postgres_user_store::PostgresUserStore;
-use auth_service::services::mock_email_client::MockEmailClient;
-use auth_service::utils::constants::{test, DATABASE_URL, JWT_COOKIE_NAME};
+use auth_service::services::data_stores::postgres_refresh_token_store::PostgresRefreshTokenStore;
+use auth_service::services::data_stores::postgres_session_store::PostgresSessionStore;
+use auth_service::services::data_stores::postgres_recovery_code_store::PostgresRecoveryCodeStore;
+use auth_service::services::data_stores::postgres_webauthn_credential_store::PostgresWebAuthnCredentialStore;
+use auth_service::services::data_stores::redis_webauthn_challenge_store::RedisWebAuthnChallengeStore;
+use auth_service::services::data_stores::redis_trusted_device_store::RedisTrustedDeviceStore;
+use auth_service::services::data_stores::redis_email_verification_token_store::RedisEmailVerificationTokenStore;
+use auth_service::services::data_stores::redis_password_reset_token_store::RedisPasswordResetTokenStore;
+use auth_service::services::data_stores::redis_email_change_store::RedisEmailChangeStore;
+use auth_service::services::data_stores::redis_phone_verification_store::RedisPhoneVerificationStore;
+use auth_service::services::data_stores::postgres_signing_key_store::PostgresSigningKeyStore;
+use auth_service::services::data_stores::postgres_api_client_store::PostgresApiClientStore;
+use auth_service::services::data_stores::banned_token_store::HashsetBannedTokenStore;
+use auth_service::services::data_stores::hashmap_api_client_store::HashmapApiClientStore;
+use auth_service::services::data_stores::hashmap_email_change_store::HashmapEmailChangeStore;
+use auth_service::services::data_stores::hashmap_email_verification_token_store::HashmapEmailVerificationTokenStore;
+use auth_service::services::data_stores::hashmap_password_reset_token_store::HashmapPasswordResetTokenStore;
+use auth_service::services::data_stores::hashmap_phone_verification_store::HashmapPhoneVerificationStore;
+use auth_service::services::data_stores::hashmap_recovery_code_store::HashmapRecoveryCodeStore;
+use auth_service::services::data_stores::hashmap_refresh_token_store::HashmapRefreshTokenStore;
+use auth_service::services::data_stores::hashmap_session_store::HashmapSessionStore;
+use auth_service::services::data_stores::hashmap_signing_key_store::HashmapSigningKeyStore;
+use auth_service::services::data_stores::hashmap_trusted_device_store::HashmapTrustedDeviceStore;
+use auth_service::services::data_stores::hashmap_two_fa_code_store::HashmapTwoFACodeStore;
+use auth_service::services::data_stores::hashmap_user_store::HashmapUserStore;
+use auth_service::services::data_stores::hashmap_webauthn_challenge_store::HashmapWebAuthnChallengeStore;
+use auth_service::services::data_stores::hashmap_webauthn_credential_store::HashmapWebAuthnCredentialStore;
+use auth_service::domain::data_stores::{ApiClient, ApiClientId, ApiClientSecret, TwoFaCodeLimits};
+use auth_service::utils::auth::KeyRing;
+use auth_service::domain::email::Email;
+use auth_service::domain::{EmailClient, SmsClient};
+use auth_service::domain::phone_number::PhoneNumber;
+use auth_service::services::revocation_feed::{run_redis_revocation_listener, RevocationFeed};
+use auth_service::utils::constants::{test, DATABASE_URL, JWT_COOKIE_NAME, TWO_FA_CODE_LIMITS};
 
 pub struct TestApp {
     pub address: String,
@@ -22,14 +53,43 @@ pub struct TestApp {
     pub http_client: reqwest::Client,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFaCodeStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub api_client_store: ApiClientStoreType,
+    pub key_ring: KeyRingType,
+    #[allow(dead_code)]
     pub email_client: EmailClientType,
-    pub db_name: String,
+    pub emails: RecordingEmailClient,
+    pub sms: RecordingSmsClient,
+    // None when the app runs on hashmap stores
+    pub db_name: Option<String>,
     pub clean_up_called: bool
 }
 
+// What `start` builds the app from
+struct TestStores {
+    user_store: UserStoreType,
+    banned_token_store: BannedTokenStoreType,
+    revocation_feed: RevocationFeed,
+    two_fa_code_store: TwoFaCodeStoreType,
+    recovery_code_store: RecoveryCodeStoreType,
+    webauthn_credential_store: WebAuthnCredentialStoreType,
+    webauthn_challenge_store: WebAuthnChallengeStoreType,
+    trusted_device_store: TrustedDeviceStoreType,
+    email_verification_token_store: EmailVerificationTokenStoreType,
+    password_reset_token_store: PasswordResetTokenStoreType,
+    email_change_store: EmailChangeStoreType,
+    phone_verification_store: PhoneVerificationStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+    session_store: SessionStoreType,
+    api_client_store: ApiClientStoreType,
+    key_ring: KeyRingType,
+}
+
 impl TestApp {
     pub async fn clean_up(&mut self) {
-        delete_database(self.db_name.as_str()).await;
+        if let Some(db_name) = &self.db_name {
+            delete_database(db_name).await;
+        }
         self.clean_up_called = true;
     }
 
@@ -37,14 +97,76 @@ impl TestApp {
         let db_name = Uuid::new_v4().to_string();
         let pg_pool = configure_postgresql(db_name.as_str()).await;
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+        let refresh_token_store = Arc::new(RwLock::new(PostgresRefreshTokenStore::new(pg_pool.clone())));
+        let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+        let api_client_store = Arc::new(RwLock::new(PostgresApiClientStore::new(pg_pool.clone())));
+        let recovery_code_store = Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+        let webauthn_credential_store = Arc::new(RwLock::new(PostgresWebAuthnCredentialStore::new(pg_pool.clone())));
+        let signing_key_store = Arc::new(RwLock::new(PostgresSigningKeyStore::new(pg_pool)));
+        let key_ring = Arc::new(RwLock::new(
+            KeyRing::load(signing_key_store).await.expect("Failed to load signing keys"),
+        ));
 
         let redis_conn = Arc::new(RwLock::new(configure_redis()));
         let banned_token_store =  Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn)));
         let redis_conn_2fa = Arc::new(RwLock::new(configure_redis()));
-        let two_fa_code_store =  Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn_2fa)));
-        let email_client =  Arc::new(RwLock::new(MockEmailClient{}));
-        let app_state = AppState::new(user_store, banned_token_store.clone(), two_fa_code_store.clone(), email_client.clone());
+        // A cooldown tests can wait out
+        let two_fa_code_limits = TwoFaCodeLimits { resend_cooldown_seconds: 1, ..*TWO_FA_CODE_LIMITS };
+        let two_fa_code_store =  Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn_2fa, two_fa_code_limits)));
+        let redis_conn_webauthn = Arc::new(RwLock::new(configure_redis()));
+        let webauthn_challenge_store = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(redis_conn_webauthn)));
+        let redis_conn_trusted_devices = Arc::new(RwLock::new(configure_redis()));
+        let trusted_device_store = Arc::new(RwLock::new(RedisTrustedDeviceStore::new(redis_conn_trusted_devices)));
+        let redis_conn_email_verification = Arc::new(RwLock::new(configure_redis()));
+        let email_verification_token_store = Arc::new(RwLock::new(RedisEmailVerificationTokenStore::new(redis_conn_email_verification)));
+        let redis_conn_password_reset = Arc::new(RwLock::new(configure_redis()));
+        let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(redis_conn_password_reset)));
+        let redis_conn_email_change = Arc::new(RwLock::new(configure_redis()));
+        let email_change_store = Arc::new(RwLock::new(RedisEmailChangeStore::new(redis_conn_email_change)));
+        let redis_conn_phone_verification = Arc::new(RwLock::new(configure_redis()));
+        let phone_verification_store = Arc::new(RwLock::new(RedisPhoneVerificationStore::new(redis_conn_phone_verification)));
+        let revocation_feed = RevocationFeed::new();
+        let redis_client = get_redis_client(REDIS_HOST_NAME.to_owned()).expect("Failed to get Redis client");
+        tokio::spawn(run_redis_revocation_listener(redis_client, revocation_feed.clone()));
+        let stores = TestStores { user_store, banned_token_store, revocation_feed, two_fa_code_store, recovery_code_store, webauthn_credential_store, webauthn_challenge_store, trusted_device_store, email_verification_token_store, password_reset_token_store, email_change_store, phone_verification_store, refresh_token_store, session_store, api_client_store, key_ring };
+        Self::start(stores, Some(db_name)).await
+    }
+
+    // Runs the app on in-memory stores, without Postgres or Redis
+    pub async fn with_hashmap_stores() -> Self {
+        let signing_key_store = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
+        let key_ring = Arc::new(RwLock::new(
+            KeyRing::load(signing_key_store).await.expect("Failed to load signing keys"),
+        ));
+        let two_fa_code_limits = TwoFaCodeLimits { resend_cooldown_seconds: 1, ..*TWO_FA_CODE_LIMITS };
+        let stores = TestStores {
+            user_store: Arc::new(RwLock::new(HashmapUserStore::default())),
+            banned_token_store: Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            revocation_feed: RevocationFeed::new(),
+            two_fa_code_store: Arc::new(RwLock::new(HashmapTwoFACodeStore::new(two_fa_code_limits))),
+            recovery_code_store: Arc::new(RwLock::new(HashmapRecoveryCodeStore::default())),
+            webauthn_credential_store: Arc::new(RwLock::new(HashmapWebAuthnCredentialStore::default())),
+            webauthn_challenge_store: Arc::new(RwLock::new(HashmapWebAuthnChallengeStore::default())),
+            trusted_device_store: Arc::new(RwLock::new(HashmapTrustedDeviceStore::default())),
+            email_verification_token_store: Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default())),
+            password_reset_token_store: Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default())),
+            email_change_store: Arc::new(RwLock::new(HashmapEmailChangeStore::default())),
+            phone_verification_store: Arc::new(RwLock::new(HashmapPhoneVerificationStore::default())),
+            refresh_token_store: Arc::new(RwLock::new(HashmapRefreshTokenStore::default())),
+            session_store: Arc::new(RwLock::new(HashmapSessionStore::default())),
+            api_client_store: Arc::new(RwLock::new(HashmapApiClientStore::default())),
+            key_ring,
+        };
+        Self::start(stores, None).await
+    }
+
+    async fn start(stores: TestStores, db_name: Option<String>) -> Self {
+        let emails = RecordingEmailClient::default();
+        let email_client: EmailClientType = Arc::new(RwLock::new(emails.clone()));
+        let sms = RecordingSmsClient::default();
+        let sms_client = Arc::new(RwLock::new(sms.clone()));
+        let app_state = AppState::new(stores.user_store, stores.banned_token_store.clone(), stores.revocation_feed, stores.two_fa_code_store.clone(), stores.recovery_code_store, stores.webauthn_credential_store, stores.webauthn_challenge_store, stores.trusted_device_store, stores.email_verification_token_store, stores.password_reset_token_store, stores.email_change_store, stores.phone_verification_store, stores.refresh_token_store.clone(), stores.session_store, stores.api_client_store.clone(), stores.key_ring.clone(), email_client.clone(), sms_client);
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
             .expect("Failed to build app");
@@ -54,7 +176,6 @@ impl TestApp {
         #[allow(clippy::let_underscore_future)]
         let _ = tokio::spawn(app.run());
 
-        let http_client = reqwest::Client::new();
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
             .cookie_provider(cookie_jar.clone())
@@ -64,39 +185,167 @@ impl TestApp {
             address,
             cookie_jar,
             http_client,
-            banned_token_store,
-            two_fa_code_store,
+            banned_token_store: stores.banned_token_store,
+            two_fa_code_store: stores.two_fa_code_store,
+            refresh_token_store: stores.refresh_token_store,
+            api_client_store: stores.api_client_store,
+            key_ring: stores.key_ring,
             email_client,
+            emails,
+            sms,
             db_name,
             clean_up_called: false
         }
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
-        self.http_client.get(&format!("{}/", self.address)).send().await.unwrap()
+        self.http_client.get(format!("{}/", self.address)).send().await.unwrap()
+    }
+
+
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client.get(format!("{}/.well-known/jwks.json", self.address)).send().await.unwrap()
+    }
+
+    // Signs up and follows the verification link, so the account can be logged into right away
+    pub async fn post_signup(&self, body: &serde_json::Value) -> reqwest::Response {
+        let response = self.post_signup_unverified(body).await;
+        if response.status().as_u16() == 201 {
+            let email = body["email"].as_str().expect("No email in signup body");
+            assert_eq!(self.verify_email(email).await.status().as_u16(), 200);
+        }
+        response
+    }
+
+    pub async fn post_signup_unverified<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/signup", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Follows the latest verification link sent to `email`
+    pub async fn verify_email(&self, email: &str) -> reqwest::Response {
+        let token = self.emails.last_verification_token(email);
+        self.get_verify_email(&token).await
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-verification", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
     }
 
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 
+    pub async fn get_confirm_email_change(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/confirm-email-change", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_cancel_email_change(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/cancel-email-change", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 
-    pub async fn post_signup<Body>(&self, body: &Body) -> reqwest::Response
+    pub async fn delete_account<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .delete(format!("{}/me", &self.address))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
-    pub async fn signup(&self) -> reqwest::Response {
-        self.http_client.post(&format!("{}/signup", self.address)).send().await.unwrap()
+
+    pub async fn get_account_export(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/me/export", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
     }
 
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
         where Body: serde::Serialize, {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
@@ -113,13 +362,181 @@ impl TestApp {
             .await
             .expect("Failed to execute request.")
     }
-    
-    pub async fn verify_2fa(&self) -> reqwest::Response {
-        self.http_client.post(&format!("{}/verify-2fa", self.address)).send().await.unwrap()
+
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enable_2fa(&self) -> reqwest::Response {
+        self.http_client.post(format!("{}/enable-2fa", self.address)).send().await.unwrap()
+    }
+
+    pub async fn post_disable_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/disable-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_two_fa_channel<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa-channel", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_two_fa_channel<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa-channel/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enroll_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/enroll-totp", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/confirm-totp", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/recovery-codes", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_registration_options(&self) -> reqwest::Response {
+        self.http_client.post(format!("{}/passkeys/registration-options", self.address)).send().await.unwrap()
+    }
+
+    pub async fn post_passkey<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_login_options<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/login-options", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_passkey_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/passkeys/login", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
     }
 
     pub async fn logout(&self) -> reqwest::Response {
-        self.http_client.post(&format!("{}/logout", self.address)).send().await.unwrap()
+        self.http_client.post(format!("{}/logout", self.address)).send().await.unwrap()
+    }
+
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client.post(format!("{}/logout-all", self.address)).send().await.unwrap()
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client.get(format!("{}/sessions", self.address)).send().await.unwrap()
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client.delete(format!("{}/sessions/{}", self.address, id)).send().await.unwrap()
+    }
+
+    pub async fn get_trusted_devices(&self) -> reqwest::Response {
+        self.http_client.get(format!("{}/trusted-devices", self.address)).send().await.unwrap()
+    }
+
+    pub async fn delete_trusted_device(&self, id: &str) -> reqwest::Response {
+        self.http_client.delete(format!("{}/trusted-devices/{}", self.address, id)).send().await.unwrap()
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client.post(format!("{}/refresh", self.address)).send().await.unwrap()
+    }
+
+    // Registers an API client allowed to call /introspect and returns its credentials
+    pub async fn add_api_client(&self) -> (String, String) {
+        let id = format!("client-{}", Uuid::new_v4());
+        let secret = ApiClientSecret::default();
+        let client = ApiClient::new(ApiClientId::parse(id.clone()).unwrap(), &secret);
+        self.api_client_store.write().await.add_client(client).await.unwrap();
+        (id, secret.as_ref().to_owned())
+    }
+
+    pub async fn post_introspect<Body>(&self, body: &Body, credentials: Option<(&str, &str)>) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self.http_client.post(format!("{}/introspect", &self.address)).form(body);
+        if let Some((id, secret)) = credentials {
+            request = request.basic_auth(id, Some(secret));
+        }
+        request.send().await.expect("Failed to execute request.")
     }
 
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
@@ -137,6 +554,93 @@ impl TestApp {
 
 }
 
+// Keeps every email the app sends, so tests can read what users receive
+#[derive(Default, Clone)]
+pub struct RecordingEmailClient {
+    sent: Arc<std::sync::Mutex<Vec<SentEmail>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SentEmail {
+    pub recipient: String,
+    pub subject: String,
+    pub content: String,
+}
+
+impl RecordingEmailClient {
+    // The latest email sent to `recipient`, if any
+    pub fn last_email_to(&self, recipient: &str) -> Option<SentEmail> {
+        self.sent.lock().unwrap().iter().rev().find(|email| email.recipient == recipient).cloned()
+    }
+
+    // The code of the latest 2FA email sent to `recipient`
+    pub fn last_2fa_code(&self, recipient: &str) -> String {
+        let email = self.last_email_to(recipient).expect("No email sent");
+        assert_eq!(email.subject, "Your 2fa code");
+        email.content.rsplit(':').next().unwrap().chars().filter(char::is_ascii_digit).collect()
+    }
+
+    // The token in the latest verification link sent to `recipient`
+    pub fn last_verification_token(&self, recipient: &str) -> String {
+        let email = self.sent.lock().unwrap().iter().rev()
+            .find(|email| email.recipient == recipient && email.subject == "Verify your email address")
+            .cloned()
+            .expect("No verification email sent");
+        email.content.rsplit("token=").next().unwrap().trim().to_owned()
+    }
+
+    // The token in the latest email change link sent to `recipient`, if any
+    pub fn last_email_change_token(&self, recipient: &str) -> Option<String> {
+        let sent = self.sent.lock().unwrap();
+        let email = sent.iter().rev()
+            .find(|email| email.recipient == recipient && email.content.contains("-email-change?token="))?;
+        Some(email.content.rsplit("token=").next().unwrap().trim().to_owned())
+    }
+
+    // The token of the latest password reset email sent to `recipient`, if any
+    pub fn last_password_reset_token(&self, recipient: &str) -> Option<String> {
+        let sent = self.sent.lock().unwrap();
+        let email = sent.iter().rev()
+            .find(|email| email.recipient == recipient && email.subject == "Reset your password")?;
+        Some(email.content.rsplit(':').next().unwrap().trim().to_owned())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for RecordingEmailClient {
+    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<(), String> {
+        self.sent.lock().unwrap().push(SentEmail {
+            recipient: recipient.as_ref().to_owned(),
+            subject: subject.to_owned(),
+            content: content.to_owned(),
+        });
+        Ok(())
+    }
+}
+
+// Keeps every SMS the app sends, like `RecordingEmailClient`
+#[derive(Default, Clone)]
+pub struct RecordingSmsClient {
+    sent: Arc<std::sync::Mutex<Vec<(String, String)>>>,
+}
+
+impl RecordingSmsClient {
+    // The code of the latest 2FA message sent to `recipient`, if any
+    pub fn last_2fa_code(&self, recipient: &str) -> Option<String> {
+        let sent = self.sent.lock().unwrap();
+        let (_, content) = sent.iter().rev().find(|(to, _)| to == recipient)?;
+        Some(content.rsplit(':').next().unwrap().chars().filter(char::is_ascii_digit).collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for RecordingSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<(), String> {
+        self.sent.lock().unwrap().push((recipient.as_ref().to_owned(), content.to_owned()));
+        Ok(())
+    }
+}
+
 impl Drop for TestApp {
     fn drop(&mut self) {
         if !self.clean_up_called {
@@ -190,7 +694,7 @@ fn configure_redis() -> redis::Connection {
 async fn configure_postgresql(db_name: &str) -> PgPool {
     let postgresql_conn_url = DATABASE_URL.to_owned();
 
-    configure_database(&postgresql_conn_url, &db_name).await;
+    configure_database(&postgresql_conn_url, db_name).await;
 
     let postgresql_conn_url_with_db = format!("{}/{}", postgresql_conn_url, db_name);
 
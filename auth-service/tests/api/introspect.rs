@@ -0,0 +1,111 @@
+use auth_service::utils::auth::validate_token;
+use auth_service_middleware::{AuthError, RemoteValidator, TokenValidator};
+use serde_json::{json, Value};
+
+use crate::helpers::{signup_and_login, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+#[test_with_cleanup]
+async fn should_return_401_without_client_credentials() {
+    let token = signup_and_login(&app).await;
+
+    let response = app.post_introspect(&[("token", token.as_str())], None).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers().get("www-authenticate").unwrap(), "Basic");
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_client_secret_is_wrong() {
+    let token = signup_and_login(&app).await;
+    let (client_id, _) = app.add_api_client().await;
+    let (_, other_secret) = app.add_api_client().await;
+
+    let response = app
+        .post_introspect(&[("token", token.as_str())], Some((&client_id, &other_secret)))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_describe_active_token() {
+    let token = signup_and_login(&app).await;
+    let (client_id, secret) = app.add_api_client().await;
+
+    let response = app
+        .post_introspect(&[("token", token.as_str()), ("token_type_hint", "access_token")], Some((&client_id, &secret)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["active"], true);
+    assert_eq!(body["scope"], "session");
+    assert_eq!(body["client_id"], "app-service");
+    assert!(body["sub"].as_str().unwrap().ends_with("@example.com"));
+    assert!(body["exp"].as_u64().unwrap() > body["iat"].as_u64().unwrap());
+}
+
+#[test_with_cleanup]
+async fn should_accept_json_body() {
+    let token = signup_and_login(&app).await;
+    let (client_id, secret) = app.add_api_client().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/introspect", app.address))
+        .basic_auth(&client_id, Some(&secret))
+        .json(&json!({ "token": token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["active"], true);
+}
+
+#[test_with_cleanup]
+async fn should_report_invalid_or_revoked_tokens_as_inactive() {
+    let token = signup_and_login(&app).await;
+    let (client_id, secret) = app.add_api_client().await;
+
+    let response = app
+        .post_introspect(&[("token", "invalid")], Some((&client_id, &secret)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body, json!({ "active": false }));
+
+    let response = app.logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_introspect(&[("token", token.as_str())], Some((&client_id, &secret)))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body, json!({ "active": false }));
+}
+
+#[test_with_cleanup]
+async fn should_return_422_if_token_is_missing() {
+    let (client_id, secret) = app.add_api_client().await;
+
+    let response = app
+        .post_introspect(&[("token_type_hint", "access_token")], Some((&client_id, &secret)))
+        .await;
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[test_with_cleanup]
+async fn should_back_the_middleware_remote_validator() {
+    let token = signup_and_login(&app).await;
+    let (client_id, secret) = app.add_api_client().await;
+    let validator = RemoteValidator::new(&app.address, &client_id, &secret);
+
+    let claims = validator.validate(&token).await.unwrap();
+    assert_eq!(claims, validate_token(&token, &app.key_ring).await.unwrap());
+
+    let response = app.logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(validator.validate(&token).await, Err(AuthError::InvalidToken));
+}
@@ -0,0 +1,16 @@
+use crate::helpers::TestApp;
+use auth_service_macros::test_with_cleanup;
+
+#[test_with_cleanup]
+async fn should_return_200_with_key_set() {
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body to JSON");
+
+    assert!(body["keys"].is_array());
+}
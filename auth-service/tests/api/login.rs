@@ -1,5 +1,6 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{utils::constants::JWT_COOKIE_NAME};
+use auth_service::domain::data_stores::LoginAttemptId;
 use auth_service::domain::email::Email;
 use auth_service::routes::TwoFactorAuthResponse;
 use auth_service_macros::test_with_cleanup;
@@ -37,8 +38,9 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
 
     // TODO: assert that `json_body.login_attempt_id` is stored inside `app.two_fa_code_store`
-    let result = app.two_fa_code_store.read().await.get_code(&mail).await.unwrap();
-    assert_eq!(result.0.as_ref(), json_body.login_attempt_id);
+    let login_attempt_id = LoginAttemptId::parse(json_body.login_attempt_id).unwrap();
+    let result = app.two_fa_code_store.read().await.get_code(&login_attempt_id).await.unwrap();
+    assert_eq!(result.0, mail);
 
 }
 
@@ -76,8 +78,6 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
 #[test_with_cleanup]
 async fn should_return_422_if_malformed_input() {
-    let random_email = get_random_email();
-
     // TODO: add more malformed input test cases
     let test_cases = [
         serde_json::json!({
@@ -1,7 +1,7 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME};
+use auth_service::utils::{auth::validate_token, constants::JWT_COOKIE_NAME};
 use reqwest::Url;
 
-use crate::helpers::{get_random_email, signup_and_login, TestApp};
+use crate::helpers::{signup_and_login, TestApp};
 use auth_service_macros::test_with_cleanup;
 
 #[test_with_cleanup]
@@ -29,12 +29,13 @@ async fn should_return_401_if_invalid_token() {
 async fn should_return_200_if_valid_jwt_cookie() {
 
     let token = signup_and_login(&app).await;
+    let claims = validate_token(&token, &app.key_ring).await.unwrap();
 
     let response = app.logout().await;
     assert_eq!(response.status().as_u16(), 200);
 
-    // check that token was added to banned token store
-    assert!(app.banned_token_store.read().await.is_token_banned(token).await);
+    // check that the token id was added to banned token store
+    assert!(app.banned_token_store.read().await.is_token_banned(claims.jti).await);
 }
 
 #[test_with_cleanup]
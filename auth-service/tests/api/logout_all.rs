@@ -0,0 +1,83 @@
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Logs in and returns the auth and refresh token cookie values
+async fn login(app: &TestApp, email: &str) -> (String, String) {
+    let login_body = json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie_value = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("Cookie not found")
+            .value()
+            .to_owned()
+    };
+
+    (cookie_value(JWT_COOKIE_NAME), cookie_value(REFRESH_TOKEN_COOKIE_NAME))
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_invalid_token() {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}=invalid; HttpOnly; SameSite=Lax; Path=/", JWT_COOKIE_NAME),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_invalidate_tokens_from_every_device() {
+    let random_email = get_random_email();
+
+    let signup_body = json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // One login per device. The cookie jar ends up holding the second one.
+    let (other_device_token, other_device_refresh_token) = login(&app, &random_email).await;
+    let (token, _) = login(&app, &random_email).await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    for token in [&other_device_token, &token] {
+        let response = app.post_verify_token(&json!({ "token": token })).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The other device can't mint a fresh token either
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Path=/refresh", REFRESH_TOKEN_COOKIE_NAME, other_device_refresh_token),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Logging in again starts a new valid session
+    let (token, _) = login(&app, &random_email).await;
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -1,7 +1,26 @@
 mod helpers;
+mod account;
+mod change_email;
+mod change_password;
+mod client;
+mod email_verification;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod password_reset;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod revocations;
+mod sessions;
 mod signup;
+mod totp;
+mod trusted_devices;
+mod two_fa;
+mod two_fa_channel;
 mod verify_2fa;
 mod verify_token;
+mod webauthn;
 mod root;
\ No newline at end of file
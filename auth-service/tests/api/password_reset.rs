@@ -0,0 +1,94 @@
+use auth_service::routes::PasswordResetResponse;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    email
+}
+
+async fn request_reset_token(app: &TestApp, email: &str) -> String {
+    let response = app.post_forgot_password(&json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.emails.last_password_reset_token(email).expect("No reset token sent")
+}
+
+#[test_with_cleanup]
+async fn should_reset_password_and_revoke_sessions() {
+    let email = signup(&app).await;
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response.cookies().find(|cookie| cookie.name() == JWT_COOKIE_NAME).unwrap().value().to_owned();
+
+    let reset_token = request_reset_token(&app, &email).await;
+    let response = app.post_reset_password(&json!({ "token": reset_token, "newPassword": "new-password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.json::<PasswordResetResponse>().await.unwrap().message, "Password reset");
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_login(&json!({ "email": email, "password": "new-password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "Your password was reset");
+}
+
+#[test_with_cleanup]
+async fn should_only_accept_a_reset_token_once() {
+    let email = signup(&app).await;
+    let reset_token = request_reset_token(&app, &email).await;
+
+    let body = json!({ "token": reset_token, "newPassword": "new-password123" });
+    assert_eq!(app.post_reset_password(&body).await.status().as_u16(), 200);
+    assert_eq!(app.post_reset_password(&body).await.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_keep_the_token_when_the_new_password_is_rejected() {
+    let email = signup(&app).await;
+    let reset_token = request_reset_token(&app, &email).await;
+
+    let response = app.post_reset_password(&json!({ "token": reset_token, "newPassword": "short" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_reset_password(&json!({ "token": reset_token, "newPassword": "new-password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_reject_invalid_tokens() {
+    let response = app.post_reset_password(&json!({ "token": "not-a-token", "newPassword": "new-password123" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let unknown = "a".repeat(64);
+    let response = app.post_reset_password(&json!({ "token": unknown, "newPassword": "new-password123" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_answer_forgot_password_the_same_for_every_address() {
+    let existing = signup(&app).await;
+    let unknown = get_random_email();
+
+    let mut messages = Vec::new();
+    for email in [&existing, &unknown] {
+        let response = app.post_forgot_password(&json!({ "email": email })).await;
+        assert_eq!(response.status().as_u16(), 200);
+        messages.push(response.json::<PasswordResetResponse>().await.unwrap().message);
+    }
+    assert_eq!(messages[0], messages[1]);
+
+    assert!(app.emails.last_password_reset_token(&existing).is_some());
+    assert!(app.emails.last_password_reset_token(&unknown).is_none());
+
+    let response = app.post_forgot_password(&json!({ "email": "invalid" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,102 @@
+use auth_service::routes::{RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, signup_and_login, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+async fn signup_with_2fa(app: &TestApp, email: &str) -> Vec<String> {
+    let body = json!({ "email": email, "password": "password123", "requires2FA": true });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let body: SignupResponse = response.json().await.unwrap();
+    body.recovery_codes.expect("No recovery codes returned")
+}
+
+async fn verify_2fa_with(app: &TestApp, email: &str, code: &str) -> reqwest::Response {
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": code });
+    app.post_verify_2fa(&body).await
+}
+
+#[test_with_cleanup]
+async fn should_return_recovery_codes_on_signup_with_2fa() {
+    let codes = signup_with_2fa(&app, &get_random_email()).await;
+    assert_eq!(codes.len(), 10);
+
+    let body = json!({ "email": get_random_email(), "password": "password123", "requires2FA": false });
+    let response = app.post_signup(&body).await;
+    let body: SignupResponse = response.json().await.unwrap();
+    assert_eq!(body.recovery_codes, None);
+}
+
+#[test_with_cleanup]
+async fn should_accept_each_recovery_code_once() {
+    let email = get_random_email();
+    let codes = signup_with_2fa(&app, &email).await;
+
+    // Case and the dash don't matter
+    let typed_code = codes[0].to_uppercase().replace('-', "");
+    assert_eq!(verify_2fa_with(&app, &email, &typed_code).await.status().as_u16(), 200);
+    assert_eq!(verify_2fa_with(&app, &email, &codes[0]).await.status().as_u16(), 401);
+    assert_eq!(verify_2fa_with(&app, &email, &codes[1]).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_unknown_recovery_code() {
+    let email = get_random_email();
+    signup_with_2fa(&app, &email).await;
+
+    assert_eq!(verify_2fa_with(&app, &email, "aaaaa-bbbbb").await.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_regenerate_recovery_codes() {
+    let email = get_random_email();
+    let old_codes = signup_with_2fa(&app, &email).await;
+    assert_eq!(verify_2fa_with(&app, &email, &old_codes[0]).await.status().as_u16(), 200);
+
+    let response = app.post_recovery_codes(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_codes = response.json::<RecoveryCodesResponse>().await.unwrap().recovery_codes;
+    assert_eq!(new_codes.len(), 10);
+
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "New recovery codes");
+
+    assert_eq!(verify_2fa_with(&app, &email, &old_codes[1]).await.status().as_u16(), 401);
+    assert_eq!(verify_2fa_with(&app, &email, &new_codes[0]).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_require_password_or_2fa_code_to_regenerate() {
+    let email = get_random_email();
+    let codes = signup_with_2fa(&app, &email).await;
+    assert_eq!(verify_2fa_with(&app, &email, &codes[0]).await.status().as_u16(), 200);
+
+    assert_eq!(app.post_recovery_codes(&json!({})).await.status().as_u16(), 400);
+    let response = app.post_recovery_codes(&json!({ "password": "wrong-password" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    // Used codes don't count
+    let response = app.post_recovery_codes(&json!({ "2FACode": codes[0] })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_recovery_codes(&json!({ "2FACode": codes[1] })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_409_if_2fa_not_enabled() {
+    signup_and_login(&app).await;
+
+    let response = app.post_recovery_codes(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_recovery_codes(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
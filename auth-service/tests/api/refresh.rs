@@ -0,0 +1,115 @@
+use auth_service::domain::data_stores::RefreshToken;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use reqwest::Url;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Signs up a user without 2FA, logs in and returns the refresh token cookie value
+async fn signup_and_login_with_refresh_token(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+
+    assert_eq!(refresh_cookie.path(), Some("/refresh"));
+    assert!(refresh_cookie.http_only());
+    refresh_cookie.value().to_owned()
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/refresh",
+            REFRESH_TOKEN_COOKIE_NAME, token
+        ),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_invalid_refresh_token() {
+    set_refresh_cookie(&app, "invalid");
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_return_200_and_rotate_refresh_token() {
+    let old_token = signup_and_login_with_refresh_token(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+    assert_ne!(refresh_cookie.value(), old_token);
+
+    // The old token stays on record as consumed so a replay can be detected
+    let old_token = RefreshToken::parse(old_token).unwrap();
+    let record = app.refresh_token_store.read().await.get_token(&old_token).await.unwrap();
+    assert!(record.used);
+
+    // The rotated token can be used in turn
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_revoke_token_family_if_old_token_is_reused() {
+    let old_token = signup_and_login_with_refresh_token(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    // Replaying the rotated token is rejected...
+    set_refresh_cookie(&app, &old_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // ...and takes down the token that replaced it
+    set_refresh_cookie(&app, &new_token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
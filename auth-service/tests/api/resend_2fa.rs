@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::utils::constants::TWO_FA_CODE_LIMITS;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+async fn signup_and_start_login(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let response = app.post_signup(&json!({ "email": email, "password": "password123", "requires2FA": true })).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    (email, login_attempt_id)
+}
+
+// Waits out the resend cooldown of the test app
+async fn wait_for_cooldown() {
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+}
+
+#[test_with_cleanup]
+async fn should_email_new_code() {
+    let (email, login_attempt_id) = signup_and_start_login(&app).await;
+    wait_for_cooldown().await;
+
+    let response = app.post_resend_2fa(&json!({ "email": email, "loginAttemptId": login_attempt_id })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": app.emails.last_2fa_code(&email) });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_429_if_resent_too_soon() {
+    let (email, login_attempt_id) = signup_and_start_login(&app).await;
+
+    let response = app.post_resend_2fa(&json!({ "email": email, "loginAttemptId": login_attempt_id })).await;
+    assert_eq!(response.status().as_u16(), 429);
+}
+
+#[test_with_cleanup]
+async fn should_return_429_once_resends_used_up() {
+    let (email, login_attempt_id) = signup_and_start_login(&app).await;
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id });
+
+    for _ in 0..TWO_FA_CODE_LIMITS.max_resends {
+        wait_for_cooldown().await;
+        assert_eq!(app.post_resend_2fa(&body).await.status().as_u16(), 200);
+    }
+    wait_for_cooldown().await;
+    assert_eq!(app.post_resend_2fa(&body).await.status().as_u16(), 429);
+
+    // The last code still works
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": app.emails.last_2fa_code(&email) });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_unknown_login_attempt() {
+    let (email, _) = signup_and_start_login(&app).await;
+    wait_for_cooldown().await;
+
+    let body = json!({ "email": email, "loginAttemptId": uuid::Uuid::new_v4().to_string() });
+    assert_eq!(app.post_resend_2fa(&body).await.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_invalid_input() {
+    let body = json!({ "email": "not-an-email", "loginAttemptId": uuid::Uuid::new_v4().to_string() });
+    assert_eq!(app.post_resend_2fa(&body).await.status().as_u16(), 400);
+
+    let body = json!({ "email": get_random_email(), "loginAttemptId": "not-a-uuid" });
+    assert_eq!(app.post_resend_2fa(&body).await.status().as_u16(), 400);
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use auth_service::utils::constants::JWT_SECRET;
+use auth_service_middleware::{AuthError, LocalValidator, RevocationCache, TokenValidator};
+
+use crate::helpers::{signup_and_login, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Revocations reach the cache asynchronously, so results are polled for a while
+async fn wait_for(validator: &LocalValidator, token: &str, expected: Result<(), AuthError>) {
+    for _ in 0..50 {
+        if validator.validate(token).await.map(|_| ()) == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("token validation never returned {:?}", expected);
+}
+
+async fn ready_validator(app: &TestApp) -> LocalValidator {
+    let (client_id, secret) = app.add_api_client().await;
+    let cache = RevocationCache::subscribe(&app.address, &client_id, &secret);
+    let validator = LocalValidator::with_secret(JWT_SECRET.as_bytes()).revocations(Arc::clone(&cache));
+
+    for _ in 0..50 {
+        if cache.is_ready().await {
+            return validator;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("revocation cache never became ready");
+}
+
+#[test_with_cleanup]
+async fn should_return_401_without_client_credentials() {
+    let response = app.http_client.get(format!("{}/revocations", app.address)).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_stream_snapshot_then_ready() {
+    let _token = signup_and_login(&app).await;
+    let response = app.logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let (client_id, secret) = app.add_api_client().await;
+
+    let mut response = app
+        .http_client
+        .get(format!("{}/revocations", app.address))
+        .basic_auth(&client_id, Some(&secret))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+
+    let mut body = String::new();
+    while !body.contains("event: ready") {
+        let chunk = response.chunk().await.unwrap().expect("stream ended before the snapshot");
+        body.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    assert!(body.contains("event: revoked"));
+}
+
+#[test_with_cleanup]
+async fn should_revoke_logged_out_tokens_in_local_validator() {
+    let token = signup_and_login(&app).await;
+    let validator = ready_validator(&app).await;
+    wait_for(&validator, &token, Ok(())).await;
+
+    let response = app.logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+    wait_for(&validator, &token, Err(AuthError::InvalidToken)).await;
+}
+
+#[test_with_cleanup]
+async fn should_revoke_earlier_tokens_of_logged_out_session() {
+    let earlier_token = signup_and_login(&app).await;
+    let validator = ready_validator(&app).await;
+    wait_for(&validator, &earlier_token, Ok(())).await;
+
+    // The session's current token is a newer one
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+    wait_for(&validator, &earlier_token, Err(AuthError::InvalidToken)).await;
+}
+
+#[test_with_cleanup]
+async fn should_revoke_every_session_on_logout_all() {
+    let token = signup_and_login(&app).await;
+    let validator = ready_validator(&app).await;
+    wait_for(&validator, &token, Ok(())).await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+    wait_for(&validator, &token, Err(AuthError::InvalidToken)).await;
+}
+
+#[test_with_cleanup]
+async fn should_bootstrap_with_earlier_revocations() {
+    let token = signup_and_login(&app).await;
+    let response = app.logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The logout happened before the cache existed: only the snapshot can tell
+    let validator = ready_validator(&app).await;
+    assert_eq!(validator.validate(&token).await, Err(AuthError::InvalidToken));
+}
@@ -0,0 +1,136 @@
+use auth_service::routes::SessionResponse;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, signup_and_login, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Logs in from a second device with its own cookie jar and returns that device's auth token
+async fn login_from_other_device(app: &TestApp, email: &str) -> String {
+    let http_client = reqwest::Client::builder()
+        .user_agent("other-device")
+        .build()
+        .unwrap();
+
+    let response = http_client
+        .post(format!("{}/login", app.address))
+        .json(&json!({ "email": email, "password": "password123" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    token
+}
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+    let signup_body = json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    random_email
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.delete_session("00000000-0000-0000-0000-000000000000").await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_invalid_token() {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}=invalid; HttpOnly; SameSite=Lax; Path=/", JWT_COOKIE_NAME),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_list_sessions_of_the_user() {
+    let email = signup(&app).await;
+    login_from_other_device(&app, &email).await;
+    signup_and_login(&app).await;
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to sessions");
+
+    // The session of the other user created in between is not listed
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].user_agent.as_deref(), Some("other-device"));
+    assert!(!sessions[0].current);
+    assert!(sessions[1].current);
+    for session in &sessions {
+        assert_eq!(session.ip_address.as_deref(), Some("127.0.0.1"));
+    }
+}
+
+#[test_with_cleanup]
+async fn should_revoke_session_immediately() {
+    let email = signup(&app).await;
+    let other_device_token = login_from_other_device(&app, &email).await;
+    let login_body = json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = app.get_sessions().await.json::<Vec<SessionResponse>>().await.unwrap();
+    let other_session = sessions.iter().find(|session| !session.current).unwrap();
+
+    let response = app.delete_session(&other_session.id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": other_device_token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let sessions = app.get_sessions().await.json::<Vec<SessionResponse>>().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    // Deleting it again finds nothing
+    let response = app.delete_session(&other_session.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[test_with_cleanup]
+async fn should_return_404_for_session_of_another_user() {
+    let email = signup(&app).await;
+    login_from_other_device(&app, &email).await;
+
+    let other_user_sessions = {
+        let login_body = json!({ "email": email, "password": "password123" });
+        app.post_login(&login_body).await;
+        app.get_sessions().await.json::<Vec<SessionResponse>>().await.unwrap()
+    };
+
+    signup_and_login(&app).await;
+
+    let response = app.delete_session(&other_user_sessions[0].id).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.delete_session("not-a-session-id").await;
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -21,8 +21,6 @@ async fn should_return_201_if_valid_input() {
 
 #[test_with_cleanup]
 async fn should_return_422_if_malformed_input() {
-    let random_email = get_random_email();
-
     // TODO: add more malformed input test cases
     let test_cases = [
         serde_json::json!({
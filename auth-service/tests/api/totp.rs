@@ -0,0 +1,187 @@
+use auth_service::domain::totp::{TotpSecret, TOTP_STEP_SECONDS};
+use auth_service::routes::{ConfirmTotpResponse, RecoveryCodesResponse, TotpEnrollmentResponse, TwoFactorAuthResponse};
+use chrono::Utc;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+fn current_step() -> i64 {
+    Utc::now().timestamp() / TOTP_STEP_SECONDS
+}
+
+async fn signup_and_login_as(app: &TestApp, email: &str) {
+    let body = json!({ "email": email, "password": "password123", "requires2FA": false });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&body).await.status().as_u16(), 200);
+}
+
+// Enrolls and confirms an authenticator app, returning the step the confirmation used up
+async fn enable_totp(app: &TestApp) -> (TotpSecret, i64) {
+    let response = app.post_enroll_totp(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let enrollment: TotpEnrollmentResponse = response.json().await.unwrap();
+    let secret = TotpSecret::parse(enrollment.secret).unwrap();
+
+    let step = current_step();
+    let response = app.post_confirm_totp(&json!({ "code": secret.code_at(step), "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    // 2FA was off until now, so recovery codes come with it
+    let confirmation: ConfirmTotpResponse = response.json().await.unwrap();
+    assert_eq!(confirmation.recovery_codes.map(|codes| codes.len()), Some(10));
+    (secret, step)
+}
+
+async fn login_with_2fa(app: &TestApp, email: &str) -> String {
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_enroll_totp(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[test_with_cleanup]
+async fn should_require_password_to_add_app() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+
+    assert_eq!(app.post_enroll_totp(&json!({})).await.status().as_u16(), 400);
+    let response = app.post_enroll_totp(&json!({ "password": "wrong-password" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    // Without 2FA on, there's no second factor to confirm with
+    let response = app.post_enroll_totp(&json!({ "2FACode": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_enroll_totp(&json!({ "password": "password123" })).await;
+    let enrollment: TotpEnrollmentResponse = response.json().await.unwrap();
+    let secret = TotpSecret::parse(enrollment.secret).unwrap();
+    let code = secret.code_at(current_step());
+    let response = app.post_confirm_totp(&json!({ "code": code, "password": "wrong-password" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Still no 2FA on login
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_accept_recovery_code_with_2fa_on() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+    let response = app.post_enable_2fa().await;
+    let recovery_codes = response.json::<RecoveryCodesResponse>().await.unwrap().recovery_codes;
+
+    let response = app.post_enroll_totp(&json!({ "2FACode": recovery_codes[0] })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let enrollment: TotpEnrollmentResponse = response.json().await.unwrap();
+    let secret = TotpSecret::parse(enrollment.secret).unwrap();
+
+    let body = json!({ "code": secret.code_at(current_step()), "2FACode": recovery_codes[1] });
+    let response = app.post_confirm_totp(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    // Users who had emailed codes keep their recovery codes
+    assert!(response.json::<ConfirmTotpResponse>().await.unwrap().recovery_codes.is_none());
+}
+
+#[test_with_cleanup]
+async fn should_email_user_when_app_is_added() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+    enable_totp(&app).await;
+
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "Authenticator app added");
+}
+
+#[test_with_cleanup]
+async fn should_return_secret_and_otpauth_uri() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+
+    let response = app.post_enroll_totp(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let enrollment: TotpEnrollmentResponse = response.json().await.unwrap();
+
+    let secret = TotpSecret::parse(enrollment.secret.clone()).unwrap();
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/auth-service:"));
+    assert!(enrollment.otpauth_uri.contains(&format!("secret={}", secret.encode())));
+}
+
+#[test_with_cleanup]
+async fn should_not_enable_totp_without_correct_code() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+
+    let response = app.post_confirm_totp(&json!({ "code": "123456", "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.post_enroll_totp(&json!({ "password": "password123" })).await;
+    let enrollment: TotpEnrollmentResponse = response.json().await.unwrap();
+    let secret = TotpSecret::parse(enrollment.secret).unwrap();
+
+    let response = app.post_confirm_totp(&json!({ "code": "12ab56", "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.post_confirm_totp(&json!({ "code": secret.code_at(current_step() - 5), "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Still no 2FA on login
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_409_if_totp_already_enabled() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+    enable_totp(&app).await;
+
+    let response = app.post_enroll_totp(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[test_with_cleanup]
+async fn should_login_with_totp_code() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+    let (secret, step) = enable_totp(&app).await;
+
+    let login_attempt_id = login_with_2fa(&app, &email).await;
+
+    // The confirmation used up its step; the next one is within the skew window
+    let body = json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": secret.code_at(step + 1),
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_reject_reused_totp_code() {
+    let email = get_random_email();
+    signup_and_login_as(&app, &email).await;
+    let (secret, step) = enable_totp(&app).await;
+
+    let login_attempt_id = login_with_2fa(&app, &email).await;
+    let body = json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": secret.code_at(step),
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let code = secret.code_at(step + 1);
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": code });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+
+    let login_attempt_id = login_with_2fa(&app, &email).await;
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": code });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 401);
+}
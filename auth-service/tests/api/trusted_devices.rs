@@ -0,0 +1,123 @@
+use auth_service::routes::{TrustedDeviceResponse, TwoFactorAuthResponse};
+use auth_service::utils::constants::TRUSTED_DEVICE_COOKIE_NAME;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+async fn signup_with_2fa(app: &TestApp) -> String {
+    let email = get_random_email();
+    let response = app.post_signup(&json!({ "email": email, "password": "password123", "requires2FA": true })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    email
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({ "email": email, "password": "password123" })).await
+}
+
+// Logs in with 2FA, optionally remembering the browser
+async fn login_with_2fa(app: &TestApp, email: &str, remember_device: bool) -> reqwest::Response {
+    let response = login(app, email).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+
+    let body = json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": app.emails.last_2fa_code(email),
+        "rememberDevice": remember_device,
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+}
+
+async fn get_trusted_devices(app: &TestApp) -> Vec<TrustedDeviceResponse> {
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+#[test_with_cleanup]
+async fn should_skip_2fa_on_remembered_device() {
+    let email = signup_with_2fa(&app).await;
+    let response = login_with_2fa(&app, &email, true).await;
+    let cookie = response.cookies().find(|cookie| cookie.name() == TRUSTED_DEVICE_COOKIE_NAME).expect("No trusted device cookie");
+    assert!(cookie.http_only());
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    assert_eq!(login(&app, &email).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_ask_for_2fa_if_device_not_remembered() {
+    let email = signup_with_2fa(&app).await;
+    let response = login_with_2fa(&app, &email, false).await;
+    assert!(response.cookies().all(|cookie| cookie.name() != TRUSTED_DEVICE_COOKIE_NAME));
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    assert_eq!(login(&app, &email).await.status().as_u16(), 206);
+}
+
+#[test_with_cleanup]
+async fn should_not_trust_device_for_another_user() {
+    let email = signup_with_2fa(&app).await;
+    login_with_2fa(&app, &email, true).await;
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let other_email = signup_with_2fa(&app).await;
+    assert_eq!(login(&app, &other_email).await.status().as_u16(), 206);
+}
+
+#[test_with_cleanup]
+async fn should_ask_for_2fa_if_device_cookie_forged() {
+    let email = signup_with_2fa(&app).await;
+    login_with_2fa(&app, &email, true).await;
+    let device_id = get_trusted_devices(&app).await.remove(0).id;
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let url = Url::parse(&app.address).expect("Failed to parse URL");
+    for value in [device_id.clone(), format!("{}.c2lnbmF0dXJl", device_id)] {
+        app.cookie_jar.add_cookie_str(&format!("{}={}; HttpOnly; SameSite=Lax; Path=/login", TRUSTED_DEVICE_COOKIE_NAME, value), &url);
+        assert_eq!(login(&app, &email).await.status().as_u16(), 206);
+    }
+}
+
+#[test_with_cleanup]
+async fn should_list_and_revoke_trusted_devices() {
+    let email = signup_with_2fa(&app).await;
+    login_with_2fa(&app, &email, true).await;
+
+    let devices = get_trusted_devices(&app).await;
+    assert_eq!(devices.len(), 1);
+
+    let response = app.delete_trusted_device(&devices[0].id).await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(get_trusted_devices(&app).await.is_empty());
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    assert_eq!(login(&app, &email).await.status().as_u16(), 206);
+}
+
+#[test_with_cleanup]
+async fn should_return_404_for_device_of_another_user() {
+    let email = signup_with_2fa(&app).await;
+    login_with_2fa(&app, &email, true).await;
+    let device_id = get_trusted_devices(&app).await.remove(0).id;
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let other_email = signup_with_2fa(&app).await;
+    login_with_2fa(&app, &other_email, false).await;
+    assert_eq!(app.delete_trusted_device(&device_id).await.status().as_u16(), 404);
+    assert_eq!(app.delete_trusted_device("not-a-device").await.status().as_u16(), 404);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    assert_eq!(app.get_trusted_devices().await.status().as_u16(), 400);
+
+    let response = app.delete_trusted_device(&uuid::Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
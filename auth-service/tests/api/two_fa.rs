@@ -0,0 +1,112 @@
+use auth_service::routes::{RecoveryCodesResponse, TwoFactorAuthResponse};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, signup_and_login, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({ "email": email, "password": "password123" })).await
+}
+
+// Signs up without 2FA and turns it on, returning the email and the recovery codes
+async fn signup_and_enable_2fa(app: &TestApp) -> (String, Vec<String>) {
+    let email = get_random_email();
+    let response = app.post_signup(&json!({ "email": email, "password": "password123", "requires2FA": false })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(login(app, &email).await.status().as_u16(), 200);
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: RecoveryCodesResponse = response.json().await.unwrap();
+    (email, body.recovery_codes)
+}
+
+#[test_with_cleanup]
+async fn should_enable_2fa() {
+    let (email, recovery_codes) = signup_and_enable_2fa(&app).await;
+    assert_eq!(recovery_codes.len(), 10);
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "2FA enabled");
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": app.emails.last_2fa_code(&email) });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_409_if_2fa_already_enabled() {
+    signup_and_enable_2fa(&app).await;
+
+    let response = app.post_enable_2fa().await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[test_with_cleanup]
+async fn should_disable_2fa_with_password() {
+    let (email, _) = signup_and_enable_2fa(&app).await;
+
+    let response = app.post_disable_2fa(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "2FA disabled");
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    assert_eq!(login(&app, &email).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_disable_2fa_with_recovery_code() {
+    let (email, recovery_codes) = signup_and_enable_2fa(&app).await;
+
+    let response = app.post_disable_2fa(&json!({ "2FACode": recovery_codes[0] })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+    assert_eq!(login(&app, &email).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_confirmation_is_incorrect() {
+    signup_and_enable_2fa(&app).await;
+
+    let test_cases = [
+        json!({ "password": "wrong-password" }),
+        json!({ "2FACode": "123456" }),
+        json!({ "2FACode": "AAAAA-AAAAA" }),
+    ];
+    for test_case in test_cases {
+        let response = app.post_disable_2fa(&test_case).await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for input: {:?}", test_case);
+    }
+}
+
+#[test_with_cleanup]
+async fn should_return_400_without_a_single_confirmation() {
+    signup_and_enable_2fa(&app).await;
+
+    let test_cases = [
+        json!({}),
+        json!({ "password": "password123", "2FACode": "123456" }),
+        json!({ "2FACode": "not-a-code" }),
+    ];
+    for test_case in test_cases {
+        let response = app.post_disable_2fa(&test_case).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", test_case);
+    }
+}
+
+#[test_with_cleanup]
+async fn should_return_409_if_2fa_not_enabled() {
+    signup_and_login(&app).await;
+
+    let response = app.post_disable_2fa(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    assert_eq!(app.post_enable_2fa().await.status().as_u16(), 400);
+
+    let response = app.post_disable_2fa(&json!({ "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
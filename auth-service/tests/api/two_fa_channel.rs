@@ -0,0 +1,144 @@
+use auth_service::routes::{PhoneVerificationResponse, TwoFaChannelResponse, TwoFactorAuthResponse};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, signup_and_login, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+async fn signup_with_2fa_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+    let response = app.post_signup(&json!({ "email": email, "password": "password123", "requires2FA": true })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let login_attempt_id = start_login(app, &email).await;
+
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": app.emails.last_2fa_code(&email) });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+    email
+}
+
+async fn start_login(app: &TestApp, email: &str) -> String {
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id
+}
+
+// Asks for codes by SMS and returns the code texted to the number
+async fn request_sms(app: &TestApp, phone_number: &str) -> String {
+    let body = json!({ "channel": "sms", "phoneNumber": phone_number, "password": "password123" });
+    let response = app.post_two_fa_channel(&body).await;
+    assert_eq!(response.status().as_u16(), 202);
+    response.json::<PhoneVerificationResponse>().await.unwrap();
+    let normalized: String = phone_number.chars().filter(|c| *c == '+' || c.is_ascii_digit()).collect();
+    app.sms.last_2fa_code(&normalized).expect("No SMS sent")
+}
+
+#[test_with_cleanup]
+async fn should_send_2fa_codes_by_sms() {
+    let email = signup_with_2fa_and_login(&app).await;
+
+    let code = request_sms(&app, "+1 (415) 555-0123").await;
+    let response = app.post_confirm_two_fa_channel(&json!({ "code": code })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let channel = response.json::<TwoFaChannelResponse>().await.unwrap();
+    assert_eq!(channel.channel, "sms");
+    assert_eq!(channel.phone_number.as_deref(), Some("+14155550123"));
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "2FA channel changed");
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let login_attempt_id = start_login(&app, &email).await;
+    let code = app.sms.last_2fa_code("+14155550123").expect("No SMS sent");
+    // Nothing new was emailed
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "2FA channel changed");
+
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": code });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_keep_emailing_codes_until_number_is_confirmed() {
+    let email = signup_with_2fa_and_login(&app).await;
+    request_sms(&app, "+14155550123").await;
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let login_attempt_id = start_login(&app, &email).await;
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": app.emails.last_2fa_code(&email) });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_drop_pending_number_after_wrong_code() {
+    signup_with_2fa_and_login(&app).await;
+    let code = request_sms(&app, "+14155550123").await;
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    let response = app.post_confirm_two_fa_channel(&json!({ "code": wrong_code })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_confirm_two_fa_channel(&json!({ "code": code })).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[test_with_cleanup]
+async fn should_require_password_or_2fa_code() {
+    let email = signup_with_2fa_and_login(&app).await;
+
+    let body = json!({ "channel": "sms", "phoneNumber": "+14155550123" });
+    assert_eq!(app.post_two_fa_channel(&body).await.status().as_u16(), 400);
+    let body = json!({ "channel": "sms", "phoneNumber": "+14155550123", "password": "wrong-password" });
+    assert_eq!(app.post_two_fa_channel(&body).await.status().as_u16(), 401);
+    let body = json!({ "channel": "email", "2FACode": "aaaaa-bbbbb" });
+    assert_eq!(app.post_two_fa_channel(&body).await.status().as_u16(), 401);
+    assert!(app.sms.last_2fa_code("+14155550123").is_none());
+
+    let response = app.post_two_fa_channel(&json!({ "channel": "email", "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.emails.last_email_to(&email).unwrap().subject, "2FA channel changed");
+}
+
+#[test_with_cleanup]
+async fn should_switch_back_to_email() {
+    let email = signup_with_2fa_and_login(&app).await;
+    let code = request_sms(&app, "+14155550123").await;
+    assert_eq!(app.post_confirm_two_fa_channel(&json!({ "code": code })).await.status().as_u16(), 200);
+
+    let response = app.post_two_fa_channel(&json!({ "channel": "email", "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.json::<TwoFaChannelResponse>().await.unwrap().phone_number, None);
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let sms_code = app.sms.last_2fa_code("+14155550123");
+    let login_attempt_id = start_login(&app, &email).await;
+    // Nothing new was texted
+    assert_eq!(app.sms.last_2fa_code("+14155550123"), sms_code);
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": app.emails.last_2fa_code(&email) });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_for_invalid_input() {
+    signup_and_login(&app).await;
+
+    let test_cases = [
+        json!({ "channel": "sms", "password": "password123" }),
+        json!({ "channel": "sms", "phoneNumber": "4155550123", "password": "password123" }),
+        json!({ "channel": "sms", "phoneNumber": "+1415", "password": "password123" }),
+        json!({ "channel": "email", "phoneNumber": "+14155550123", "password": "password123" }),
+        json!({ "channel": "carrier-pigeon", "password": "password123" }),
+    ];
+    for test_case in test_cases {
+        let response = app.post_two_fa_channel(&test_case).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", test_case);
+    }
+
+    let response = app.post_confirm_two_fa_channel(&json!({ "code": "12ab56" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    // No number waiting to be confirmed
+    let response = app.post_confirm_two_fa_channel(&json!({ "code": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_two_fa_channel(&json!({ "channel": "email", "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.post_confirm_two_fa_channel(&json!({ "code": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
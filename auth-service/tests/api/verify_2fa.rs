@@ -1,7 +1,7 @@
 use auth_service::domain::data_stores::LoginAttemptId;
 use auth_service::domain::email::Email;
 use auth_service::routes::TwoFactorAuthResponse;
-use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, LOGIN_ATTEMPT_COOKIE_NAME, LOGIN_ATTEMPT_COOKIE_PATH, TWO_FA_CODE_LIMITS};
 use crate::helpers::{get_random_email, TestApp};
 use auth_service_macros::test_with_cleanup;
 
@@ -78,10 +78,9 @@ async fn should_return_401_if_incorrect_credentials() {
 }
 
 #[test_with_cleanup]
-async fn should_return_401_if_old_code() {
-    // Call login twice. Then, attempt to call verify-fa with the 2FA code from the first login requet. This should fail.
+async fn should_return_200_for_each_concurrent_login_attempt() {
+    // Log in twice, e.g. from a laptop and a phone. Both login attempts can be finished.
     let random_email = get_random_email();
-    let mail = Email::parse(random_email.clone()).unwrap();
 
     let signup_body = serde_json::json!({
         "email": random_email,
@@ -92,30 +91,132 @@ async fn should_return_401_if_old_code() {
     let response = app.post_signup(&signup_body).await;
 
     assert_eq!(response.status().as_u16(), 201);
-    let login_1_body = serde_json::json!({
+
+    let laptop = new_browser();
+    let phone = new_browser();
+    let (attempt_1, code_1) = login_from(&app, &laptop, &random_email).await;
+    let (attempt_2, code_2) = login_from(&app, &phone, &random_email).await;
+
+    // Codes only work for their own login attempt
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": attempt_2,
+        "2FACode": code_1
+    });
+    assert_eq!(post_from(&app, &phone, "/verify-2fa", &verify_2fa_body).await.status().as_u16(), 401);
+
+    for (browser, attempt, code) in [(&laptop, attempt_1, code_1), (&phone, attempt_2, code_2)] {
+        let verify_2fa_body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": attempt,
+            "2FACode": code
+        });
+        assert_eq!(post_from(&app, browser, "/verify-2fa", &verify_2fa_body).await.status().as_u16(), 200);
+    }
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_login_attempt_dropped_over_the_cap() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
-        "2FACode": "123"
+        "requires2FA": true
     });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
 
-    let response = app.post_login(&login_1_body).await;
-    assert_eq!(response.status().as_u16(), 206);
+    let mut attempts = Vec::new();
+    for _ in 0..=TWO_FA_CODE_LIMITS.max_pending_attempts {
+        let browser = new_browser();
+        let (login_attempt_id, code) = login_from(&app, &browser, &random_email).await;
+        attempts.push((browser, login_attempt_id, code));
+    }
 
-    let result_1 = app.two_fa_code_store.read().await.get_code(&mail).await.unwrap();
-    let attempt_1 = result_1.0;
-    let code_1 = result_1.1;
-    // login again
-    let response = app.post_login(&login_1_body).await;
+    // The oldest login attempt made room for the newest
+    let (oldest_browser, oldest_attempt, oldest_code) = attempts.first().unwrap();
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": oldest_attempt,
+        "2FACode": oldest_code
+    });
+    assert_eq!(post_from(&app, oldest_browser, "/verify-2fa", &verify_2fa_body).await.status().as_u16(), 401);
 
+    let (newest_browser, newest_attempt, newest_code) = attempts.last().unwrap();
     let verify_2fa_body = serde_json::json!({
         "email": random_email,
-        "loginAttemptId": attempt_1.as_ref(),
-        "2FACode": code_1.as_ref()
+        "loginAttemptId": newest_attempt,
+        "2FACode": newest_code
     });
+    assert_eq!(post_from(&app, newest_browser, "/verify-2fa", &verify_2fa_body).await.status().as_u16(), 200);
+}
 
-    let response = app.post_verify_2fa(&verify_2fa_body).await;
+#[test_with_cleanup]
+async fn should_return_401_from_another_browser() {
+    // Someone who phished the code can't finish the login from their own browser
+    let random_email = get_random_email();
 
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == LOGIN_ATTEMPT_COOKIE_NAME)
+        .expect("No login attempt cookie found");
+    assert!(login_attempt_cookie.http_only());
+    assert_eq!(login_attempt_cookie.path(), Some(LOGIN_ATTEMPT_COOKIE_PATH));
+
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": app.emails.last_2fa_code(&random_email)
+    });
+    let response = post_from(&app, &new_browser(), "/verify-2fa", &verify_2fa_body).await;
     assert_eq!(response.status().as_u16(), 401);
+
+    // The failed try didn't use the login attempt up for the user's own browser
+    assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status().as_u16(), 200);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_login_attempt_of_another_user() {
+    let random_email = get_random_email();
+    let other_email = get_random_email();
+
+    for email in [&random_email, &other_email] {
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true
+        });
+        assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+    }
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+
+    let verify_2fa_body = serde_json::json!({
+        "email": other_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": app.emails.last_2fa_code(&random_email)
+    });
+    assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status().as_u16(), 401);
 }
 
 #[test_with_cleanup]
@@ -149,13 +250,15 @@ async fn should_return_200_if_correct_code() {
 
     assert_eq!(json_body.message, "2FA required".to_owned());
 
-    let result = app.two_fa_code_store.read().await.get_code(&mail).await.unwrap();
-    assert_eq!(result.0.as_ref(), json_body.login_attempt_id);
+    let login_attempt_id = LoginAttemptId::parse(json_body.login_attempt_id).unwrap();
+    let result = app.two_fa_code_store.read().await.get_code(&login_attempt_id).await.unwrap();
+    assert_eq!(result.0, mail);
+    let code = app.emails.last_2fa_code(&random_email);
 
     let verify_2fa_body = serde_json::json!({
         "email": random_email,
-        "loginAttemptId": result.0.as_ref(),
-        "2FACode": result.1.as_ref()
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code
     });
 
     let response = app.post_verify_2fa(&verify_2fa_body).await;
@@ -201,13 +304,15 @@ async fn should_return_401_if_same_code_twice() {
 
     assert_eq!(json_body.message, "2FA required".to_owned());
 
-    let result = app.two_fa_code_store.read().await.get_code(&mail).await.unwrap();
-    assert_eq!(result.0.as_ref(), json_body.login_attempt_id);
+    let login_attempt_id = LoginAttemptId::parse(json_body.login_attempt_id).unwrap();
+    let result = app.two_fa_code_store.read().await.get_code(&login_attempt_id).await.unwrap();
+    assert_eq!(result.0, mail);
+    let code = app.emails.last_2fa_code(&random_email);
 
     let verify_2fa_body = serde_json::json!({
         "email": random_email,
-        "loginAttemptId": result.0.as_ref(),
-        "2FACode": result.1.as_ref()
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code
     });
 
     let response = app.post_verify_2fa(&verify_2fa_body).await;
@@ -223,11 +328,86 @@ async fn should_return_401_if_same_code_twice() {
 
     let verify_2fa_body = serde_json::json!({
         "email": random_email,
-        "loginAttemptId": result.0.as_ref(),
-        "2FACode": result.1.as_ref()
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code
     });
 
     let response = app.post_verify_2fa(&verify_2fa_body).await;
 
     assert_eq!(response.status().as_u16(), 401);
-}
\ No newline at end of file
+}
+#[test_with_cleanup]
+async fn should_return_429_once_attempts_used_up() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    let code = app.emails.last_2fa_code(&random_email);
+    let wrong_code = if code == "000000" { "000001" } else { "000000" };
+
+    for _ in 0..TWO_FA_CODE_LIMITS.max_failed_attempts {
+        let verify_2fa_body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": wrong_code
+        });
+        assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status().as_u16(), 401);
+    }
+
+    // Even the right code is refused now
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code
+    });
+    assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status().as_u16(), 429);
+    assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status().as_u16(), 401);
+
+    // Logging in again starts a fresh attempt
+    let response = app.post_login(&login_body).await;
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": app.emails.last_2fa_code(&random_email)
+    });
+    assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status().as_u16(), 200);
+}
+
+// A browser of its own, next to the one of `app`
+fn new_browser() -> reqwest::Client {
+    reqwest::Client::builder().cookie_store(true).build().unwrap()
+}
+
+async fn post_from(app: &TestApp, browser: &reqwest::Client, path: &str, body: &serde_json::Value) -> reqwest::Response {
+    browser
+        .post(format!("{}{}", app.address, path))
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+// Starts a login from the browser, returning its login attempt id and the code that was sent
+async fn login_from(app: &TestApp, browser: &reqwest::Client, email: &str) -> (String, String) {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = post_from(app, browser, "/login", &login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id;
+    (login_attempt_id, app.emails.last_2fa_code(email))
+}
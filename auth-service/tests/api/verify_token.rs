@@ -1,4 +1,3 @@
-use reqwest::cookie::CookieStore;
 use serde_json::json;
 
 use auth_service::{utils::constants::JWT_COOKIE_NAME};
@@ -105,4 +104,44 @@ async fn should_return_401_if_banned_token() {
 
     let response = app.post_verify_token(&json!({"token": token})).await;
     assert_eq!(response.status().as_u16(), 401);
-}
\ No newline at end of file
+}
+#[test_with_cleanup]
+async fn should_accept_tokens_signed_before_key_rotation_until_key_is_retired() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let old_kid = app.key_ring.read().await.active_kid().unwrap().to_owned();
+    app.key_ring.write().await.rotate().await.unwrap();
+
+    // The demoted key still verifies the tokens it signed...
+    let response = app.post_verify_token(&json!({"token": token})).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // ...until it is retired
+    app.key_ring.write().await.retire(&old_kid).await.unwrap();
+    let response = app.post_verify_token(&json!({"token": token})).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
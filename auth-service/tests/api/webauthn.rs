@@ -0,0 +1,277 @@
+use auth_service::domain::data_stores::LoginAttemptId;
+use auth_service::routes::TwoFactorAuthResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID};
+use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ciborium::value::Value;
+use serde_json::{json, Value as Json};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{get_random_email, signup_and_login, TestApp};
+use auth_service_macros::test_with_cleanup;
+
+// Stands in for a platform authenticator: one ES256 passkey, answering the options the
+// browser would pass to `navigator.credentials`
+struct SoftAuthenticator {
+    key_pair: EcdsaKeyPair,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    user_verified: bool,
+    origin: String,
+}
+
+impl SoftAuthenticator {
+    fn new() -> Self {
+        Self {
+            key_pair: EcdsaKeyPair::generate(&ECDSA_P256_SHA256_ASN1_SIGNING).unwrap(),
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            sign_count: 0,
+            user_verified: true,
+            origin: WEBAUTHN_ORIGIN.to_owned(),
+        }
+    }
+
+    fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn create(&self, options: &Json) -> Json {
+        let client_data_json = self.client_data("webauthn.create", options);
+
+        let public_key = self.key_pair.public_key().as_ref();
+        let cose_key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer((-7).into())),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (Value::Integer((-2).into()), Value::Bytes(public_key[1..33].to_vec())),
+            (Value::Integer((-3).into()), Value::Bytes(public_key[33..].to_vec())),
+        ]);
+        let mut authenticator_data = self.authenticator_data(0x40);
+        authenticator_data.extend_from_slice(&[0; 16]);
+        authenticator_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        authenticator_data.extend_from_slice(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut authenticator_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            (Value::Text("fmt".to_owned()), Value::Text("none".to_owned())),
+            (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+            (Value::Text("authData".to_owned()), Value::Bytes(authenticator_data)),
+        ]);
+        let mut attestation_object = vec![];
+        ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        json!({
+            "id": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+            }
+        })
+    }
+
+    fn get(&mut self, options: &Json) -> Json {
+        self.sign_count += 1;
+        let client_data_json = self.client_data("webauthn.get", options);
+        let authenticator_data = self.authenticator_data(0);
+
+        let mut signed = authenticator_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature = self.key_pair.sign(&SystemRandom::new(), &signed).unwrap();
+
+        json!({
+            "id": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(authenticator_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            }
+        })
+    }
+
+    fn client_data(&self, ceremony_type: &str, options: &Json) -> Vec<u8> {
+        json!({
+            "type": ceremony_type,
+            "challenge": options["publicKey"]["challenge"],
+            "origin": self.origin,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn authenticator_data(&self, extra_flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        let user_verified = if self.user_verified { 0x04 } else { 0 };
+        data.push(0x01 | user_verified | extra_flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+}
+
+async fn register_passkey(app: &TestApp, authenticator: &SoftAuthenticator) -> reqwest::Response {
+    let response = app.post_passkey_registration_options().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let options: Json = response.json().await.unwrap();
+
+    app.post_passkey(&authenticator.create(&options)).await
+}
+
+async fn passkey_login(app: &TestApp, authenticator: &mut SoftAuthenticator, body: &Json) -> (Json, reqwest::Response) {
+    let response = app.post_passkey_login_options(body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let options: Json = response.json().await.unwrap();
+
+    let assertion = authenticator.get(&options);
+    let response = app.post_passkey_login(&assertion).await;
+    (assertion, response)
+}
+
+async fn signup_with_2fa_and_login(app: &TestApp) -> String {
+    let email = get_random_email();
+    let response = app.post_signup(&json!({ "email": email, "password": "password123", "requires2FA": true })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let login_attempt_id = start_login(app, &email).await;
+
+    let code = app.emails.last_2fa_code(&email);
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id, "2FACode": code });
+    assert_eq!(app.post_verify_2fa(&body).await.status().as_u16(), 200);
+    email
+}
+
+async fn start_login(app: &TestApp, email: &str) -> String {
+    let response = app.post_login(&json!({ "email": email, "password": "password123" })).await;
+    assert_eq!(response.status().as_u16(), 206);
+    response.json::<TwoFactorAuthResponse>().await.unwrap().login_attempt_id
+}
+
+#[test_with_cleanup]
+async fn should_register_passkey_and_login_without_password() {
+    signup_and_login(&app).await;
+    let mut authenticator = SoftAuthenticator::new();
+
+    let response = register_passkey(&app, &authenticator).await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(response.json::<Json>().await.unwrap()["id"], authenticator.credential_id());
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let (_, response) = passkey_login(&app, &mut authenticator, &json!({})).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+}
+
+#[test_with_cleanup]
+async fn should_accept_passkey_as_second_factor() {
+    let email = signup_with_2fa_and_login(&app).await;
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status().as_u16(), 201);
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let login_attempt_id = start_login(&app, &email).await;
+    // A password is enough of a first factor, the user doesn't need to be verified again
+    authenticator.user_verified = false;
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id });
+    let (_, response) = passkey_login(&app, &mut authenticator, &body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    // The login attempt is finished
+    let login_attempt_id = LoginAttemptId::parse(login_attempt_id).unwrap();
+    let pending = app.two_fa_code_store.read().await.get_code(&login_attempt_id).await;
+    assert!(pending.is_err());
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_passkey_of_another_user_as_second_factor() {
+    let mut authenticator = SoftAuthenticator::new();
+    signup_and_login(&app).await;
+    assert_eq!(register_passkey(&app, &authenticator).await.status().as_u16(), 201);
+
+    let email = signup_with_2fa_and_login(&app).await;
+    assert_eq!(register_passkey(&app, &SoftAuthenticator::new()).await.status().as_u16(), 201);
+
+    let login_attempt_id = start_login(&app, &email).await;
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id });
+    let (_, response) = passkey_login(&app, &mut authenticator, &body).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_return_404_if_no_passkey_for_second_factor() {
+    let email = signup_with_2fa_and_login(&app).await;
+    let login_attempt_id = start_login(&app, &email).await;
+
+    let body = json!({ "email": email, "loginAttemptId": login_attempt_id });
+    let response = app.post_passkey_login_options(&body).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_replayed_assertion() {
+    signup_and_login(&app).await;
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status().as_u16(), 201);
+
+    let (assertion, response) = passkey_login(&app, &mut authenticator, &json!({})).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_passkey_login(&assertion).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_other_origin() {
+    signup_and_login(&app).await;
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status().as_u16(), 201);
+
+    // A phishing site can't get a usable assertion
+    authenticator.origin = "https://auth-service.example".to_owned();
+    let (_, response) = passkey_login(&app, &mut authenticator, &json!({})).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_for_passwordless_login_without_user_verification() {
+    signup_and_login(&app).await;
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status().as_u16(), 201);
+
+    authenticator.user_verified = false;
+    let (_, response) = passkey_login(&app, &mut authenticator, &json!({})).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_return_401_if_sign_count_goes_back() {
+    signup_and_login(&app).await;
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status().as_u16(), 201);
+
+    authenticator.sign_count = 10;
+    let (_, response) = passkey_login(&app, &mut authenticator, &json!({})).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Looks like a clone of the authenticator
+    authenticator.sign_count = 5;
+    let (_, response) = passkey_login(&app, &mut authenticator, &json!({})).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[test_with_cleanup]
+async fn should_return_409_if_passkey_already_registered() {
+    signup_and_login(&app).await;
+    let authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status().as_u16(), 201);
+
+    let response = register_passkey(&app, &authenticator).await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[test_with_cleanup]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_passkey_registration_options().await;
+    assert_eq!(response.status().as_u16(), 400);
+}